@@ -0,0 +1,69 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+use cli_weather::api::{Client, ForecastResponse, Location, WeatherResponse};
+use cli_weather::cache::Cache;
+use cli_weather::display::{self, IconSet, ReportOptions};
+use cli_weather::middleware::Response;
+use cli_weather::template::Template;
+
+const WEATHER: &str = include_str!("../tests/fixtures/weather.json");
+const FORECAST: &str = include_str!("../tests/fixtures/forecast.json");
+
+fn parsing(c: &mut Criterion) {
+    c.bench_function("parse current weather", |b| {
+        b.iter(|| serde_json::from_str::<WeatherResponse>(black_box(WEATHER)).unwrap())
+    });
+    c.bench_function("parse 5 day forecast", |b| {
+        b.iter(|| serde_json::from_str::<ForecastResponse>(black_box(FORECAST)).unwrap())
+    });
+}
+
+fn rendering(c: &mut Criterion) {
+    let weather: WeatherResponse = serde_json::from_str(WEATHER).unwrap();
+
+    c.bench_function("render current weather", |b| {
+        b.iter(|| display::render_weather_info(black_box(&weather), &ReportOptions::default()))
+    });
+}
+
+fn paris() -> Location {
+    Location::Place {
+        city: String::from("Paris"),
+        country_code: String::from("FR"),
+    }
+}
+
+fn cache_lookup(c: &mut Criterion) {
+    let cache = Cache::new(std::env::temp_dir().join("cli_weather_bench"));
+    let request = Client::new("key").weather_request(&paris());
+    cache.put(&request, &Response::ok(WEATHER), 0).unwrap();
+
+    c.bench_function("cache hit", |b| {
+        b.iter(|| cache.get(black_box(&request), 60).unwrap())
+    });
+}
+
+/// What a status bar pays on every refresh: a cache hit, decoding it and one
+/// template render.
+fn status_line(c: &mut Criterion) {
+    let cache = Cache::new(std::env::temp_dir().join("cli_weather_bench"));
+    let request = Client::new("key").weather_request(&paris());
+    cache.put(&request, &Response::ok(WEATHER), 0).unwrap();
+
+    c.bench_function("status line", |b| {
+        b.iter(|| {
+            let template = Template::parse(
+                black_box("{temp}{icon} {city}"),
+                display::STATUS_PLACEHOLDERS,
+            )
+            .unwrap();
+            let response = cache.get(&request, 60).unwrap();
+            let weather: WeatherResponse = serde_json::from_str(&response.body).unwrap();
+            display::render_status_line(&template, &weather, IconSet::NerdFont)
+        })
+    });
+}
+
+criterion_group!(benches, parsing, rendering, cache_lookup, status_line);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::coords;
+
+// `--coords` is typed by hand or pasted from a map app; whatever parses must
+// be a real place on the globe.
+fuzz_target!(|input: &str| {
+    if let Ok(coord) = coords::parse(input) {
+        assert!(coord.lat.abs() <= 90.0 && coord.lon.abs() <= 180.0);
+        coords::parse(&coords::geo_uri(&coord)).unwrap();
+    }
+});
@@ -0,0 +1,66 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::api::WeatherResponse;
+use cli_weather::config::{Comfort, Thresholds};
+use cli_weather::display::{self, IconSet, Part};
+use cli_weather::energy;
+use cli_weather::notify;
+use cli_weather::template::Template;
+use cli_weather::units::{self, Clock, SpeedUnit, TempUnit};
+use cli_weather::uv;
+
+// Whatever the API sends back, decoding and every renderer must not panic.
+fuzz_target!(|data: &[u8]| {
+    let Ok(weather) = serde_json::from_slice::<WeatherResponse>(data) else {
+        return;
+    };
+
+    units::init(units::Preferences {
+        temperature: TempUnit::Fahrenheit,
+        wind: SpeedUnit::Knots,
+        clock: Clock::TwelveHour,
+    });
+    display::render_weather_info(
+        &weather,
+        &display::ReportOptions {
+            geo_uri: true,
+            art: true,
+            // Any numbers will do to exercise the UV line.
+            uv: Part::Shown(uv::Summary {
+                now: weather.main.temp,
+                today_max: Some(weather.main.feels_like),
+            }),
+            comfort: Some(Comfort::default()),
+            advice: true,
+            rain_chance: Part::Shown(weather.main.humidity / 100.0),
+            energy: Part::Shown(energy::Window {
+                start: i64::from(weather.timezone),
+                end: i64::from(weather.timezone) + 7200,
+                intensity: weather.main.temp,
+                renewable: Some(weather.main.feels_like),
+                breezy: true,
+                sunny: true,
+            }),
+            diff: true,
+            since: Some(display::Reading {
+                at: i64::from(weather.timezone),
+                temp: weather.main.feels_like,
+                pressure: weather.main.humidity,
+                humidity: weather.main.pressure,
+                wind: weather.wind.gust.unwrap_or_default(),
+            }),
+        },
+    );
+
+    let template = Template::parse(
+        "{city} {description} {temp} {feels_like} {humidity} {pressure} {wind} {wind_dir} {gust} {beaufort} {icon} {emoji}",
+        display::STATUS_PLACEHOLDERS,
+    )
+    .unwrap();
+    display::render_status_line(&template, &weather, IconSet::Emoji);
+    display::render_status_line(&template, &weather, IconSet::NerdFont);
+
+    notify::evaluate(&weather, None, &[], &Thresholds::default(), 0);
+});
@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::api::{ForecastResponse, WeatherResponse};
+use cli_weather::config::Thresholds;
+use cli_weather::notify;
+
+const WEATHER: &str = include_str!("../../tests/fixtures/weather.json");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(forecast) = serde_json::from_slice::<ForecastResponse>(data) else {
+        return;
+    };
+
+    let weather: WeatherResponse = serde_json::from_str(WEATHER).unwrap();
+    for now in [i64::MIN, 0, i64::MAX] {
+        notify::evaluate(&weather, Some(&forecast), &[], &Thresholds::default(), now);
+    }
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::{display, ical};
+
+// `agenda` reads calendar files exported from anywhere.
+fuzz_target!(|text: &str| {
+    let Ok(appointments) = ical::read(text) else {
+        return;
+    };
+    let offset = display::utc_offset(-12 * 3600);
+    for appointment in appointments {
+        appointment.start.at(offset);
+        if let Some(location) = &appointment.location {
+            ical::place_queries(location);
+        }
+    }
+});
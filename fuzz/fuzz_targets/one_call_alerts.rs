@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::api::{OneCallAlerts, WeatherResponse};
+use cli_weather::config::Thresholds;
+use cli_weather::notify;
+
+const WEATHER: &str = include_str!("../../tests/fixtures/weather.json");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(response) = serde_json::from_slice::<OneCallAlerts>(data) else {
+        return;
+    };
+
+    let weather: WeatherResponse = serde_json::from_str(WEATHER).unwrap();
+    let mut thresholds = Thresholds::default();
+    thresholds.alerts = true;
+    for now in [i64::MIN, 0, i64::MAX] {
+        notify::evaluate(&weather, None, &response.alerts, &thresholds, now);
+    }
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::api::{ForecastResponse, WeatherResponse};
+use cli_weather::rule::{self, Sample};
+
+const WEATHER: &str = include_str!("../../tests/fixtures/weather.json");
+const FORECAST: &str = include_str!("../../tests/fixtures/forecast.json");
+
+// `[notify.rules]` come straight from the config file.
+fuzz_target!(|source: &str| {
+    let Ok(expr) = rule::parse(source) else {
+        return;
+    };
+
+    let weather: WeatherResponse = serde_json::from_str(WEATHER).unwrap();
+    let forecast: ForecastResponse = serde_json::from_str(FORECAST).unwrap();
+    // Just before the first step, as `check` would see it.
+    let now = forecast.list[0].dt - 600;
+    let mut timeline = vec![Sample::current(&weather, now)];
+    timeline.extend(forecast.list.iter().map(Sample::forecast));
+    expr.holds(&timeline);
+});
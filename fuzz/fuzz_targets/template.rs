@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use cli_weather::api::WeatherResponse;
+use cli_weather::display::{self, IconSet};
+use cli_weather::template::Template;
+
+const WEATHER: &str = include_str!("../../tests/fixtures/weather.json");
+
+// `--format` strings come straight from the user (or a status bar config).
+fuzz_target!(|source: &str| {
+    let Ok(template) = Template::parse(source, display::STATUS_PLACEHOLDERS) else {
+        return;
+    };
+
+    let weather: WeatherResponse = serde_json::from_str(WEATHER).unwrap();
+    display::render_status_line(&template, &weather, IconSet::NerdFont);
+});
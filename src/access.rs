@@ -0,0 +1,223 @@
+//! Who may use `serve`, when `[[serve.clients]]` are configured: each client
+//! sends its own token as `Authorization: Bearer ...`, makes at most
+//! `per_minute` requests a minute, and may be limited to some places, so
+//! one server can be shared by a household without sharing the API key.
+
+use std::{collections::HashMap, fmt};
+
+use crate::api::{Coord, Location};
+use crate::config::ClientSettings;
+use crate::{coords, countries};
+
+/// Degrees of latitude or longitude a coordinate scope stretches either
+/// way, a few km; forecasts do not differ much within it.
+pub const NEAR_DEGREES: f64 = 0.05;
+
+/// A place a client may ask about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    /// By name, matching requests with the same city and country.
+    Place { city: String, country_code: String },
+    /// Matching requests by coordinates within `NEAR_DEGREES`.
+    Near(Coord),
+}
+
+impl Scope {
+    /// "Paris, FR", "Paris, France" or coordinates such as "48.85,2.35".
+    pub fn parse(text: &str) -> Result<Scope, String> {
+        if let Ok(coord) = coords::parse(text) {
+            return Ok(Scope::Near(coord));
+        }
+        let Some((city, country)) = text.rsplit_once(',') else {
+            return Err(format!(
+                "'{}' is not a place; give e.g. \"Paris, FR\" or \"48.85,2.35\"",
+                text
+            ));
+        };
+        let country_code = countries::resolve(country.trim()).map_err(|e| e.to_string())?;
+        Ok(Scope::Place {
+            city: city.trim().to_lowercase(),
+            country_code: country_code.to_string(),
+        })
+    }
+
+    pub fn allows(&self, location: &Location) -> bool {
+        match (self, location) {
+            (
+                Scope::Place { city, country_code },
+                Location::Place {
+                    city: asked,
+                    country_code: asked_country,
+                },
+            ) => *city == asked.to_lowercase() && country_code.eq_ignore_ascii_case(asked_country),
+            (Scope::Near(near), Location::Coordinates(asked)) => {
+                (near.lat - asked.lat).abs() <= NEAR_DEGREES
+                    && (near.lon - asked.lon).abs() <= NEAR_DEGREES
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Client {
+    pub name: String,
+    token: String,
+    /// 0 for no limit.
+    pub per_minute: u32,
+    /// Empty for anywhere.
+    pub scopes: Vec<Scope>,
+}
+
+impl Client {
+    pub fn allows(&self, location: &Location) -> bool {
+        self.scopes.is_empty() || self.scopes.iter().any(|scope| scope.allows(location))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Denied {
+    /// No token was sent.
+    Missing,
+    Unknown,
+    RateLimited {
+        client: String,
+        retry_after: u64,
+    },
+    OutOfScope {
+        client: String,
+        location: String,
+    },
+}
+
+impl Denied {
+    /// The HTTP status to answer with.
+    pub fn status(&self) -> u16 {
+        match self {
+            Denied::Missing | Denied::Unknown => 401,
+            Denied::RateLimited { .. } => 429,
+            Denied::OutOfScope { .. } => 403,
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Denied::Missing => Some("send your token as `Authorization: Bearer <token>`"),
+            Denied::Unknown => Some("ask whoever runs the server for a token"),
+            Denied::RateLimited { .. } => Some("wait, or ask for a higher per_minute"),
+            Denied::OutOfScope { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Denied::Missing => write!(f, "this server needs a token"),
+            Denied::Unknown => write!(f, "unknown token"),
+            Denied::RateLimited {
+                client,
+                retry_after,
+            } => write!(
+                f,
+                "{} made too many requests this minute; try again in {} seconds",
+                client, retry_after
+            ),
+            Denied::OutOfScope { client, location } => {
+                write!(f, "{} may not ask about {}", client, location)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Denied {}
+
+/// The configured clients and how many requests each made this minute.
+#[derive(Debug, Default)]
+pub struct Clients {
+    clients: Vec<Client>,
+    /// By client name: the minute, as Unix time / 60, and requests in it.
+    used: HashMap<String, (i64, u32)>,
+}
+
+/// Compares without stopping at the first difference, so response times
+/// do not give away how much of a guessed token was right.
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+impl Clients {
+    /// Checks names and tokens are given and unique and every place reads.
+    pub fn new(settings: &[ClientSettings]) -> Result<Clients, String> {
+        let mut clients: Vec<Client> = Vec::new();
+        for (i, client) in settings.iter().enumerate() {
+            let name = if client.name.is_empty() {
+                format!("client {}", i + 1)
+            } else {
+                client.name.clone()
+            };
+            if client.token.is_empty() {
+                return Err(format!("serve.clients: {} has no token", name));
+            }
+            if clients.iter().any(|other| other.name == name) {
+                return Err(format!("serve.clients: {} is named twice", name));
+            }
+            if clients.iter().any(|other| other.token == client.token) {
+                return Err(format!(
+                    "serve.clients: {} has the same token as another client",
+                    name
+                ));
+            }
+            let scopes = client
+                .places
+                .iter()
+                .map(|place| Scope::parse(place))
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("serve.clients: {}: {}", name, e))?;
+            clients.push(Client {
+                name,
+                token: client.token.clone(),
+                per_minute: client.per_minute,
+                scopes,
+            });
+        }
+        Ok(Clients {
+            clients,
+            used: HashMap::new(),
+        })
+    }
+
+    /// Whether requests need a token at all.
+    pub fn required(&self) -> bool {
+        !self.clients.is_empty()
+    }
+
+    /// The client `token` belongs to, counting the request against its
+    /// limit at `now`.
+    pub fn admit(&mut self, token: Option<&str>, now: i64) -> Result<&Client, Denied> {
+        let token = token.ok_or(Denied::Missing)?;
+        let client = self
+            .clients
+            .iter()
+            .find(|client| same(&client.token, token))
+            .ok_or(Denied::Unknown)?;
+
+        let minute = now.div_euclid(60);
+        let used = self.used.entry(client.name.clone()).or_insert((minute, 0));
+        if used.0 != minute {
+            *used = (minute, 0);
+        }
+        if client.per_minute > 0 && used.1 >= client.per_minute {
+            return Err(Denied::RateLimited {
+                client: client.name.clone(),
+                retry_after: (60 - now.rem_euclid(60)) as u64,
+            });
+        }
+        used.1 += 1;
+        Ok(client)
+    }
+}
@@ -0,0 +1,151 @@
+//! What to wear and whether to go out, for `--advice`: a list of rules,
+//! each looking at the same plain conditions and maybe giving a tip.
+//!
+//! Adding advice means adding a rule to `RULES`; order there is the order
+//! tips are shown in.
+
+use crate::api::WeatherResponse;
+use crate::units;
+use crate::uv::{self, Risk};
+
+/// Probability of precipitation above which an umbrella is worth it.
+const UMBRELLA_CHANCE: f64 = 0.5;
+/// Beaufort force from which cycling is hard work: a strong breeze.
+const WINDY_FORCE: u8 = 6;
+/// m/s; gusts this strong knock cyclists about whatever the mean wind.
+const GUSTY: f64 = 15.0;
+/// °C feels-like from which heat is a concern of its own.
+const HOT: f64 = 30.0;
+
+/// Everything the rules look at, with temperatures in °C and speeds in m/s.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Conditions {
+    pub feels_like: f64,
+    pub wind: f64,
+    pub gust: Option<f64>,
+    /// The current condition code, e.g. 501 for moderate rain.
+    pub condition: Option<u32>,
+    /// The chance of rain in the next hours, 0 to 1, when the forecast was
+    /// fetched.
+    pub rain_chance: Option<f64>,
+    /// The highest UV index expected today, when known.
+    pub uv: Option<f64>,
+}
+
+impl Conditions {
+    pub fn new(
+        weather: &WeatherResponse,
+        rain_chance: Option<f64>,
+        uv: Option<uv::Summary>,
+    ) -> Conditions {
+        Conditions {
+            feels_like: weather.main.feels_like,
+            wind: weather.wind.speed,
+            gust: weather.wind.gust,
+            condition: weather.weather.first().map(|w| w.id),
+            rain_chance,
+            uv: uv.map(|uv| uv.today_max.unwrap_or(uv.now).max(uv.now)),
+        }
+    }
+
+    fn condition_in(&self, codes: std::ops::RangeInclusive<u32>) -> bool {
+        self.condition.is_some_and(|id| codes.contains(&id))
+    }
+}
+
+pub struct Rule {
+    /// Identifies the rule in tests and docs, e.g. "umbrella".
+    pub name: &'static str,
+    pub advise: fn(&Conditions) -> Option<String>,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        name: "umbrella",
+        advise: umbrella,
+    },
+    Rule {
+        name: "snow",
+        advise: snow,
+    },
+    Rule {
+        name: "layers",
+        advise: layers,
+    },
+    Rule {
+        name: "heat",
+        advise: heat,
+    },
+    Rule {
+        name: "sunscreen",
+        advise: sunscreen,
+    },
+    Rule {
+        name: "wind",
+        advise: wind,
+    },
+    Rule {
+        name: "fog",
+        advise: fog,
+    },
+];
+
+/// Every tip that applies, by rule name.
+pub fn advise(conditions: &Conditions) -> Vec<(&'static str, String)> {
+    RULES
+        .iter()
+        .filter_map(|rule| (rule.advise)(conditions).map(|tip| (rule.name, tip)))
+        .collect()
+}
+
+fn umbrella(c: &Conditions) -> Option<String> {
+    let raining = c.condition_in(200..=599);
+    match c.rain_chance {
+        Some(chance) if chance > UMBRELLA_CHANCE => Some(format!(
+            "Take an umbrella: {:.0}% chance of rain",
+            chance * 100.0
+        )),
+        _ if raining => Some(String::from("Take an umbrella: it is raining")),
+        _ => None,
+    }
+}
+
+fn snow(c: &Conditions) -> Option<String> {
+    c.condition_in(600..=699)
+        .then(|| String::from("Wear waterproof boots with a good grip"))
+}
+
+/// By feels-like temperature, from a heavy coat down to light clothes.
+fn layers(c: &Conditions) -> Option<String> {
+    let tip = match c.feels_like {
+        t if t < -10.0 => "Heavy coat, hat, scarf and gloves",
+        t if t < 0.0 => "Winter coat and warm layers",
+        t if t < 10.0 => "A warm jacket over a sweater",
+        t if t < 16.0 => "A light jacket or a sweater",
+        t if t < 22.0 => "A long-sleeved top; a light layer for the evening",
+        _ => "Light, breathable clothes",
+    };
+    Some(tip.to_string())
+}
+
+fn heat(c: &Conditions) -> Option<String> {
+    (c.feels_like >= HOT).then(|| String::from("Drink plenty of water and keep to the shade"))
+}
+
+fn sunscreen(c: &Conditions) -> Option<String> {
+    let uvi = c.uv?;
+    let risk = Risk::from_index(uvi);
+    (risk >= Risk::Moderate)
+        .then(|| format!("Wear sunscreen: UV up to {:.0} ({})", uvi, risk.name()))
+}
+
+fn wind(c: &Conditions) -> Option<String> {
+    let strong = units::beaufort(c.wind) >= WINDY_FORCE;
+    let gusty = c.gust.is_some_and(|gust| gust >= GUSTY);
+    (strong || gusty).then(|| String::from("Strong winds: not a good day for cycling"))
+}
+
+fn fog(c: &Conditions) -> Option<String> {
+    c.condition_in(741..=741)
+        .then(|| String::from("Fog: allow extra time if driving"))
+}
@@ -0,0 +1,929 @@
+use chrono::{DateTime, NaiveDate};
+use reqwest::header::{self, HeaderMap};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::cache::Cache;
+use crate::circuit::Breaker;
+use crate::credentials::Auth;
+use crate::metrics::Metrics;
+use crate::middleware::{
+    Authenticate, Cached, Log, Middleware, Next, RateLimit, Request, Response, Retry, Trace,
+};
+use crate::progress::Spinner;
+use crate::telemetry;
+use crate::usage::Tracker;
+
+/// Where requests go unless `with_base_url` names another server.
+pub const BASE_URL: &str = "http://api.openweathermap.org";
+
+/// Named in error messages so failures say where they came from.
+pub const PROVIDER: &str = "OpenWeatherMap";
+
+/// Sent unless the config names another; some providers reject requests
+/// without one.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WeatherResponse {
+    pub coord: Coord,
+    pub weather: Vec<Weather>,
+    pub main: Main,
+    pub wind: Wind,
+    pub name: String,
+    /// Offset from UTC in seconds.
+    pub timezone: i32,
+    /// Only sent while it rains.
+    #[serde(default)]
+    pub rain: Option<RecentRain>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecentRain {
+    /// mm over the last hour.
+    #[serde(rename = "1h", default)]
+    pub one_hour: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Coord {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Weather {
+    pub id: u32,
+    pub main: String,
+    pub description: String,
+    pub icon: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Main {
+    pub temp: f64,
+    pub feels_like: f64,
+    pub pressure: f64,
+    pub humidity: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Wind {
+    /// m/s.
+    pub speed: f64,
+    /// Where the wind blows from, in degrees clockwise from north.
+    #[serde(default)]
+    pub deg: Option<f64>,
+    /// m/s; only sent when there are gusts.
+    #[serde(default)]
+    pub gust: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForecastResponse {
+    pub list: Vec<ForecastEntry>,
+    pub city: ForecastCity,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForecastCity {
+    pub name: String,
+    /// Offset from UTC in seconds.
+    pub timezone: i32,
+}
+
+/// °C over one forecast step.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForecastMain {
+    pub temp: f64,
+    pub temp_min: f64,
+    pub temp_max: f64,
+    #[serde(default)]
+    pub feels_like: Option<f64>,
+    /// hPa.
+    #[serde(default)]
+    pub pressure: Option<f64>,
+    #[serde(default)]
+    pub humidity: f64,
+}
+
+/// One 3-hour step of the 5 day forecast.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ForecastEntry {
+    pub dt: i64,
+    pub dt_txt: String,
+    pub main: ForecastMain,
+    pub weather: Vec<Weather>,
+    #[serde(default)]
+    pub wind: Option<Wind>,
+    pub rain: Option<Precipitation>,
+    /// Probability of precipitation, 0 to 1.
+    #[serde(default)]
+    pub pop: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Precipitation {
+    #[serde(rename = "3h", default)]
+    pub three_hours: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OneCallAlerts {
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Alert {
+    pub sender_name: String,
+    pub event: String,
+    pub start: i64,
+    pub end: i64,
+    pub description: String,
+}
+
+/// The UV index parts of a One Call response.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UvForecast {
+    /// Offset from UTC in seconds.
+    pub timezone_offset: i32,
+    pub current: UvReading,
+    /// The next 48 hours.
+    #[serde(default)]
+    pub hourly: Vec<UvReading>,
+    /// Each day's maximum, starting today.
+    #[serde(default)]
+    pub daily: Vec<UvReading>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct UvReading {
+    pub dt: i64,
+    pub uvi: f64,
+}
+
+/// One day's aggregates from One Call's day_summary, in metric units.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DaySummary {
+    pub temperature: DayTemperature,
+    pub precipitation: DayPrecipitation,
+    #[serde(default)]
+    pub wind: Option<DayWind>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DayTemperature {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DayPrecipitation {
+    /// mm over the day.
+    pub total: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DayWind {
+    pub max: DayWindMax,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DayWindMax {
+    /// m/s.
+    pub speed: f64,
+}
+
+/// The body OpenWeatherMap sends with a failed request, e.g.
+/// `{"cod":"404","message":"city not found"}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiError {
+    #[serde(deserialize_with = "string_or_number")]
+    pub cod: String,
+    pub message: String,
+}
+
+/// `cod` is a string in error bodies but a number in some other responses.
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => Ok(s),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// What a provider may offer beyond the current weather.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Forecast,
+    Hourly,
+    Alerts,
+    Uv,
+    History,
+    AirQuality,
+    Marine,
+}
+
+impl Feature {
+    /// Needs OpenWeatherMap's One Call API, a subscription of its own.
+    fn one_call(&self) -> bool {
+        matches!(self, Feature::Alerts | Feature::Uv | Feature::History)
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Feature::Forecast => "the forecast",
+            Feature::Hourly => "hourly forecasts",
+            Feature::Alerts => "weather alerts",
+            Feature::Uv => "the UV index",
+            Feature::History => "past weather",
+            Feature::AirQuality => "air quality",
+            Feature::Marine => "marine forecasts",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Which features a provider offers, so commands made of several sections
+/// can leave out what it cannot give instead of failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The 5 day forecast in 3-hour steps.
+    pub forecast: bool,
+    pub hourly: bool,
+    pub alerts: bool,
+    pub uv: bool,
+    pub history: bool,
+    pub air_quality: bool,
+    pub marine: bool,
+}
+
+impl Capabilities {
+    /// OpenWeatherMap as this client uses it, assuming One Call until a
+    /// request shows otherwise.
+    pub const OPEN_WEATHER_MAP: Capabilities = Capabilities {
+        forecast: true,
+        hourly: false,
+        alerts: true,
+        uv: true,
+        history: true,
+        air_quality: false,
+        marine: false,
+    };
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::Forecast => self.forecast,
+            Feature::Hourly => self.hourly,
+            Feature::Alerts => self.alerts,
+            Feature::Uv => self.uv,
+            Feature::History => self.history,
+            Feature::AirQuality => self.air_quality,
+            Feature::Marine => self.marine,
+        }
+    }
+
+    /// The same, less everything that needs One Call.
+    pub fn without_one_call(self) -> Capabilities {
+        Capabilities {
+            alerts: false,
+            uv: false,
+            history: false,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The request never got an HTTP response: DNS, TLS, timeouts and the like.
+    Network(reqwest::Error),
+    /// The server answered with a non-success status.
+    Api { status: u16, error: ApiError },
+    /// HTTP 429. `retry_after` is the wait in seconds the server asked for.
+    RateLimited {
+        retry_after: Option<u64>,
+        error: ApiError,
+    },
+    /// A success response whose body did not have the expected shape.
+    Decode(serde_json::Error),
+    /// Not attempted: the provider kept failing and is skipped for another
+    /// `retry_in` seconds.
+    CircuitOpen { retry_in: u64 },
+    /// No credentials could be obtained, e.g. from an OAuth token endpoint.
+    Auth(String),
+    /// Not attempted, or refused: the provider does not offer this, or not
+    /// with this key.
+    Unsupported(Feature),
+}
+
+impl Error {
+    /// What the user can do about it, where there is something.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::Network(_) => Some("check your internet connection"),
+            Error::Api { status: 401, .. } => {
+                Some("check your API key; new keys can take a couple of hours to activate")
+            }
+            Error::Api { status: 404, .. } => Some("check the city name and country code"),
+            Error::Api {
+                status: 500..=599, ..
+            } => Some("OpenWeatherMap is having trouble; try again later"),
+            Error::Api { .. } => None,
+            Error::RateLimited { .. } => Some(
+                "the free plan allows 60 calls a minute; run `cli_weather usage` to see today's calls",
+            ),
+            Error::Decode(_) => Some("the API response changed shape; please report this"),
+            Error::CircuitOpen { .. } => Some("OpenWeatherMap looks down; try again later"),
+            Error::Auth(_) => Some("check the client id and secret"),
+            Error::Unsupported(feature) if feature.one_call() => {
+                Some("this needs a One Call subscription on your OpenWeatherMap account")
+            }
+            Error::Unsupported(_) => None,
+        }
+    }
+
+    /// Failures that suggest the provider is down rather than that the
+    /// request was wrong.
+    pub fn is_outage(&self) -> bool {
+        matches!(
+            self,
+            Error::Network(_)
+                | Error::Api {
+                    status: 500..=599,
+                    ..
+                }
+                | Error::CircuitOpen { .. }
+        )
+    }
+
+    /// A short label for counting errors, as in `serve`'s metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Network(_) => "network",
+            Error::Api {
+                status: 500..=599, ..
+            } => "server_error",
+            Error::Api { .. } => "client_error",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::Decode(_) => "decode",
+            Error::CircuitOpen { .. } => "circuit_open",
+            Error::Auth(_) => "auth",
+            Error::Unsupported(_) => "unsupported",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Network(e) => write!(f, "request failed: {}", e),
+            Error::Api { status, error } => {
+                let class = if (500..600).contains(status) {
+                    "server error"
+                } else {
+                    "request rejected"
+                };
+                write!(f, "{} ({}): {}", class, status, error.message)
+            }
+            Error::RateLimited { retry_after, error } => {
+                write!(f, "rate limited (429): {}", error.message)?;
+                match retry_after {
+                    Some(secs) => write!(f, "; retry in {} seconds", secs),
+                    None => write!(f, "; retry in a minute"),
+                }
+            }
+            Error::Decode(e) => write!(f, "unexpected response: {}", e),
+            Error::CircuitOpen { retry_in } => write!(
+                f,
+                "skipped after repeated failures; trying again in {} seconds",
+                retry_in
+            ),
+            Error::Auth(message) => write!(f, "could not authenticate: {}", message),
+            Error::Unsupported(feature) => write!(f, "{} does not offer {}", PROVIDER, feature),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Where to get weather for: a named place or exact coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Place { city: String, country_code: String },
+    Coordinates(Coord),
+}
+
+impl Location {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Location::Place { city, country_code } => {
+                vec![("q", format!("{},{}", city, country_code))]
+            }
+            Location::Coordinates(coord) => vec![
+                ("lat", coord.lat.to_string()),
+                ("lon", coord.lon.to_string()),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Place { city, country_code } if country_code.is_empty() => {
+                write!(f, "{}", city)
+            }
+            Location::Place { city, country_code } => write!(f, "{}, {}", city, country_code),
+            Location::Coordinates(coord) => write!(f, "{:.4}, {:.4}", coord.lat, coord.lon),
+        }
+    }
+}
+
+/// A place the geocoding API matched, with its name in other languages.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GeoCandidate {
+    pub name: String,
+    #[serde(default)]
+    pub local_names: HashMap<String, String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub country: String,
+    pub state: Option<String>,
+}
+
+impl GeoCandidate {
+    /// The name in `lang` when the API knows one, otherwise the default name.
+    pub fn localized_name(&self, lang: Option<&str>) -> &str {
+        lang.and_then(|lang| self.local_names.get(lang))
+            .unwrap_or(&self.name)
+    }
+
+    pub fn coord(&self) -> Coord {
+        Coord {
+            lat: self.lat,
+            lon: self.lon,
+        }
+    }
+}
+
+/// Seconds a response may be reused, from `Cache-Control` (`no-store` and
+/// `no-cache` mean none) or else `Expires`, less any `Age` already spent in
+/// shared caches. None when neither header says.
+fn max_age(headers: &HeaderMap, now: i64) -> Option<u64> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let age = header(header::AGE)
+        .and_then(|age| age.trim().parse::<u64>().ok())
+        .unwrap_or_default();
+
+    if let Some(cache_control) = header(header::CACHE_CONTROL) {
+        let mut max_age = None;
+        for directive in cache_control.split(',').map(str::trim) {
+            let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+            match name.to_ascii_lowercase().as_str() {
+                "no-store" | "no-cache" => return Some(0),
+                "max-age" => max_age = value.trim_matches('"').parse::<u64>().ok(),
+                _ => {}
+            }
+        }
+        if let Some(max_age) = max_age {
+            return Some(max_age.saturating_sub(age));
+        }
+    }
+
+    let http_date = |value: &str| DateTime::parse_from_rfc2822(value).ok();
+    let expires = http_date(header(header::EXPIRES)?)
+        // Unparseable dates such as "0" mean already expired.
+        .map_or(0, |expires| expires.timestamp());
+    let date = header(header::DATE)
+        .and_then(http_date)
+        .map_or(now, |date| date.timestamp());
+    Some(u64::try_from(expires - date).unwrap_or_default())
+}
+
+pub struct Client {
+    http: reqwest::blocking::Client,
+    base_url: String,
+    auth: Auth,
+    lang: RefCell<Option<String>>,
+    usage: Option<Tracker>,
+    cache: Option<Cache>,
+    rate_limit: Option<RateLimit>,
+    breaker: Breaker,
+    metrics: Option<Metrics>,
+    progress: bool,
+    capabilities: Cell<Capabilities>,
+    log: bool,
+    layers: Vec<Box<dyn Middleware>>,
+}
+
+/// An HTTP client that sends `user_agent` and `headers` with every request.
+pub fn http_client(
+    user_agent: &str,
+    headers: &BTreeMap<String, String>,
+) -> Result<reqwest::blocking::Client, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("'{}' is not a valid header name", name))?;
+        let mut value = header::HeaderValue::from_str(value)
+            .map_err(|_| format!("the value of '{}' is not a valid header value", name))?;
+        value.set_sensitive(true);
+        map.insert(name, value);
+    }
+
+    reqwest::blocking::Client::builder()
+        .user_agent(user_agent)
+        .default_headers(map)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+impl Client {
+    pub fn new(api_key: &str) -> Client {
+        Client {
+            http: http_client(USER_AGENT, &BTreeMap::new()).unwrap_or_default(),
+            base_url: BASE_URL.to_string(),
+            auth: Auth::QueryKey {
+                param: "appid",
+                key: api_key.to_string(),
+            },
+            lang: RefCell::new(None),
+            usage: None,
+            cache: None,
+            rate_limit: None,
+            breaker: Breaker::default(),
+            metrics: None,
+            progress: false,
+            capabilities: Cell::new(Capabilities::OPEN_WEATHER_MAP),
+            log: false,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Replaces the `appid` key given to `new`, for gateways or backends
+    /// that authenticate another way.
+    pub fn with_auth(mut self, auth: Auth) -> Client {
+        self.auth = auth;
+        self
+    }
+
+    /// Replaces the default HTTP client, for a custom User-Agent or headers.
+    pub fn with_http(mut self, http: reqwest::blocking::Client) -> Client {
+        self.http = http;
+        self
+    }
+
+    /// Sends requests to another server than OpenWeatherMap, e.g. a proxy
+    /// or a local mock in tests.
+    pub fn with_base_url(mut self, base_url: &str) -> Client {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Replaces the default breaker: open after 3 failures, for 5 minutes.
+    pub fn with_breaker(mut self, breaker: Breaker) -> Client {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Counts every request made, for `usage` and the daily limit warning.
+    pub fn with_usage(mut self, usage: Option<Tracker>) -> Client {
+        self.usage = usage;
+        self
+    }
+
+    /// Keeps the current weather on disk and answers from it while fresh.
+    pub fn with_cache(mut self, cache: Option<Cache>) -> Client {
+        self.cache = cache;
+        self
+    }
+
+    /// Sends at most `per_minute` requests in any minute; 0 for no limit.
+    pub fn with_rate_limit(mut self, per_minute: u32) -> Client {
+        self.rate_limit = (per_minute > 0).then(|| RateLimit::new(per_minute));
+        self
+    }
+
+    /// Times every request and counts failures by kind.
+    pub fn with_metrics(mut self, metrics: Option<Metrics>) -> Client {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Shows a spinner on stderr while each request is in flight.
+    pub fn with_progress(mut self, progress: bool) -> Client {
+        self.progress = progress;
+        self
+    }
+
+    /// Prints each request and its outcome to stderr.
+    pub fn with_log(mut self, log: bool) -> Client {
+        self.log = log;
+        self
+    }
+
+    /// Adds a layer in front of the built-in ones, e.g. `Canned` answers.
+    /// Layers added first run first.
+    pub fn with_middleware(mut self, layer: impl Middleware + 'static) -> Client {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    /// Replaces what the provider is assumed to offer, e.g. to skip One
+    /// Call for an account without it.
+    pub fn with_capabilities(self, capabilities: Capabilities) -> Client {
+        self.capabilities.set(capabilities);
+        self
+    }
+
+    /// What the provider offers, as far as this client knows so far: a One
+    /// Call request refused for the key drops the One Call features.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities.get()
+    }
+
+    fn require(&self, feature: Feature) -> Result<(), Error> {
+        if self.capabilities().supports(feature) {
+            Ok(())
+        } else {
+            Err(Error::Unsupported(feature))
+        }
+    }
+
+    /// A One Call request for `feature`. A key without the subscription is
+    /// refused with 401 and a message saying so, after which no more One
+    /// Call requests are sent; other refusals, as of a bad key, stand.
+    fn one_call<T: DeserializeOwned>(
+        &self,
+        feature: Feature,
+        path: &str,
+        params: Vec<(&'static str, String)>,
+    ) -> Result<T, Error> {
+        self.require(feature)?;
+        match self.get_json(path, params) {
+            Err(Error::Api {
+                status: 401 | 403,
+                error,
+            }) if error.message.to_lowercase().contains("subscription") => {
+                self.capabilities
+                    .set(self.capabilities().without_one_call());
+                Err(Error::Unsupported(feature))
+            }
+            result => result,
+        }
+    }
+
+    fn spinner(&self, message: impl FnOnce() -> String) -> Option<Spinner> {
+        self.progress.then(|| Spinner::start(message()))
+    }
+
+    pub fn usage(&self) -> Option<&Tracker> {
+        self.usage.as_ref()
+    }
+
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Language for condition descriptions, e.g. "de".
+    pub fn with_lang(mut self, lang: Option<String>) -> Client {
+        *self.lang.get_mut() = lang;
+        self
+    }
+
+    pub fn lang(&self) -> Option<String> {
+        self.lang.borrow().clone()
+    }
+
+    /// Switches the language for the requests that follow, for `serve`
+    /// answering each client in its own.
+    pub fn set_lang(&self, lang: Option<String>) {
+        *self.lang.borrow_mut() = lang;
+    }
+
+    /// `path` with `params` and the language, if one is set.
+    fn request(&self, path: &str, mut params: Vec<(&'static str, String)>) -> Request {
+        if let Some(lang) = self.lang() {
+            params.push(("lang", lang));
+        }
+        Request::new(path, params)
+    }
+
+    fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: Vec<(&'static str, String)>,
+    ) -> Result<T, Error> {
+        self.get(&self.request(path, params))
+    }
+
+    /// Sends `request` through the middleware chain, see `middleware` for
+    /// the order, and decodes the answer.
+    fn get<T: DeserializeOwned>(&self, request: &Request) -> Result<T, Error> {
+        let cached = self.cache.as_ref().map(|cache| Cached {
+            cache,
+            metrics: self.metrics.as_ref(),
+        });
+        let retry = Retry::default();
+        let authenticate = Authenticate {
+            auth: &self.auth,
+            http: &self.http,
+        };
+        let mut layers: Vec<&dyn Middleware> = self.layers.iter().map(AsRef::as_ref).collect();
+        if let Some(cached) = &cached {
+            layers.push(cached);
+        }
+        layers.push(&retry);
+        if let Some(rate_limit) = &self.rate_limit {
+            layers.push(rate_limit);
+        }
+        if let Some(metrics) = &self.metrics {
+            layers.push(metrics);
+        }
+        layers.push(&self.breaker);
+        if let Some(usage) = &self.usage {
+            layers.push(usage);
+        }
+        if self.log {
+            layers.push(&Log);
+        }
+        layers.push(&Trace);
+        layers.push(&authenticate);
+
+        let send = |request: &Request| self.send(request);
+        let response = Next::new(&layers, &send).run(request)?;
+
+        let mut parse = telemetry::span("parse");
+        serde_json::from_str(&response.body)
+            .map_err(Error::Decode)
+            .inspect_err(|e| parse.fail(e))
+    }
+
+    /// The end of the chain: one HTTP request, with failures mapped to
+    /// `Error`s. Transport errors drop the URL since it carries the API key.
+    fn send(&self, request: &Request) -> Result<Response, Error> {
+        let network = |e: reqwest::Error| Error::Network(e.without_url());
+        let response = request
+            .headers
+            .iter()
+            .fold(
+                self.http
+                    .get(format!("{}{}", self.base_url, request.path))
+                    .query(&request.params),
+                |builder, (name, value)| builder.header(name.as_str(), value.as_str()),
+            )
+            .send()
+            .map_err(network)?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok());
+        let max_age = max_age(response.headers(), chrono::Utc::now().timestamp());
+        let body = response.text().map_err(network)?;
+
+        if !status.is_success() {
+            let error = serde_json::from_str::<ApiError>(&body).unwrap_or_else(|_| ApiError {
+                cod: status.as_str().to_string(),
+                message: status
+                    .canonical_reason()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            });
+            return Err(if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                Error::RateLimited { retry_after, error }
+            } else {
+                Error::Api {
+                    status: status.as_u16(),
+                    error,
+                }
+            });
+        }
+
+        Ok(Response {
+            status: status.as_u16(),
+            body,
+            max_age,
+        })
+    }
+
+    /// The current weather, from the cache while it is fresh.
+    pub fn weather(&self, location: &Location) -> Result<WeatherResponse, Error> {
+        let _spinner = self.spinner(|| format!("Fetching the weather for {}", location));
+        self.get(&self.weather_request(location))
+    }
+
+    /// Like `weather`, but not from the cache, for `check` and `watch`
+    /// which want every refresh to be new. What comes back is still kept.
+    pub fn latest_weather(&self, location: &Location) -> Result<WeatherResponse, Error> {
+        let _spinner = self.spinner(|| format!("Fetching the weather for {}", location));
+        let mut request = self.weather_request(location);
+        request
+            .headers
+            .push((String::from("Cache-Control"), String::from("no-cache")));
+        self.get(&request)
+    }
+
+    /// What `weather` asks for, e.g. to look up its answer in a `Cache`.
+    pub fn weather_request(&self, location: &Location) -> Request {
+        let mut params = location.query();
+        params.push(("units", String::from("metric")));
+        self.request("/data/2.5/weather", params)
+    }
+
+    pub fn forecast(&self, location: &Location) -> Result<ForecastResponse, Error> {
+        self.require(Feature::Forecast)?;
+        let _spinner = self.spinner(|| format!("Fetching the forecast for {}", location));
+        let mut params = location.query();
+        params.push(("units", String::from("metric")));
+        self.get_json("/data/2.5/forecast", params)
+    }
+
+    /// Active alerts come from the One Call API, which needs its own subscription.
+    pub fn alerts(&self, coord: &Coord) -> Result<Vec<Alert>, Error> {
+        let _spinner = self.spinner(|| String::from("Fetching weather alerts"));
+        let params = vec![
+            ("lat", coord.lat.to_string()),
+            ("lon", coord.lon.to_string()),
+            ("exclude", String::from("current,minutely,hourly,daily")),
+        ];
+        let response: OneCallAlerts =
+            self.one_call(Feature::Alerts, "/data/3.0/onecall", params)?;
+        Ok(response.alerts)
+    }
+
+    /// The UV index now, hourly and as daily maximums, from One Call.
+    pub fn uv(&self, coord: &Coord) -> Result<UvForecast, Error> {
+        let _spinner = self.spinner(|| String::from("Fetching the UV index"));
+        let params = vec![
+            ("lat", coord.lat.to_string()),
+            ("lon", coord.lon.to_string()),
+            ("exclude", String::from("minutely,alerts")),
+        ];
+        self.one_call(Feature::Uv, "/data/3.0/onecall", params)
+    }
+
+    /// Aggregates for `date` at `coord`, from One Call. The date is the
+    /// place's own.
+    pub fn day_summary(&self, coord: &Coord, date: NaiveDate) -> Result<DaySummary, Error> {
+        let _spinner = self.spinner(|| format!("Fetching the weather of {}", date));
+        self.fetch_day_summary(coord, date)
+    }
+
+    /// `day_summary` for each of `dates`, one request each, with how far
+    /// along it is on the spinner.
+    pub fn day_summaries(
+        &self,
+        coord: &Coord,
+        dates: &[NaiveDate],
+    ) -> Vec<Result<DaySummary, Error>> {
+        let spinner = self.spinner(|| format!("Fetching the weather of {} days", dates.len()));
+        dates
+            .iter()
+            .enumerate()
+            .map(|(i, date)| {
+                if let Some(spinner) = &spinner {
+                    spinner.set_message(format!(
+                        "Fetching the weather of {} ({} of {})",
+                        date,
+                        i + 1,
+                        dates.len()
+                    ));
+                }
+                self.fetch_day_summary(coord, *date)
+            })
+            .collect()
+    }
+
+    fn fetch_day_summary(&self, coord: &Coord, date: NaiveDate) -> Result<DaySummary, Error> {
+        let params = vec![
+            ("lat", coord.lat.to_string()),
+            ("lon", coord.lon.to_string()),
+            ("date", date.to_string()),
+            ("units", String::from("metric")),
+        ];
+        self.one_call(Feature::History, "/data/3.0/onecall/day_summary", params)
+    }
+
+    /// Places matching `city`, which may be spelled in any language the
+    /// geocoding API knows (München and Munich both work). An empty
+    /// `country_code` searches every country.
+    pub fn geocode(&self, city: &str, country_code: &str) -> Result<Vec<GeoCandidate>, Error> {
+        let query = if country_code.is_empty() {
+            city.to_string()
+        } else {
+            format!("{},{}", city, country_code)
+        };
+        let _spinner = self.spinner(|| format!("Looking up {}", query));
+        let params = vec![("q", query), ("limit", String::from("5"))];
+        self.get_json("/geo/1.0/direct", params)
+    }
+}
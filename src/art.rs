@@ -0,0 +1,147 @@
+//! Small pictures of the sky for `--art`, in the style of wttr.in.
+//!
+//! Each picture is five lines of art and a matching colour mask: `y` sun
+//! and lightning, `w` cloud, `g` dark cloud and fog, `b` rain, `s` snow,
+//! `m` moon and stars, each painted with the theme's role for it. Spaces in
+//! the mask keep the terminal's colour.
+
+use colored::*;
+
+use crate::theme::{self, Role};
+
+pub const HEIGHT: usize = 5;
+pub const WIDTH: usize = 15;
+
+type Picture = [(&'static str, &'static str); HEIGHT];
+
+const SUN: Picture = [
+    ("    \\   /      ", "    y   y      "),
+    ("     .-.       ", "     yyy       "),
+    ("  ― (   ) ―    ", "  y y   y y    "),
+    ("     `-’       ", "     yyy       "),
+    ("    /   \\      ", "    y   y      "),
+];
+
+const MOON: Picture = [
+    ("      *    .   ", "      m    m   "),
+    ("    .--.       ", "    mmmm       "),
+    ("   (   (    *  ", "   m   m    m  "),
+    ("    '--'       ", "    mmmm       "),
+    ("  .      *     ", "  m      m     "),
+];
+
+const SUN_AND_CLOUD: Picture = [
+    ("   \\  /        ", "   y  y        "),
+    (" _ /\"\".-.      ", " y yyywww      "),
+    ("   \\_(   ).    ", "   yyw   ww    "),
+    ("   /(___(__)   ", "   ywwwwwwww   "),
+    ("               ", "               "),
+];
+
+const MOON_AND_CLOUD: Picture = [
+    ("    .-.   *    ", "    mmm   m    "),
+    ("   (  (.-.     ", "   m  mwww     "),
+    ("    '(   ).    ", "    mw   ww    "),
+    ("    (___(__)   ", "    wwwwwwww   "),
+    ("  *            ", "  m            "),
+];
+
+const CLOUDS: Picture = [
+    ("               ", "               "),
+    ("     .--.      ", "     wwww      "),
+    ("  .-(    ).    ", "  www    ww    "),
+    (" (___.__)__)   ", " wwwwwwwwwww   "),
+    ("               ", "               "),
+];
+
+const RAIN: Picture = [
+    ("     .-.       ", "     ggg       "),
+    ("    (   ).     ", "    g   gg     "),
+    ("   (___(__)    ", "   gggggggg    "),
+    ("    ‚‘‚‘‚‘     ", "    bbbbbb     "),
+    ("    ‚’‚’‚’     ", "    bbbbbb     "),
+];
+
+const THUNDERSTORM: Picture = [
+    ("     .-.       ", "     ggg       "),
+    ("    (   ).     ", "    g   gg     "),
+    ("   (___(__)    ", "   gggggggg    "),
+    ("   ‚‘/_‚‘/_    ", "   bbyybbyy    "),
+    ("    ‚’ /‚’ /   ", "    bb ybb y   "),
+];
+
+const SNOW: Picture = [
+    ("     .-.       ", "     www       "),
+    ("    (   ).     ", "    w   ww     "),
+    ("   (___(__)    ", "   wwwwwwww    "),
+    ("    *  *  *    ", "    s  s  s    "),
+    ("   *  *  *     ", "   s  s  s     "),
+];
+
+const FOG: Picture = [
+    ("               ", "               "),
+    (" _ - _ - _ -   ", " g g g g g g   "),
+    ("  _ - _ - _    ", "  g g g g g    "),
+    (" _ - _ - _ -   ", " g g g g g g   "),
+    ("               ", "               "),
+];
+
+const UNKNOWN: Picture = [
+    ("    .-.        ", "    www        "),
+    ("     __)       ", "     www       "),
+    ("    (          ", "    w          "),
+    ("     `-’       ", "     www       "),
+    ("      •        ", "      w        "),
+];
+
+/// Picks the picture for an OpenWeatherMap condition code.
+fn picture(condition: u32, night: bool) -> &'static Picture {
+    match condition {
+        200..=299 => &THUNDERSTORM,
+        300..=399 | 500..=599 => &RAIN,
+        600..=699 => &SNOW,
+        700..=799 => &FOG,
+        800 if night => &MOON,
+        800 => &SUN,
+        801 | 802 if night => &MOON_AND_CLOUD,
+        801 | 802 => &SUN_AND_CLOUD,
+        803 | 804 => &CLOUDS,
+        _ => &UNKNOWN,
+    }
+}
+
+fn paint(text: &str, mask: char) -> ColoredString {
+    let role = match mask {
+        'y' => Role::Sun,
+        'w' => Role::Cloud,
+        'g' => Role::Muted,
+        'b' => Role::Rain,
+        's' => Role::Snow,
+        'm' => Role::Moon,
+        _ => return text.normal(),
+    };
+    theme::paint(role, text)
+}
+
+/// The picture's lines, coloured, each `WIDTH` columns wide.
+pub fn banner(condition: u32, night: bool) -> Vec<String> {
+    picture(condition, night)
+        .iter()
+        .map(|(art, mask)| {
+            let mut line = String::new();
+            let mut run = String::new();
+            let mut run_mask = ' ';
+
+            for (c, m) in art.chars().zip(mask.chars().chain(std::iter::repeat(' '))) {
+                if m != run_mask && !run.is_empty() {
+                    line.push_str(&paint(&run, run_mask).to_string());
+                    run.clear();
+                }
+                run_mask = m;
+                run.push(c);
+            }
+            line.push_str(&paint(&run, run_mask).to_string());
+            line
+        })
+        .collect()
+}
@@ -0,0 +1,202 @@
+//! Sun and moon positions computed locally from coordinates and a date, so
+//! no extra API subscription is needed.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::f64::consts::PI;
+
+const J2000: f64 = 2451545.0;
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+const OBLIQUITY: f64 = 23.4397;
+const SYNODIC_MONTH: f64 = 29.530588853;
+/// Julian date of the new moon of 2000-01-06 18:14 UTC.
+const REFERENCE_NEW_MOON: f64 = 2451550.1;
+
+/// Solar altitudes that define each event, in degrees.
+const SUNRISE_ALTITUDE: f64 = -0.833;
+const CIVIL_TWILIGHT_ALTITUDE: f64 = -6.0;
+const NAUTICAL_TWILIGHT_ALTITUDE: f64 = -12.0;
+const ASTRONOMICAL_TWILIGHT_ALTITUDE: f64 = -18.0;
+
+/// When the sun crosses an altitude on a given day, or why it does not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossing {
+    At {
+        rising: DateTime<Utc>,
+        setting: DateTime<Utc>,
+    },
+    /// The sun stays above the altitude all day.
+    AlwaysAbove,
+    /// The sun stays below the altitude all day.
+    AlwaysBelow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunTimes {
+    pub noon: DateTime<Utc>,
+    pub sunrise: Crossing,
+    /// Each twilight runs from its dawn crossing to sunrise and from sunset
+    /// to its dusk crossing; the deeper ones start earlier and end later.
+    pub civil_twilight: Crossing,
+    pub nautical_twilight: Crossing,
+    pub astronomical_twilight: Crossing,
+}
+
+impl SunTimes {
+    /// Time between sunrise and sunset, in seconds.
+    pub fn day_length(&self) -> i64 {
+        match self.sunrise {
+            Crossing::At { rising, setting } => (setting - rising).num_seconds(),
+            Crossing::AlwaysAbove => 86400,
+            Crossing::AlwaysBelow => 0,
+        }
+    }
+}
+
+fn to_datetime(julian: f64) -> DateTime<Utc> {
+    let seconds = ((julian - UNIX_EPOCH_JD) * 86400.0).round() as i64;
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
+
+fn julian(time: DateTime<Utc>) -> f64 {
+    time.timestamp() as f64 / 86400.0 + UNIX_EPOCH_JD
+}
+
+/// The standard sunrise equation, accurate to a minute or two outside the
+/// polar regions. `lon` is positive east.
+pub fn sun_times(lat: f64, lon: f64, date: NaiveDate) -> SunTimes {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let day = (julian(midnight) - J2000 + 0.0008).ceil();
+
+    let mean_solar_noon = day - lon / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * mean_solar_noon).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_solar_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+    let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+
+    let crossing = |altitude: f64| {
+        let phi = lat.to_radians();
+        let cos_hour_angle = (altitude.to_radians().sin() - phi.sin() * declination.sin())
+            / (phi.cos() * declination.cos());
+
+        if cos_hour_angle < -1.0 {
+            Crossing::AlwaysAbove
+        } else if cos_hour_angle > 1.0 {
+            Crossing::AlwaysBelow
+        } else {
+            let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+            Crossing::At {
+                rising: to_datetime(transit - half_day),
+                setting: to_datetime(transit + half_day),
+            }
+        }
+    };
+
+    SunTimes {
+        noon: to_datetime(transit),
+        sunrise: crossing(SUNRISE_ALTITUDE),
+        civil_twilight: crossing(CIVIL_TWILIGHT_ALTITUDE),
+        nautical_twilight: crossing(NAUTICAL_TWILIGHT_ALTITUDE),
+        astronomical_twilight: crossing(ASTRONOMICAL_TWILIGHT_ALTITUDE),
+    }
+}
+
+/// Where the sun is in the sky, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunPosition {
+    /// Above the horizon, negative below it; geometric, without refraction.
+    pub elevation: f64,
+    /// Clockwise from north, 0 to 360.
+    pub azimuth: f64,
+}
+
+impl SunPosition {
+    /// How long a shadow is per unit of height, e.g. 2.0 for a 1 m pole
+    /// throwing a 2 m shadow. None while the sun is down.
+    pub fn shadow_ratio(&self) -> Option<f64> {
+        (self.elevation > 0.0).then(|| 1.0 / self.elevation.to_radians().tan())
+    }
+
+    /// The compass bearing shadows point along, away from the sun.
+    pub fn shadow_bearing(&self) -> f64 {
+        (self.azimuth + 180.0).rem_euclid(360.0)
+    }
+}
+
+/// The same low-precision solar model as `sun_times`, good to about a
+/// degree. `lon` is positive east.
+pub fn sun_position(lat: f64, lon: f64, time: DateTime<Utc>) -> SunPosition {
+    let day = julian(time) - J2000;
+
+    let anomaly = (357.5291 + 0.98560028 * day).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let obliquity = OBLIQUITY.to_radians();
+    let declination = (ecliptic_longitude.sin() * obliquity.sin()).asin();
+    let right_ascension =
+        (ecliptic_longitude.sin() * obliquity.cos()).atan2(ecliptic_longitude.cos());
+
+    let sidereal = (280.16 + 360.9856235 * day + lon).to_radians();
+    let hour_angle = sidereal - right_ascension;
+    let phi = lat.to_radians();
+
+    let elevation =
+        (phi.sin() * declination.sin() + phi.cos() * declination.cos() * hour_angle.cos()).asin();
+    // Measured from south by the formula, so turned round to start at north.
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * phi.sin() - declination.tan() * phi.cos());
+
+    SunPosition {
+        elevation: elevation.to_degrees(),
+        azimuth: (azimuth.to_degrees() + 180.0).rem_euclid(360.0),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoonPhase {
+    /// Days since the last new moon.
+    pub age: f64,
+    /// Lit fraction of the disc, 0 to 1.
+    pub illumination: f64,
+}
+
+const PHASES: [(&str, &str); 8] = [
+    ("new moon", "🌑"),
+    ("waxing crescent", "🌒"),
+    ("first quarter", "🌓"),
+    ("waxing gibbous", "🌔"),
+    ("full moon", "🌕"),
+    ("waning gibbous", "🌖"),
+    ("last quarter", "🌗"),
+    ("waning crescent", "🌘"),
+];
+
+impl MoonPhase {
+    fn index(&self) -> usize {
+        ((self.age / SYNODIC_MONTH * 8.0).round() as usize) % PHASES.len()
+    }
+
+    pub fn name(&self) -> &'static str {
+        PHASES[self.index()].0
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        PHASES[self.index()].1
+    }
+}
+
+/// Mean lunar phase; within about half a day of the true one.
+pub fn moon_phase(time: DateTime<Utc>) -> MoonPhase {
+    let age = (julian(time) - REFERENCE_NEW_MOON).rem_euclid(SYNODIC_MONTH);
+    let illumination = (1.0 - (2.0 * PI * age / SYNODIC_MONTH).cos()) / 2.0;
+
+    MoonPhase { age, illumination }
+}
@@ -0,0 +1,309 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use crate::middleware::{Request, Response};
+use crate::storage::{self, Schema};
+use crate::telemetry;
+
+const CACHE_DIR: &str = "cli_weather";
+
+pub const SCHEMA: Schema = Schema {
+    name: "cache",
+    migrations: &[],
+};
+
+/// The endpoints whose answers are kept, and the directory each goes in.
+const CACHED: &[(&str, &str)] = &[("/data/2.5/weather", "current")];
+
+/// Seconds a response is reused for when it has no cache headers.
+const DEFAULT_MAX_AGE: u64 = 600;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    fetched_at: i64,
+    /// When the provider's cache headers say the response goes stale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+    /// As the provider sent it.
+    body: String,
+    /// The entry this one replaced, for comparing with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<Previous>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Previous {
+    fetched_at: i64,
+    body: String,
+}
+
+/// Recent responses on disk, one file per request, so repeated invocations
+/// (status bars refresh every few seconds) stay within the API quota. The
+/// `middleware::Cached` layer reads and fills it.
+pub struct Cache {
+    dir: PathBuf,
+    max_age: u64,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Cache {
+        Cache {
+            dir,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+
+    /// Seconds to reuse responses for that have no cache headers.
+    pub fn with_max_age(self, max_age: u64) -> Cache {
+        Cache { max_age, ..self }
+    }
+
+    pub fn open() -> Option<Cache> {
+        dirs::cache_dir().map(|dir| Cache::new(dir.join(CACHE_DIR)))
+    }
+
+    /// Whether answers to `request` are kept at all.
+    pub fn keeps(&self, request: &Request) -> bool {
+        self.path(request).is_some()
+    }
+
+    /// One file per endpoint and query, named for each parameter and its
+    /// value, e.g. `q=paris_2cfr,units=metric.json`. A query in another
+    /// language is another entry, since descriptions come translated;
+    /// coordinates are rounded so that nearby requests share one.
+    fn path(&self, request: &Request) -> Option<PathBuf> {
+        let (_, dir) = CACHED.iter().find(|(path, _)| *path == request.path)?;
+        let key = request
+            .params
+            .iter()
+            .map(|(name, value)| {
+                let value = match value.parse::<f64>() {
+                    Ok(degrees) if matches!(*name, "lat" | "lon") => format!("{:.4}", degrees),
+                    _ => value.clone(),
+                };
+                format!("{}={}", name, escape(&value.to_lowercase()))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        Some(self.dir.join(dir).join(format!("{}.json", key)))
+    }
+
+    /// Returns the cached response while it is fresh: until the expiry the
+    /// provider gave, or for `max_age` seconds when it gave none. Unreadable
+    /// entries count as misses.
+    pub fn get(&self, request: &Request, now: i64) -> Option<Response> {
+        let mut span = telemetry::span("cache.get");
+        let fresh = self.entry(request).and_then(|entry| {
+            let expires_at = entry
+                .expires_at
+                .unwrap_or(entry.fetched_at.saturating_add_unsigned(self.max_age));
+            let left = u64::try_from(expires_at.checked_sub(now)?).ok()?;
+            Some(Response {
+                status: 200,
+                body: entry.body,
+                max_age: Some(left),
+            })
+        });
+        span.attr("cache.hit", fresh.is_some());
+        fresh
+    }
+
+    /// The last response however old it is, with when it was fetched, for
+    /// when the provider is down.
+    pub fn get_stale(&self, request: &Request) -> Option<(Response, i64)> {
+        self.entry(request)
+            .map(|entry| (Response::ok(entry.body), entry.fetched_at))
+    }
+
+    /// The response the last one replaced, decoded, with when it was
+    /// fetched: what the last is new against.
+    pub fn previous<T: DeserializeOwned>(&self, request: &Request) -> Option<(T, i64)> {
+        let previous = self.entry(request)?.previous?;
+        Some((
+            serde_json::from_str(&previous.body).ok()?,
+            previous.fetched_at,
+        ))
+    }
+
+    fn entry(&self, request: &Request) -> Option<Entry> {
+        storage::load(&self.path(request)?, &SCHEMA).ok()?
+    }
+
+    /// Keeps `response` for as long as its `max_age` says, if it says.
+    /// Requests to endpoints that are not kept are ignored.
+    pub fn put(
+        &self,
+        request: &Request,
+        response: &Response,
+        now: i64,
+    ) -> Result<(), storage::Error> {
+        let Some(path) = self.path(request) else {
+            return Ok(());
+        };
+        let mut span = telemetry::span("cache.put");
+        let entry = Entry {
+            fetched_at: now,
+            expires_at: response
+                .max_age
+                .map(|secs| now.saturating_add_unsigned(secs)),
+            body: response.body.clone(),
+            previous: self.entry(request).map(|replaced| Previous {
+                fetched_at: replaced.fetched_at,
+                body: replaced.body,
+            }),
+        };
+        storage::save(&path, &SCHEMA, &entry).inspect_err(|e| span.fail(e))
+    }
+
+    /// Deletes entries last saved more than `days` days before `now`, so
+    /// places asked about once do not stay for ever. Returns how many went.
+    pub fn prune(&self, days: u32, now: SystemTime) -> io::Result<usize> {
+        let cutoff = now - Duration::from_secs(u64::from(days) * 86400);
+        let mut removed = 0;
+        for (_, dir) in CACHED {
+            let entries = match fs::read_dir(self.dir.join(dir)) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for entry in entries {
+                let path = entry?.path();
+                let modified = fs::metadata(&path)?.modified()?;
+                if path.extension().is_some_and(|ext| ext == "json") && modified < cutoff {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// `value` safe in a file name and told apart from every other: letters,
+/// digits, `.` and `-` as they are, anything else as `_` and its UTF-8
+/// bytes in hex.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        if c.is_alphanumeric() || c == '.' || c == '-' {
+            escaped.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                escaped.push_str(&format!("_{:02x}", byte));
+            }
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str) -> Cache {
+        Cache::new(std::env::temp_dir().join(format!(
+            "cli_weather-cache-{}-{}",
+            name,
+            std::process::id()
+        )))
+    }
+
+    fn weather(city: &str) -> Request {
+        Request::new("/data/2.5/weather", vec![("q", city.to_string())])
+    }
+
+    #[test]
+    fn prune_without_a_cache() {
+        let cache = cache("none");
+        assert_eq!(cache.prune(30, SystemTime::now()).unwrap(), 0);
+    }
+
+    #[test]
+    fn prune_keeps_recent_entries() {
+        let cache = cache("recent");
+        cache
+            .put(&weather("Paris"), &Response::ok("{}"), 0)
+            .unwrap();
+        assert_eq!(cache.prune(30, SystemTime::now()).unwrap(), 0);
+        assert!(cache.get_stale(&weather("Paris")).is_some());
+        fs::remove_dir_all(&cache.dir).ok();
+    }
+
+    #[test]
+    fn prune_removes_entries_older_than_the_days() {
+        let cache = cache("old");
+        cache
+            .put(&weather("Paris"), &Response::ok("{}"), 0)
+            .unwrap();
+        cache.put(&weather("Oslo"), &Response::ok("{}"), 0).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(31 * 86400);
+        assert_eq!(cache.prune(30, later).unwrap(), 2);
+        assert!(cache.get_stale(&weather("Paris")).is_none());
+        fs::remove_dir_all(&cache.dir).ok();
+    }
+
+    #[test]
+    fn another_language_is_another_entry() {
+        let cache = cache("lang");
+        let mut german = weather("Paris");
+        german.params.push(("lang", String::from("de")));
+        cache
+            .put(&weather("Paris"), &Response::ok("{}"), 0)
+            .unwrap();
+        assert!(cache.get_stale(&german).is_none());
+        fs::remove_dir_all(&cache.dir).ok();
+    }
+
+    #[test]
+    fn other_endpoints_are_not_kept() {
+        let cache = cache("other");
+        let geocoding = Request::new("/geo/1.0/direct", Vec::new());
+        assert!(!cache.keeps(&geocoding));
+        cache.put(&geocoding, &Response::ok("[]"), 0).unwrap();
+        assert!(!cache.dir.exists());
+    }
+
+    #[test]
+    fn path_names_each_parameter() {
+        let cache = cache("path");
+        let path = |params: Vec<(&'static str, &str)>| {
+            let params = params
+                .into_iter()
+                .map(|(name, value)| (name, value.to_string()))
+                .collect();
+            cache.path(&Request::new("/data/2.5/weather", params))
+        };
+        assert_eq!(
+            path(vec![("q", "Paris,FR")]).unwrap().file_name().unwrap(),
+            "q=paris_2cfr.json"
+        );
+        assert_ne!(
+            path(vec![("q", "48.85,2.35")]),
+            path(vec![("lat", "48.85"), ("lon", "2.35")])
+        );
+        assert_ne!(
+            path(vec![("q", "St. Louis")]),
+            path(vec![("q", "St_ Louis")])
+        );
+        assert_eq!(
+            path(vec![("lat", "48.850001"), ("lon", "2.35")]),
+            path(vec![("lat", "48.85"), ("lon", "2.350002")])
+        );
+    }
+
+    #[test]
+    fn put_keeps_the_entry_it_replaces() {
+        let cache = cache("previous");
+        let request = weather("Paris");
+        cache.put(&request, &Response::ok("1"), 0).unwrap();
+        assert!(cache.previous::<u32>(&request).is_none());
+        cache.put(&request, &Response::ok("2"), 600).unwrap();
+        assert_eq!(cache.previous::<u32>(&request), Some((1, 0)));
+        fs::remove_dir_all(&cache.dir).ok();
+    }
+}
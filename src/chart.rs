@@ -0,0 +1,41 @@
+//! Bars for charts drawn in the terminal, one row per value.
+
+/// Blocks filling one to eight eighths of a cell, left to right.
+const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// `value` drawn `cells_per_unit` cells to the unit, to the nearest eighth
+/// of a cell and at most `max_cells` long. Empty for zero or less.
+pub fn bar(value: f64, cells_per_unit: f64, max_cells: usize) -> String {
+    let eighths = (value * cells_per_unit * 8.0)
+        .round()
+        .clamp(0.0, (max_cells * 8) as f64) as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(EIGHTHS[eighths % 8 - 1]);
+    }
+    bar
+}
+
+/// The stretch from `low` to `high` on an axis `width` cells wide running
+/// from `min` to `max`, padded with spaces to the full width so columns
+/// after it line up. Always at least one cell.
+pub fn span(low: f64, high: f64, min: f64, max: f64, width: usize) -> String {
+    let range = max - min;
+    let cell = |value: f64| {
+        if range > 0.0 {
+            ((value - min) / range * width as f64)
+                .round()
+                .clamp(0.0, width as f64) as usize
+        } else {
+            0
+        }
+    };
+    let start = cell(low).min(width.saturating_sub(1));
+    let end = cell(high).max(start + 1).min(width.max(1));
+    format!(
+        "{}{}{}",
+        " ".repeat(start),
+        "█".repeat(end - start),
+        " ".repeat(width.saturating_sub(end))
+    )
+}
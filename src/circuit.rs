@@ -0,0 +1,66 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    /// Calls are skipped until `until`; the first call after that is a trial.
+    Open {
+        until: Instant,
+    },
+}
+
+/// Stops calling a provider that keeps failing, so a long outage costs one
+/// timeout per cooldown instead of one per request.
+#[derive(Debug)]
+pub struct Breaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Cell<State>,
+}
+
+impl Breaker {
+    /// Opens after `threshold` failures in a row and stays open for `cooldown`.
+    pub fn new(threshold: u32, cooldown: Duration) -> Breaker {
+        Breaker {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Cell::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Whether a call may go ahead; otherwise how long until the next trial.
+    pub fn check(&self, now: Instant) -> Result<(), Duration> {
+        match self.state.get() {
+            State::Open { until } if now < until => Err(until - now),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record(&self, succeeded: bool, now: Instant) {
+        let state = match (self.state.get(), succeeded) {
+            (_, true) => State::Closed { failures: 0 },
+            // The trial call failed too.
+            (State::Open { .. }, false) => State::Open {
+                until: now + self.cooldown,
+            },
+            (State::Closed { failures }, false) if failures + 1 >= self.threshold => State::Open {
+                until: now + self.cooldown,
+            },
+            (State::Closed { failures }, false) => State::Closed {
+                failures: failures + 1,
+            },
+        };
+        self.state.set(state);
+    }
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Breaker::new(3, Duration::from_secs(300))
+    }
+}
@@ -0,0 +1,197 @@
+//! Climate normals for `scout`: what a month is usually like at a place,
+//! from 30 years of Open-Meteo's ERA5 reanalysis rather than a forecast.
+//! Needs no API key.
+
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+
+use crate::api::{self, ApiError, Coord};
+
+const BASE_URL: &str = "https://archive-api.open-meteo.com";
+
+pub const PROVIDER: &str = "Open-Meteo";
+
+/// The current WMO reference period.
+pub const FIRST_YEAR: i32 = 1991;
+pub const LAST_YEAR: i32 = 2020;
+
+/// mm of precipitation that make a day count as a rain day, as in WMO normals.
+pub const RAIN_DAY_MM: f64 = 1.0;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// A month as a number from 1 to 12, from e.g. "july", "Jul" or "7".
+pub fn parse_month(input: &str) -> Result<u32, String> {
+    let lower = input.trim().to_lowercase();
+    if let Ok(n @ 1..=12) = lower.parse::<u32>() {
+        return Ok(n);
+    }
+    MONTHS
+        .iter()
+        .position(|name| lower.len() >= 3 && name.starts_with(&lower))
+        .map(|i| i as u32 + 1)
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a month; use a name like july or a number",
+                input
+            )
+        })
+}
+
+/// e.g. "July" for 7.
+pub fn month_name(month: u32) -> String {
+    let name = MONTHS[(month.clamp(1, 12) - 1) as usize];
+    name[..1].to_uppercase() + &name[1..]
+}
+
+/// Daily values, one per date in `time`; days the model has no value for
+/// are null.
+#[derive(Deserialize, Debug, Default)]
+pub struct Daily {
+    pub time: Vec<String>,
+    pub temperature_2m_max: Vec<Option<f64>>,
+    pub precipitation_sum: Vec<Option<f64>>,
+    pub relative_humidity_2m_mean: Vec<Option<f64>>,
+}
+
+#[derive(Deserialize)]
+struct Archive {
+    daily: Daily,
+}
+
+/// What Open-Meteo sends with a failed request, e.g.
+/// `{"error":true,"reason":"Latitude must be in range of -90 to 90°."}`.
+#[derive(Deserialize)]
+struct ErrorBody {
+    reason: String,
+}
+
+/// A month's averages over the reference period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normals {
+    /// Mean daily maximum, °C.
+    pub high: f64,
+    /// Days with at least 1 mm of precipitation, per year.
+    pub rain_days: f64,
+    /// Mean relative humidity, percent.
+    pub humidity: f64,
+}
+
+impl Normals {
+    /// None when `daily` has no values for `month`.
+    pub fn for_month(daily: &Daily, month: u32) -> Option<Normals> {
+        let mut highs = Vec::new();
+        let mut humidity = Vec::new();
+        let mut rain_days = 0;
+        let mut years = Vec::new();
+
+        for (i, date) in daily.time.iter().enumerate() {
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                continue;
+            };
+            if date.month() != month {
+                continue;
+            }
+            if !years.contains(&date.year()) {
+                years.push(date.year());
+            }
+            let value = |values: &[Option<f64>]| values.get(i).copied().flatten();
+            highs.extend(value(&daily.temperature_2m_max));
+            humidity.extend(value(&daily.relative_humidity_2m_mean));
+            if value(&daily.precipitation_sum).is_some_and(|mm| mm >= RAIN_DAY_MM) {
+                rain_days += 1;
+            }
+        }
+
+        if highs.is_empty() || years.is_empty() {
+            return None;
+        }
+        Some(Normals {
+            high: mean(&highs),
+            rain_days: f64::from(rain_days) / years.len() as f64,
+            humidity: mean(&humidity),
+        })
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub struct Client {
+    http: reqwest::blocking::Client,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client {
+            http: api::http_client(api::USER_AGENT, &Default::default()).unwrap_or_default(),
+        }
+    }
+
+    /// Every day of the reference period at `coord`.
+    pub fn daily(&self, coord: &Coord) -> Result<Daily, api::Error> {
+        let network = |e: reqwest::Error| api::Error::Network(e.without_url());
+        let params = [
+            ("latitude", coord.lat.to_string()),
+            ("longitude", coord.lon.to_string()),
+            ("start_date", format!("{}-01-01", FIRST_YEAR)),
+            ("end_date", format!("{}-12-31", LAST_YEAR)),
+            (
+                "daily",
+                String::from("temperature_2m_max,precipitation_sum,relative_humidity_2m_mean"),
+            ),
+            ("timezone", String::from("auto")),
+        ];
+        let response = self
+            .http
+            .get(format!("{}/v1/archive", BASE_URL))
+            .query(&params)
+            .send()
+            .map_err(network)?;
+        let status = response.status();
+        let body = response.text().map_err(network)?;
+
+        if !status.is_success() {
+            let message = match serde_json::from_str::<ErrorBody>(&body) {
+                Ok(body) => body.reason,
+                Err(_) => status
+                    .canonical_reason()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            };
+            return Err(api::Error::Api {
+                status: status.as_u16(),
+                error: ApiError {
+                    cod: status.as_str().to_string(),
+                    message,
+                },
+            });
+        }
+
+        let archive: Archive = serde_json::from_str(&body).map_err(api::Error::Decode)?;
+        Ok(archive.daily)
+    }
+}
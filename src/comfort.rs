@@ -0,0 +1,88 @@
+//! How close the weather comes to the conditions someone likes best, as a
+//! score out of 100 for `--score`.
+
+use crate::api::{ForecastEntry, WeatherResponse};
+use crate::config::Comfort;
+use crate::forecast::Day;
+
+/// Points lost per °C outside the temperature range.
+const PER_DEGREE: f64 = 5.0;
+/// Points lost per m/s over the wind limit.
+const PER_MPS: f64 = 4.0;
+/// Points lost per percentage point over the humidity limit.
+const PER_PERCENT: f64 = 1.0;
+
+/// Where the conditions fall short of the profile, and by how much: °C,
+/// m/s or percentage points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Miss {
+    TooCold(f64),
+    TooWarm(f64),
+    TooWindy(f64),
+    TooHumid(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Score {
+    /// 100 when every condition is within the profile.
+    pub value: u8,
+    pub misses: Vec<Miss>,
+}
+
+impl Score {
+    pub fn new(profile: &Comfort, temp: f64, wind: f64, humidity: f64) -> Score {
+        let mut misses = Vec::new();
+        if temp < profile.min_temp {
+            misses.push(Miss::TooCold(profile.min_temp - temp));
+        } else if temp > profile.max_temp {
+            misses.push(Miss::TooWarm(temp - profile.max_temp));
+        }
+        if wind > profile.max_wind {
+            misses.push(Miss::TooWindy(wind - profile.max_wind));
+        }
+        if humidity > profile.max_humidity {
+            misses.push(Miss::TooHumid(humidity - profile.max_humidity));
+        }
+
+        let penalty: f64 = misses
+            .iter()
+            .map(|miss| match *miss {
+                Miss::TooCold(by) | Miss::TooWarm(by) => by * PER_DEGREE,
+                Miss::TooWindy(by) => by * PER_MPS,
+                Miss::TooHumid(by) => by * PER_PERCENT,
+            })
+            .sum();
+        Score {
+            value: (100.0 - penalty).clamp(0.0, 100.0).round() as u8,
+            misses,
+        }
+    }
+
+    pub fn current(profile: &Comfort, weather: &WeatherResponse) -> Score {
+        Score::new(
+            profile,
+            weather.main.temp,
+            weather.wind.speed,
+            weather.main.humidity,
+        )
+    }
+
+    fn entry(profile: &Comfort, entry: &ForecastEntry) -> Score {
+        let wind = entry.wind.as_ref().map_or(0.0, |wind| wind.speed);
+        Score::new(profile, entry.main.temp, wind, entry.main.humidity)
+    }
+}
+
+/// The average over the day's forecast steps, so one bad hour costs less
+/// than a bad afternoon.
+pub fn day_score(profile: &Comfort, day: &Day) -> Option<u8> {
+    if day.entries.is_empty() {
+        return None;
+    }
+    let total: f64 = day
+        .entries
+        .iter()
+        .map(|entry| f64::from(Score::entry(profile, entry).value))
+        .sum();
+    Some((total / day.entries.len() as f64).round() as u8)
+}
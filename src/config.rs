@@ -0,0 +1,801 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, fmt, path::Path, path::PathBuf};
+
+use crate::access;
+use crate::api;
+use crate::credentials::{Auth, ClientCredentials};
+use crate::energy;
+use crate::error::{Context, Error};
+use crate::locale::Locale;
+use crate::rule::{self, Expr};
+use crate::storage::{self, Schema};
+use crate::theme::Theme;
+use crate::tint;
+use crate::units::Preferences;
+use crate::usage;
+
+const CONFIG_DIR: &str = "cli_weather";
+const CONFIG_FILE: &str = "config.toml";
+
+pub const SCHEMA: Schema = Schema {
+    name: "config",
+    migrations: &[],
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Used when no key is given on the command line, in the environment or
+    /// in the OS keyring.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Language for place names and descriptions, e.g. "de".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    pub notify: Thresholds,
+    pub cache: CacheSettings,
+    pub usage: UsageSettings,
+    pub breaker: BreakerSettings,
+    pub theme: ThemeSettings,
+    pub tracing: TracingSettings,
+    pub http: HttpSettings,
+    pub auth: AuthSettings,
+    pub uv: UvSettings,
+    pub units: UnitSettings,
+    pub comfort: Comfort,
+    pub matrix: MatrixSettings,
+    pub gotify: GotifySettings,
+    pub ntfy: NtfySettings,
+    pub tint: TintSettings,
+    pub hooks: HookSettings,
+    pub monitor: MonitorSettings,
+    pub energy: EnergySettings,
+    pub serve: ServeSettings,
+    pub retention: RetentionSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// Seconds a cached response is reused before fetching again, for
+    /// responses without cache headers of their own.
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings { ttl_secs: 600 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct UsageSettings {
+    /// API calls per day to stay under; a warning is printed at 90%. The free
+    /// One Call plan allows 1,000. 0 turns the warning off.
+    pub daily_limit: u32,
+    /// Requests a minute this program sends at most; the free plan allows
+    /// 60. Ones over it wait, or fail when the wait is long. 0 for no limit.
+    pub per_minute: u32,
+}
+
+impl Default for UsageSettings {
+    fn default() -> Self {
+        UsageSettings {
+            daily_limit: 1000,
+            per_minute: 60,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ThemeSettings {
+    /// One of the built-in themes: default, solarized or monochrome.
+    pub name: String,
+    /// Per-role overrides, e.g. `hot = "bold #ff5f00"` or `value = "cyan"`.
+    pub colors: BTreeMap<String, String>,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        ThemeSettings {
+            name: String::from("default"),
+            colors: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct UnitSettings {
+    /// celsius or fahrenheit.
+    pub temperature: String,
+    /// For wind speeds and gusts in the report: m/s, km/h, mph or kn.
+    pub wind: String,
+    /// For times of day: 24h or 12h.
+    pub clock: String,
+}
+
+impl Default for UnitSettings {
+    fn default() -> Self {
+        UnitSettings::from(Preferences::default())
+    }
+}
+
+impl From<Preferences> for UnitSettings {
+    fn from(preferences: Preferences) -> Self {
+        UnitSettings {
+            temperature: preferences.temperature.name().to_string(),
+            wind: preferences.wind.symbol().to_string(),
+            clock: preferences.clock.name().to_string(),
+        }
+    }
+}
+
+impl UnitSettings {
+    /// Checks each setting, naming the one that is wrong.
+    pub fn preferences(&self) -> Result<Preferences, Error> {
+        let field = |name: &str| format!("reading units.{} from the config file", name);
+        Ok(Preferences {
+            temperature: self
+                .temperature
+                .parse()
+                .map_err(Error::new)
+                .with_context(|| field("temperature"))?,
+            wind: self
+                .wind
+                .parse()
+                .map_err(Error::new)
+                .with_context(|| field("wind"))?,
+            clock: self
+                .clock
+                .parse()
+                .map_err(Error::new)
+                .with_context(|| field("clock"))?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct UvSettings {
+    /// Whether the weather report asks One Call for the UV index. That
+    /// needs its own subscription and costs a call per report.
+    pub in_report: bool,
+}
+
+impl Default for UvSettings {
+    fn default() -> Self {
+        UvSettings { in_report: true }
+    }
+}
+
+/// What every request to the weather provider carries.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct HttpSettings {
+    /// Identifies the program; defaults to "cli_weather/<version>". Met.no
+    /// asks for a way to reach you, e.g. "cli_weather/0.1 you@example.com".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Extra headers, e.g. `Authorization = "Bearer ..."` for a gateway in
+    /// front of the provider. They are not sent to what3words.
+    pub headers: BTreeMap<String, String>,
+    /// Print each request to the provider and how it went to stderr.
+    pub log: bool,
+    /// Where to send requests instead of OpenWeatherMap, e.g. a gateway
+    /// that speaks its API, at "https://weather.example.com".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+}
+
+/// How requests to the weather provider prove who sends them.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AuthSettings {
+    /// "appid" sends the API key as OpenWeatherMap's `appid` parameter.
+    /// "header" sends it in the `header` header, as a bearer token when that
+    /// is Authorization. "signed" adds a timestamp and an HMAC signature
+    /// made with `secret`. "oauth" uses it as the client secret of
+    /// `client_id`, trading it at `token_url` for a bearer token.
+    pub scheme: String,
+    pub header: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Asked for with the token, if the endpoint needs one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        AuthSettings {
+            scheme: String::from("appid"),
+            header: String::from("X-Api-Key"),
+            secret: None,
+            token_url: None,
+            client_id: None,
+            scope: None,
+        }
+    }
+}
+
+impl AuthSettings {
+    /// The scheme for `api_key`, failing when a setting it needs is missing.
+    pub fn auth(&self, api_key: &str) -> Result<Auth, Error> {
+        let missing = |key: &str| {
+            Error::new(format!("auth.{} is not set", key))
+                .hint(format!("the {} scheme needs it", self.scheme))
+        };
+        if self.scheme == "header"
+            && reqwest::header::HeaderName::from_bytes(self.header.as_bytes()).is_err()
+        {
+            return Err(Error::new(format!(
+                "auth.header: '{}' is not a valid header name",
+                self.header
+            )));
+        }
+        let auth = match self.scheme.as_str() {
+            "appid" => Auth::QueryKey {
+                param: "appid",
+                key: api_key.to_string(),
+            },
+            "header" if self.header.eq_ignore_ascii_case("authorization") => Auth::Header {
+                name: self.header.clone(),
+                value: format!("Bearer {}", api_key),
+            },
+            "header" => Auth::Header {
+                name: self.header.clone(),
+                value: api_key.to_string(),
+            },
+            "signed" => Auth::SignedQuery {
+                key_param: "appid",
+                key: api_key.to_string(),
+                secret: self.secret.clone().ok_or_else(|| missing("secret"))?,
+            },
+            "oauth" => Auth::ClientCredentials(ClientCredentials::new(
+                self.token_url
+                    .as_deref()
+                    .ok_or_else(|| missing("token_url"))?,
+                self.client_id
+                    .as_deref()
+                    .ok_or_else(|| missing("client_id"))?,
+                api_key,
+                self.scope.as_deref(),
+            )),
+            other => {
+                return Err(
+                    Error::new(format!("auth.scheme: unknown scheme '{}'", other))
+                        .hint("use appid, header, signed or oauth"),
+                )
+            }
+        };
+        Ok(auth)
+    }
+}
+
+/// The room `check --matrix` and `watch --matrix` post to.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct MatrixSettings {
+    /// The homeserver's base URL, e.g. "https://matrix.org".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homeserver: Option<String>,
+    /// A room ID such as "!abc123:matrix.org", which the account has joined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    /// Used when MATRIX_ACCESS_TOKEN is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+}
+
+/// The server `--gotify` pushes to.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct GotifySettings {
+    /// The server's base URL, e.g. "https://gotify.example.com".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// An application token; used when GOTIFY_TOKEN is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// The topic `--ntfy` publishes to.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct NtfySettings {
+    /// Defaults to https://ntfy.sh.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// For protected topics; used when NTFY_TOKEN is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// Commands for `watch` to run; see `hooks`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Run whenever the condition changes category, e.g. from clear to
+    /// rain, and once on the first refresh.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_change: Vec<String>,
+}
+
+/// Places `check --group` and `watch --group` follow together, such as
+/// where each of the family lives.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct MonitorSettings {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub places: Vec<MonitorPlace>,
+}
+
+/// One `[[monitor.places]]` entry. One that names a channel sends its
+/// alerts there, and only there; the others go wherever the flags say.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct MonitorPlace {
+    /// "Lyon, FR" or coordinates such as "45.76,4.84".
+    pub place: String,
+    /// Shown with the city in alerts, e.g. "Grandma".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// A room on the `[matrix]` homeserver, which the account has joined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matrix_room: Option<String>,
+    /// A topic on the `[ntfy]` server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntfy_topic: Option<String>,
+    /// An application token on the `[gotify]` server, so the alerts show
+    /// under an application of their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gotify_token: Option<String>,
+}
+
+impl MonitorPlace {
+    pub fn has_channels(&self) -> bool {
+        self.matrix_room.is_some() || self.ntfy_topic.is_some() || self.gotify_token.is_some()
+    }
+}
+
+/// How long stored data is kept, so years of use do not fill the disk.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// Days of API calls counted one by one for `usage`; older days are
+    /// folded into monthly totals, which are kept for good. 0 keeps every
+    /// day.
+    pub usage_days: u32,
+    /// Days a place's cached weather is kept after it was last fetched.
+    /// `serve` and `watch` clear out older entries once a day; 0 keeps them.
+    pub cache_days: u32,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        RetentionSettings {
+            usage_days: usage::KEEP_DAYS,
+            cache_days: 30,
+        }
+    }
+}
+
+/// Who may use `serve`; see `access`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ServeSettings {
+    /// With none, anyone who can reach the server may use it.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<ClientSettings>,
+}
+
+/// One `[[serve.clients]]` entry.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct ClientSettings {
+    /// Shown in errors and the server log, e.g. "kitchen".
+    pub name: String,
+    /// What the client sends as `Authorization: Bearer ...`.
+    pub token: String,
+    /// Requests a minute; 0 for no limit.
+    pub per_minute: u32,
+    /// Places it may ask about, e.g. "Paris, FR" or "48.85,2.35"; empty for
+    /// anywhere.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub places: Vec<String>,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        ClientSettings {
+            name: String::new(),
+            token: String::new(),
+            per_minute: 60,
+            places: Vec::new(),
+        }
+    }
+}
+
+/// The grid carbon intensity hint in the report; see `energy`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct EnergySettings {
+    /// Whether the report says when the grid is greenest for running the
+    /// dryer. Covers Great Britain only, and costs a forecast call.
+    pub in_report: bool,
+    /// Your postcode or its district, e.g. "RG10", for your region's
+    /// forecast and generation mix; without it the national average.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postcode: Option<String>,
+}
+
+/// Tinting the terminal background by the weather after a report.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct TintSettings {
+    pub enabled: bool,
+    /// °C below which the report tints the background cold.
+    pub cold_below: f64,
+    /// °C above which it tints it hot.
+    pub hot_above: f64,
+    pub cold_color: String,
+    pub hot_color: String,
+    /// A command to run instead of sending OSC 11, for terminals that are
+    /// set up another way. It gets CLI_WEATHER_TINT (cold, hot or neutral),
+    /// CLI_WEATHER_TEMP in °C and CLI_WEATHER_COLOR, empty when neutral.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook: Option<String>,
+}
+
+impl Default for TintSettings {
+    fn default() -> Self {
+        TintSettings {
+            enabled: false,
+            cold_below: 5.0,
+            hot_above: 28.0,
+            cold_color: String::from("#141c2b"),
+            hot_color: String::from("#2b2114"),
+            hook: None,
+        }
+    }
+}
+
+/// Where `serve` and `watch` send OpenTelemetry spans, if anywhere.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct TracingSettings {
+    /// An OTLP/HTTP collector, e.g. "http://localhost:4318". The standard
+    /// OTEL_EXPORTER_OTLP_ENDPOINT variable takes precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// When to stop calling a provider that keeps failing.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct BreakerSettings {
+    /// Network or server errors in a row before the provider is skipped.
+    pub failures: u32,
+    /// Seconds to skip it for before trying again.
+    pub cooldown_secs: u64,
+}
+
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        BreakerSettings {
+            failures: 3,
+            cooldown_secs: 300,
+        }
+    }
+}
+
+/// The weather someone likes best, which `--score` compares with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Comfort {
+    /// °C.
+    pub min_temp: f64,
+    /// °C.
+    pub max_temp: f64,
+    /// m/s.
+    pub max_wind: f64,
+    /// Percent relative humidity.
+    pub max_humidity: f64,
+}
+
+impl Default for Comfort {
+    fn default() -> Self {
+        Comfort {
+            min_temp: 18.0,
+            max_temp: 25.0,
+            max_wind: 5.0,
+            max_humidity: 70.0,
+        }
+    }
+}
+
+/// Conditions that `check` and `watch` report on.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Thresholds {
+    /// °C; anything colder triggers.
+    pub min_temp: f64,
+    /// m/s; anything windier triggers.
+    pub max_wind: f64,
+    /// How far ahead to look for rain. 0 skips the forecast request.
+    pub rain_within_hours: u32,
+    /// Whether to query One Call for active weather alerts.
+    pub alerts: bool,
+    /// Conditions of your own by name, such as `gale = "wind > 12 for 2h"`;
+    /// see `rule` for what they can say.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, String>,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            min_temp: 0.0,
+            max_wind: 15.0,
+            rain_within_hours: 3,
+            alerts: false,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl Thresholds {
+    /// The rules parsed, by name; the first that cannot be read fails, with
+    /// where in it the problem is.
+    pub fn rules(&self) -> Result<Vec<(&str, Expr)>, Error> {
+        self.rules
+            .iter()
+            .map(|(name, source)| {
+                rule::parse(source)
+                    .map(|expr| (name.as_str(), expr))
+                    .map_err(|e| {
+                        Error::new(e)
+                            .context(format!(
+                                "reading the rule notify.rules.{} = \"{}\"",
+                                name, source
+                            ))
+                            .hint(format!("rules compare {}", rule::FIELDS_HINT))
+                    })
+            })
+            .collect()
+    }
+
+    /// Whether telling the rules apart needs the forecast.
+    pub fn needs_forecast(&self) -> bool {
+        self.rain_within_hours > 0
+            || self
+                .rules()
+                .is_ok_and(|rules| rules.iter().any(|(_, expr)| expr.needs_forecast()))
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+/// Reads the config file. When there is none yet, one is written with the
+/// language, units and clock guessed from the system locale.
+pub fn load() -> Result<Config, Error> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+
+    let config: Option<Config> = storage::load(&path, &SCHEMA)
+        .with_context(|| format!("loading config from {}", path.display()))?;
+    if let Some(config) = config {
+        config
+            .notify
+            .rules()
+            .with_context(|| format!("loading config from {}", path.display()))?;
+        return Ok(config);
+    }
+
+    let config = Config::for_locale(&Locale::from_env());
+    match storage::save(&path, &SCHEMA, &config) {
+        Ok(()) => eprintln!(
+            "Created {} with defaults for this system's locale",
+            path.display()
+        ),
+        Err(e) => eprintln!(
+            "Warning: could not write default config to {}: {}",
+            path.display(),
+            e
+        ),
+    }
+    Ok(config)
+}
+
+impl Config {
+    /// The defaults, with language and units for `locale`.
+    pub fn for_locale(locale: &Locale) -> Config {
+        Config {
+            lang: locale.language.clone(),
+            units: UnitSettings::from(locale.preferences()),
+            ..Config::default()
+        }
+    }
+}
+
+/// Reads a config file without falling back to defaults, and checks it
+/// the way startup would.
+pub fn check(path: &Path) -> Result<Config, Error> {
+    let config: Config = storage::load(path, &SCHEMA)?
+        .ok_or_else(|| Error::new(format!("{} does not exist", path.display())))?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Writes the config file, creating it if needed.
+pub fn save(config: &Config) -> Result<PathBuf, Error> {
+    let path = path().ok_or_else(|| Error::new("no config directory on this system"))?;
+    storage::save(&path, &SCHEMA, config)
+        .with_context(|| format!("saving config to {}", path.display()))?;
+    Ok(path)
+}
+
+/// A key given to `config get` or `config set` that cannot be used.
+#[derive(Debug, PartialEq)]
+pub enum KeyError {
+    Unknown(String),
+    /// A section such as `units`, which has keys of its own.
+    Table(String),
+    Invalid {
+        key: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyError::Unknown(key) => write!(f, "unknown config key '{}'", key),
+            KeyError::Table(key) => write!(f, "'{}' is a section; set one of its keys", key),
+            KeyError::Invalid { key, reason } => {
+                write!(f, "invalid value for '{}': {}", key, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl Config {
+    /// Checks what serde cannot: units, the theme, the HTTP headers and
+    /// auth scheme, the tint colors, the serve clients and the postcode.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.units.preferences()?;
+        self.notify.rules()?;
+        Theme::new(&self.theme.name, &self.theme.colors)
+            .map_err(Error::new)
+            .context("loading the color theme")?;
+        let user_agent = self.http.user_agent.as_deref().unwrap_or(api::USER_AGENT);
+        api::http_client(user_agent, &self.http.headers)
+            .map_err(Error::new)
+            .context("reading the [http] section")?;
+        self.auth.auth("")?;
+        tint::validate(&self.tint).map_err(Error::new)?;
+        access::Clients::new(&self.serve.clients).map_err(Error::new)?;
+        if let Some(i) = self
+            .monitor
+            .places
+            .iter()
+            .position(|p| p.place.trim().is_empty())
+        {
+            return Err(Error::new(format!(
+                "monitor.places: entry {} has no place",
+                i + 1
+            )));
+        }
+        if let Some(postcode) = &self.energy.postcode {
+            energy::district(postcode)
+                .map_err(|e| Error::new(format!("energy.postcode: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Every setting that has a value, by dotted key such as `units.wind`.
+    pub fn entries(&self) -> Vec<(String, Value)> {
+        let mut entries = Vec::new();
+        flatten("", &self.to_value(), &mut entries);
+        entries
+    }
+
+    /// The value at a dotted key; sections are tables.
+    pub fn get(&self, key: &str) -> Result<Value, KeyError> {
+        key.split('.')
+            .try_fold(&self.to_value(), |value, part| value.get(part))
+            .cloned()
+            .ok_or_else(|| KeyError::Unknown(key.to_string()))
+    }
+
+    /// A copy with `key` set to `value`, which is read as the type the key
+    /// already has, e.g. a number for `cache.ttl_secs`.
+    pub fn set(&self, key: &str, value: &str) -> Result<Config, KeyError> {
+        let invalid = |reason: String| KeyError::Invalid {
+            key: key.to_string(),
+            reason,
+        };
+        let mut doc = self.to_value();
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let Some(leaf) = parts.pop().filter(|leaf| !leaf.is_empty()) else {
+            return Err(KeyError::Unknown(key.to_string()));
+        };
+
+        let mut table = doc.as_object_mut().expect("config serializes to a table");
+        for part in parts {
+            table = table
+                .entry(part)
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .ok_or_else(|| KeyError::Unknown(key.to_string()))?;
+        }
+        let parsed = match table.get(leaf) {
+            Some(Value::Object(_)) => return Err(KeyError::Table(key.to_string())),
+            Some(Value::Bool(_)) => value
+                .parse::<bool>()
+                .map(Value::Bool)
+                .map_err(|_| invalid(String::from("expected true or false")))?,
+            Some(Value::Number(n)) if n.is_u64() => value
+                .parse::<u64>()
+                .map(Value::from)
+                .map_err(|_| invalid(String::from("expected a whole number")))?,
+            Some(Value::Number(_)) => value
+                .parse::<f64>()
+                .map(Value::from)
+                .map_err(|_| invalid(String::from("expected a number")))?,
+            _ => Value::from(value),
+        };
+        table.insert(leaf.to_string(), parsed.clone());
+
+        let config: Config = serde_json::from_value(doc).map_err(|e| invalid(e.to_string()))?;
+        // Keys serde does not know are dropped rather than rejected.
+        match config.get(key) {
+            Ok(stored) if stored == parsed => Ok(config),
+            _ => Err(KeyError::Unknown(key.to_string())),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        serde_json::to_value(self).expect("config always serializes")
+    }
+}
+
+fn flatten(prefix: &str, value: &Value, entries: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, entries);
+            }
+        }
+        value => entries.push((prefix.to_string(), value.clone())),
+    }
+}
+
+/// How `config get` prints a value: strings bare, numbers and booleans as
+/// they are written in the file.
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,259 @@
+//! Parsing of typed coordinates: decimal degrees (`48.8566, 2.3522`),
+//! degrees-minutes-seconds (`48°51'24"N 2°21'08"E`), geo URIs
+//! (`geo:48.8566,2.3522`, RFC 5870) and full plus codes (`8FW4V75V+8Q`).
+
+use std::fmt;
+
+use crate::api::Coord;
+
+#[derive(Debug, PartialEq)]
+pub struct CoordError(String);
+
+impl fmt::Display for CoordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CoordError {}
+
+fn invalid<T>(message: impl Into<String>) -> Result<T, CoordError> {
+    Err(CoordError(message.into()))
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    Latitude,
+    Longitude,
+}
+
+impl Axis {
+    fn limit(self) -> f64 {
+        match self {
+            Axis::Latitude => 90.0,
+            Axis::Longitude => 180.0,
+        }
+    }
+
+    /// The hemisphere letters for this axis, positive first.
+    fn hemispheres(self) -> [char; 2] {
+        match self {
+            Axis::Latitude => ['N', 'S'],
+            Axis::Longitude => ['E', 'W'],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Axis::Latitude => "latitude",
+            Axis::Longitude => "longitude",
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Coord, CoordError> {
+    let input = input.trim();
+
+    if let Some(uri) = input
+        .get(..4)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("geo:"))
+        .map(|_| &input[4..])
+    {
+        return parse_geo_uri(uri);
+    }
+    if is_plus_code(input) {
+        return parse_plus_code(input);
+    }
+
+    let (lat, lon) = split(input)?;
+    Ok(Coord {
+        lat: parse_angle(lat, Axis::Latitude)?,
+        lon: parse_angle(lon, Axis::Longitude)?,
+    })
+}
+
+/// `geo:lat,lon[,alt][;params]`; altitude and parameters are ignored.
+fn parse_geo_uri(uri: &str) -> Result<Coord, CoordError> {
+    let coordinates = uri.split(';').next().unwrap_or_default();
+    let mut parts = coordinates.split(',');
+
+    match (parts.next(), parts.next()) {
+        (Some(lat), Some(lon)) => Ok(Coord {
+            lat: parse_decimal(lat, Axis::Latitude)?,
+            lon: parse_decimal(lon, Axis::Longitude)?,
+        }),
+        _ => invalid(format!("'geo:{}' is not a valid geo URI", uri)),
+    }
+}
+
+/// Splits on a comma, after the latitude's hemisphere letter, or on
+/// whitespace between two plain numbers.
+fn split(input: &str) -> Result<(&str, &str), CoordError> {
+    if let Some((lat, lon)) = input.split_once(',') {
+        return Ok((lat, lon));
+    }
+    if let Some(i) = input.find(|c: char| matches!(c.to_ascii_uppercase(), 'N' | 'S')) {
+        let (lat, lon) = input.split_at(i + 1);
+        if !lon.trim().is_empty() {
+            return Ok((lat, lon));
+        }
+    }
+
+    let mut parts = input.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(lat), Some(lon), None) => Ok((lat, lon)),
+        _ => invalid(format!(
+            "could not read '{}' as coordinates; try '48.85,2.35' or '48°51'N 2°21'E'",
+            input
+        )),
+    }
+}
+
+fn parse_decimal(text: &str, axis: Axis) -> Result<f64, CoordError> {
+    match text.trim().parse::<f64>() {
+        Ok(value) => check_range(value, axis),
+        Err(_) => invalid(format!("'{}' is not a valid {}", text.trim(), axis.name())),
+    }
+}
+
+fn check_range(value: f64, axis: Axis) -> Result<f64, CoordError> {
+    if value.is_finite() && value.abs() <= axis.limit() {
+        Ok(value)
+    } else {
+        invalid(format!(
+            "{} {} is outside ±{}°",
+            axis.name(),
+            value,
+            axis.limit()
+        ))
+    }
+}
+
+/// Decimal degrees or up to three numbers (degrees, minutes, seconds) with
+/// an optional sign or hemisphere letter, e.g. `-2.35`, `48°51'24"N`, `2 21 8 E`.
+fn parse_angle(text: &str, axis: Axis) -> Result<f64, CoordError> {
+    let text = text.trim();
+    let [positive, negative] = axis.hemispheres();
+
+    let mut sign = 1.0;
+    let mut numbers = Vec::new();
+    let mut current = String::new();
+
+    for c in text.chars() {
+        match c.to_ascii_uppercase() {
+            '0'..='9' | '.' => current.push(c),
+            '-' if numbers.is_empty() && current.is_empty() => sign = -sign,
+            '+' if numbers.is_empty() && current.is_empty() => {}
+            letter if letter == positive => {}
+            letter if letter == negative => sign = -sign,
+            '°' | '\'' | '"' | '′' | '″' | ' ' => {
+                if !current.is_empty() {
+                    numbers.push(std::mem::take(&mut current));
+                }
+            }
+            _ => return invalid(format!("unexpected '{}' in {} '{}'", c, axis.name(), text)),
+        }
+    }
+    if !current.is_empty() {
+        numbers.push(current);
+    }
+
+    let parsed: Vec<f64> = numbers
+        .iter()
+        .map(|n| n.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .or_else(|_| invalid(format!("'{}' is not a valid {}", text, axis.name())))?;
+
+    let degrees = match parsed.as_slice() {
+        [degrees] => *degrees,
+        [degrees, minutes] if *minutes < 60.0 => degrees + minutes / 60.0,
+        [degrees, minutes, seconds] if *minutes < 60.0 && *seconds < 60.0 => {
+            degrees + minutes / 60.0 + seconds / 3600.0
+        }
+        _ => return invalid(format!("'{}' is not a valid {}", text, axis.name())),
+    };
+
+    check_range(sign * degrees, axis)
+}
+
+/// Open Location Code digits, in order of value.
+const PLUS_CODE_ALPHABET: &str = "23456789CFGHJMPQRVWX";
+/// Digits before the `+` in a full code.
+const PLUS_CODE_SEPARATOR_POSITION: usize = 8;
+/// Digits after which the code switches from pairs to a 4x5 grid.
+const PLUS_CODE_PAIR_LENGTH: usize = 10;
+
+fn is_plus_code(input: &str) -> bool {
+    input.matches('+').count() == 1
+        && input
+            .chars()
+            .all(|c| c == '+' || c == '0' || plus_code_digit(c).is_some())
+}
+
+fn plus_code_digit(c: char) -> Option<usize> {
+    PLUS_CODE_ALPHABET.find(c.to_ascii_uppercase())
+}
+
+/// The centre of the area a full plus code names.
+fn parse_plus_code(code: &str) -> Result<Coord, CoordError> {
+    let separator = code.find('+').unwrap_or_default();
+    if separator < PLUS_CODE_SEPARATOR_POSITION {
+        return invalid(format!(
+            "'{}' is a short plus code; use the full code, e.g. 8FW4V75V+8Q",
+            code
+        ));
+    }
+    if separator > PLUS_CODE_SEPARATOR_POSITION {
+        return invalid(format!("'{}' is not a valid plus code", code));
+    }
+
+    // Zero padding fills whole pairs before the separator, and nothing follows it.
+    let digits: String = code.chars().filter(|&c| c != '+').collect();
+    let padded = digits.find('0');
+    if let Some(start) = padded {
+        let padding_ok = start % 2 == 0
+            && start >= 2
+            && digits.len() == PLUS_CODE_SEPARATOR_POSITION
+            && digits[start..].chars().all(|c| c == '0');
+        if !padding_ok {
+            return invalid(format!("'{}' is not a valid plus code", code));
+        }
+    } else if digits.len() == PLUS_CODE_SEPARATOR_POSITION + 1 {
+        return invalid(format!(
+            "'{}' is not a valid plus code; it needs two digits after the '+'",
+            code
+        ));
+    }
+
+    let digits: Vec<usize> = digits.chars().filter_map(plus_code_digit).collect();
+    let base = PLUS_CODE_ALPHABET.len() as f64;
+    let (mut lat, mut lon) = (0.0, 0.0);
+    let mut resolution = base;
+    let (mut lat_size, mut lon_size) = (resolution, resolution);
+
+    for pair in digits.chunks(2).take(PLUS_CODE_PAIR_LENGTH / 2) {
+        lat += pair[0] as f64 * resolution;
+        if let Some(&digit) = pair.get(1) {
+            lon += digit as f64 * resolution;
+        }
+        lat_size = resolution;
+        lon_size = resolution;
+        resolution /= base;
+    }
+    for &digit in digits.iter().skip(PLUS_CODE_PAIR_LENGTH) {
+        lat_size /= 5.0;
+        lon_size /= 4.0;
+        lat += (digit / 4) as f64 * lat_size;
+        lon += (digit % 4) as f64 * lon_size;
+    }
+
+    Ok(Coord {
+        lat: check_range(lat + lat_size / 2.0 - 90.0, Axis::Latitude)?,
+        lon: check_range(lon + lon_size / 2.0 - 180.0, Axis::Longitude)?,
+    })
+}
+
+/// A `geo:` URI that map apps on phones and desktops can open.
+pub fn geo_uri(coord: &Coord) -> String {
+    format!("geo:{:.5},{:.5}", coord.lat, coord.lon)
+}
@@ -0,0 +1,565 @@
+use std::fmt;
+
+/// A country that is neither a known alpha-2 code nor a known name.
+#[derive(Debug, PartialEq)]
+pub struct UnknownCountry {
+    pub input: String,
+    /// Closest codes by edit distance, best first.
+    pub suggestions: Vec<&'static str>,
+}
+
+impl fmt::Display for UnknownCountry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown country '{}'", self.input)?;
+
+        let suggestions: Vec<String> = self
+            .suggestions
+            .iter()
+            .map(|code| format!("{} ({})", code, name(code).unwrap_or_default()))
+            .collect();
+        match suggestions.as_slice() {
+            [] => write!(f, "; use a two-letter ISO 3166 code such as FR"),
+            [only] => write!(f, "; did you mean {}?", only),
+            many => write!(f, "; did you mean one of {}?", many.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for UnknownCountry {}
+
+pub fn name(code: &str) -> Option<&'static str> {
+    COUNTRIES
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, name)| *name)
+}
+
+/// Turns a code ("fr") or a name ("France") into an upper-case alpha-2 code.
+pub fn resolve(input: &str) -> Result<&'static str, UnknownCountry> {
+    let input = input.trim();
+    let wanted = input.to_lowercase();
+
+    if let Some((code, _)) = COUNTRIES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(input))
+    {
+        return Ok(code);
+    }
+    if let Some((_, code)) = names().find(|(name, _)| name.to_lowercase() == wanted) {
+        return Ok(code);
+    }
+
+    Err(UnknownCountry {
+        input: input.to_string(),
+        suggestions: suggest(&wanted),
+    })
+}
+
+fn names() -> impl Iterator<Item = (&'static str, &'static str)> {
+    COUNTRIES
+        .iter()
+        .map(|(code, name)| (*name, *code))
+        .chain(ALIASES.iter().copied())
+}
+
+/// Codes are compared against codes and names against names, since any two
+/// letters are within edit distance 2 of every code.
+fn suggest(wanted: &str) -> Vec<&'static str> {
+    const MAX_SUGGESTIONS: usize = 3;
+
+    let mut scored: Vec<(usize, &'static str)> = if wanted.chars().count() <= 2 {
+        COUNTRIES
+            .iter()
+            .map(|(code, _)| (edit_distance(wanted, &code.to_lowercase()), *code))
+            .filter(|(distance, _)| *distance <= 1)
+            .collect()
+    } else {
+        let tolerance = (wanted.chars().count() / 3).max(1);
+        names()
+            .map(|(name, code)| (edit_distance(wanted, &name.to_lowercase()), code))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .collect()
+    };
+
+    scored.sort();
+    let mut codes: Vec<&'static str> = Vec::new();
+    for (_, code) in scored {
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes.truncate(MAX_SUGGESTIONS);
+    codes
+}
+
+/// Levenshtein distance over chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// ISO 3166-1 alpha-2 codes with their common English names.
+pub const COUNTRIES: &[(&str, &str)] = &[
+    ("AD", "Andorra"),
+    ("AE", "United Arab Emirates"),
+    ("AF", "Afghanistan"),
+    ("AG", "Antigua and Barbuda"),
+    ("AI", "Anguilla"),
+    ("AL", "Albania"),
+    ("AM", "Armenia"),
+    ("AO", "Angola"),
+    ("AQ", "Antarctica"),
+    ("AR", "Argentina"),
+    ("AS", "American Samoa"),
+    ("AT", "Austria"),
+    ("AU", "Australia"),
+    ("AW", "Aruba"),
+    ("AX", "Åland Islands"),
+    ("AZ", "Azerbaijan"),
+    ("BA", "Bosnia and Herzegovina"),
+    ("BB", "Barbados"),
+    ("BD", "Bangladesh"),
+    ("BE", "Belgium"),
+    ("BF", "Burkina Faso"),
+    ("BG", "Bulgaria"),
+    ("BH", "Bahrain"),
+    ("BI", "Burundi"),
+    ("BJ", "Benin"),
+    ("BL", "Saint Barthélemy"),
+    ("BM", "Bermuda"),
+    ("BN", "Brunei Darussalam"),
+    ("BO", "Bolivia"),
+    ("BQ", "Bonaire, Sint Eustatius and Saba"),
+    ("BR", "Brazil"),
+    ("BS", "Bahamas"),
+    ("BT", "Bhutan"),
+    ("BV", "Bouvet Island"),
+    ("BW", "Botswana"),
+    ("BY", "Belarus"),
+    ("BZ", "Belize"),
+    ("CA", "Canada"),
+    ("CC", "Cocos (Keeling) Islands"),
+    ("CD", "Congo, The Democratic Republic of the"),
+    ("CF", "Central African Republic"),
+    ("CG", "Congo"),
+    ("CH", "Switzerland"),
+    ("CI", "Côte d'Ivoire"),
+    ("CK", "Cook Islands"),
+    ("CL", "Chile"),
+    ("CM", "Cameroon"),
+    ("CN", "China"),
+    ("CO", "Colombia"),
+    ("CR", "Costa Rica"),
+    ("CU", "Cuba"),
+    ("CV", "Cabo Verde"),
+    ("CW", "Curaçao"),
+    ("CX", "Christmas Island"),
+    ("CY", "Cyprus"),
+    ("CZ", "Czechia"),
+    ("DE", "Germany"),
+    ("DJ", "Djibouti"),
+    ("DK", "Denmark"),
+    ("DM", "Dominica"),
+    ("DO", "Dominican Republic"),
+    ("DZ", "Algeria"),
+    ("EC", "Ecuador"),
+    ("EE", "Estonia"),
+    ("EG", "Egypt"),
+    ("EH", "Western Sahara"),
+    ("ER", "Eritrea"),
+    ("ES", "Spain"),
+    ("ET", "Ethiopia"),
+    ("FI", "Finland"),
+    ("FJ", "Fiji"),
+    ("FK", "Falkland Islands (Malvinas)"),
+    ("FM", "Micronesia, Federated States of"),
+    ("FO", "Faroe Islands"),
+    ("FR", "France"),
+    ("GA", "Gabon"),
+    ("GB", "United Kingdom"),
+    ("GD", "Grenada"),
+    ("GE", "Georgia"),
+    ("GF", "French Guiana"),
+    ("GG", "Guernsey"),
+    ("GH", "Ghana"),
+    ("GI", "Gibraltar"),
+    ("GL", "Greenland"),
+    ("GM", "Gambia"),
+    ("GN", "Guinea"),
+    ("GP", "Guadeloupe"),
+    ("GQ", "Equatorial Guinea"),
+    ("GR", "Greece"),
+    ("GS", "South Georgia and the South Sandwich Islands"),
+    ("GT", "Guatemala"),
+    ("GU", "Guam"),
+    ("GW", "Guinea-Bissau"),
+    ("GY", "Guyana"),
+    ("HK", "Hong Kong"),
+    ("HM", "Heard Island and McDonald Islands"),
+    ("HN", "Honduras"),
+    ("HR", "Croatia"),
+    ("HT", "Haiti"),
+    ("HU", "Hungary"),
+    ("ID", "Indonesia"),
+    ("IE", "Ireland"),
+    ("IL", "Israel"),
+    ("IM", "Isle of Man"),
+    ("IN", "India"),
+    ("IO", "British Indian Ocean Territory"),
+    ("IQ", "Iraq"),
+    ("IR", "Iran"),
+    ("IS", "Iceland"),
+    ("IT", "Italy"),
+    ("JE", "Jersey"),
+    ("JM", "Jamaica"),
+    ("JO", "Jordan"),
+    ("JP", "Japan"),
+    ("KE", "Kenya"),
+    ("KG", "Kyrgyzstan"),
+    ("KH", "Cambodia"),
+    ("KI", "Kiribati"),
+    ("KM", "Comoros"),
+    ("KN", "Saint Kitts and Nevis"),
+    ("KP", "North Korea"),
+    ("KR", "South Korea"),
+    ("KW", "Kuwait"),
+    ("KY", "Cayman Islands"),
+    ("KZ", "Kazakhstan"),
+    ("LA", "Laos"),
+    ("LB", "Lebanon"),
+    ("LC", "Saint Lucia"),
+    ("LI", "Liechtenstein"),
+    ("LK", "Sri Lanka"),
+    ("LR", "Liberia"),
+    ("LS", "Lesotho"),
+    ("LT", "Lithuania"),
+    ("LU", "Luxembourg"),
+    ("LV", "Latvia"),
+    ("LY", "Libya"),
+    ("MA", "Morocco"),
+    ("MC", "Monaco"),
+    ("MD", "Moldova"),
+    ("ME", "Montenegro"),
+    ("MF", "Saint Martin (French part)"),
+    ("MG", "Madagascar"),
+    ("MH", "Marshall Islands"),
+    ("MK", "North Macedonia"),
+    ("ML", "Mali"),
+    ("MM", "Myanmar"),
+    ("MN", "Mongolia"),
+    ("MO", "Macao"),
+    ("MP", "Northern Mariana Islands"),
+    ("MQ", "Martinique"),
+    ("MR", "Mauritania"),
+    ("MS", "Montserrat"),
+    ("MT", "Malta"),
+    ("MU", "Mauritius"),
+    ("MV", "Maldives"),
+    ("MW", "Malawi"),
+    ("MX", "Mexico"),
+    ("MY", "Malaysia"),
+    ("MZ", "Mozambique"),
+    ("NA", "Namibia"),
+    ("NC", "New Caledonia"),
+    ("NE", "Niger"),
+    ("NF", "Norfolk Island"),
+    ("NG", "Nigeria"),
+    ("NI", "Nicaragua"),
+    ("NL", "Netherlands"),
+    ("NO", "Norway"),
+    ("NP", "Nepal"),
+    ("NR", "Nauru"),
+    ("NU", "Niue"),
+    ("NZ", "New Zealand"),
+    ("OM", "Oman"),
+    ("PA", "Panama"),
+    ("PE", "Peru"),
+    ("PF", "French Polynesia"),
+    ("PG", "Papua New Guinea"),
+    ("PH", "Philippines"),
+    ("PK", "Pakistan"),
+    ("PL", "Poland"),
+    ("PM", "Saint Pierre and Miquelon"),
+    ("PN", "Pitcairn"),
+    ("PR", "Puerto Rico"),
+    ("PS", "Palestine, State of"),
+    ("PT", "Portugal"),
+    ("PW", "Palau"),
+    ("PY", "Paraguay"),
+    ("QA", "Qatar"),
+    ("RE", "Réunion"),
+    ("RO", "Romania"),
+    ("RS", "Serbia"),
+    ("RU", "Russian Federation"),
+    ("RW", "Rwanda"),
+    ("SA", "Saudi Arabia"),
+    ("SB", "Solomon Islands"),
+    ("SC", "Seychelles"),
+    ("SD", "Sudan"),
+    ("SE", "Sweden"),
+    ("SG", "Singapore"),
+    ("SH", "Saint Helena, Ascension and Tristan da Cunha"),
+    ("SI", "Slovenia"),
+    ("SJ", "Svalbard and Jan Mayen"),
+    ("SK", "Slovakia"),
+    ("SL", "Sierra Leone"),
+    ("SM", "San Marino"),
+    ("SN", "Senegal"),
+    ("SO", "Somalia"),
+    ("SR", "Suriname"),
+    ("SS", "South Sudan"),
+    ("ST", "Sao Tome and Principe"),
+    ("SV", "El Salvador"),
+    ("SX", "Sint Maarten (Dutch part)"),
+    ("SY", "Syria"),
+    ("SZ", "Eswatini"),
+    ("TC", "Turks and Caicos Islands"),
+    ("TD", "Chad"),
+    ("TF", "French Southern Territories"),
+    ("TG", "Togo"),
+    ("TH", "Thailand"),
+    ("TJ", "Tajikistan"),
+    ("TK", "Tokelau"),
+    ("TL", "Timor-Leste"),
+    ("TM", "Turkmenistan"),
+    ("TN", "Tunisia"),
+    ("TO", "Tonga"),
+    ("TR", "Türkiye"),
+    ("TT", "Trinidad and Tobago"),
+    ("TV", "Tuvalu"),
+    ("TW", "Taiwan"),
+    ("TZ", "Tanzania"),
+    ("UA", "Ukraine"),
+    ("UG", "Uganda"),
+    ("UM", "United States Minor Outlying Islands"),
+    ("US", "United States"),
+    ("UY", "Uruguay"),
+    ("UZ", "Uzbekistan"),
+    ("VA", "Holy See (Vatican City State)"),
+    ("VC", "Saint Vincent and the Grenadines"),
+    ("VE", "Venezuela"),
+    ("VG", "Virgin Islands, British"),
+    ("VI", "Virgin Islands, U.S."),
+    ("VN", "Vietnam"),
+    ("VU", "Vanuatu"),
+    ("WF", "Wallis and Futuna"),
+    ("WS", "Samoa"),
+    ("YE", "Yemen"),
+    ("YT", "Mayotte"),
+    ("ZA", "South Africa"),
+    ("ZM", "Zambia"),
+    ("ZW", "Zimbabwe"),
+];
+
+/// Official and colloquial names that should also resolve to a code.
+pub const ALIASES: &[(&str, &str)] = &[
+    ("Principality of Andorra", "AD"),
+    ("Islamic Republic of Afghanistan", "AF"),
+    ("Republic of Albania", "AL"),
+    ("Republic of Armenia", "AM"),
+    ("Republic of Angola", "AO"),
+    ("Argentine Republic", "AR"),
+    ("Republic of Austria", "AT"),
+    ("Republic of Azerbaijan", "AZ"),
+    ("Republic of Bosnia and Herzegovina", "BA"),
+    ("People's Republic of Bangladesh", "BD"),
+    ("Kingdom of Belgium", "BE"),
+    ("Republic of Bulgaria", "BG"),
+    ("Kingdom of Bahrain", "BH"),
+    ("Republic of Burundi", "BI"),
+    ("Republic of Benin", "BJ"),
+    ("Bolivia, Plurinational State of", "BO"),
+    ("Plurinational State of Bolivia", "BO"),
+    ("Federative Republic of Brazil", "BR"),
+    ("Commonwealth of the Bahamas", "BS"),
+    ("Kingdom of Bhutan", "BT"),
+    ("Republic of Botswana", "BW"),
+    ("Republic of Belarus", "BY"),
+    ("Republic of the Congo", "CG"),
+    ("Swiss Confederation", "CH"),
+    ("Republic of Côte d'Ivoire", "CI"),
+    ("Republic of Chile", "CL"),
+    ("Republic of Cameroon", "CM"),
+    ("People's Republic of China", "CN"),
+    ("Republic of Colombia", "CO"),
+    ("Republic of Costa Rica", "CR"),
+    ("Republic of Cuba", "CU"),
+    ("Republic of Cabo Verde", "CV"),
+    ("Republic of Cyprus", "CY"),
+    ("Czech Republic", "CZ"),
+    ("Federal Republic of Germany", "DE"),
+    ("Republic of Djibouti", "DJ"),
+    ("Kingdom of Denmark", "DK"),
+    ("Commonwealth of Dominica", "DM"),
+    ("People's Democratic Republic of Algeria", "DZ"),
+    ("Republic of Ecuador", "EC"),
+    ("Republic of Estonia", "EE"),
+    ("Arab Republic of Egypt", "EG"),
+    ("the State of Eritrea", "ER"),
+    ("Kingdom of Spain", "ES"),
+    ("Federal Democratic Republic of Ethiopia", "ET"),
+    ("Republic of Finland", "FI"),
+    ("Republic of Fiji", "FJ"),
+    ("Federated States of Micronesia", "FM"),
+    ("French Republic", "FR"),
+    ("Gabonese Republic", "GA"),
+    ("United Kingdom of Great Britain and Northern Ireland", "GB"),
+    ("Republic of Ghana", "GH"),
+    ("Republic of the Gambia", "GM"),
+    ("Republic of Guinea", "GN"),
+    ("Republic of Equatorial Guinea", "GQ"),
+    ("Hellenic Republic", "GR"),
+    ("Republic of Guatemala", "GT"),
+    ("Republic of Guinea-Bissau", "GW"),
+    ("Republic of Guyana", "GY"),
+    ("Hong Kong Special Administrative Region of China", "HK"),
+    ("Republic of Honduras", "HN"),
+    ("Republic of Croatia", "HR"),
+    ("Republic of Haiti", "HT"),
+    ("Republic of Indonesia", "ID"),
+    ("State of Israel", "IL"),
+    ("Republic of India", "IN"),
+    ("Republic of Iraq", "IQ"),
+    ("Iran, Islamic Republic of", "IR"),
+    ("Islamic Republic of Iran", "IR"),
+    ("Republic of Iceland", "IS"),
+    ("Italian Republic", "IT"),
+    ("Hashemite Kingdom of Jordan", "JO"),
+    ("Republic of Kenya", "KE"),
+    ("Kyrgyz Republic", "KG"),
+    ("Kingdom of Cambodia", "KH"),
+    ("Republic of Kiribati", "KI"),
+    ("Union of the Comoros", "KM"),
+    ("Korea, Democratic People's Republic of", "KP"),
+    ("Democratic People's Republic of Korea", "KP"),
+    ("Korea, Republic of", "KR"),
+    ("State of Kuwait", "KW"),
+    ("Republic of Kazakhstan", "KZ"),
+    ("Lao People's Democratic Republic", "LA"),
+    ("Lebanese Republic", "LB"),
+    ("Principality of Liechtenstein", "LI"),
+    ("Democratic Socialist Republic of Sri Lanka", "LK"),
+    ("Republic of Liberia", "LR"),
+    ("Kingdom of Lesotho", "LS"),
+    ("Republic of Lithuania", "LT"),
+    ("Grand Duchy of Luxembourg", "LU"),
+    ("Republic of Latvia", "LV"),
+    ("Kingdom of Morocco", "MA"),
+    ("Principality of Monaco", "MC"),
+    ("Moldova, Republic of", "MD"),
+    ("Republic of Moldova", "MD"),
+    ("Republic of Madagascar", "MG"),
+    ("Republic of the Marshall Islands", "MH"),
+    ("Republic of North Macedonia", "MK"),
+    ("Republic of Mali", "ML"),
+    ("Republic of Myanmar", "MM"),
+    ("Macao Special Administrative Region of China", "MO"),
+    ("Commonwealth of the Northern Mariana Islands", "MP"),
+    ("Islamic Republic of Mauritania", "MR"),
+    ("Republic of Malta", "MT"),
+    ("Republic of Mauritius", "MU"),
+    ("Republic of Maldives", "MV"),
+    ("Republic of Malawi", "MW"),
+    ("United Mexican States", "MX"),
+    ("Republic of Mozambique", "MZ"),
+    ("Republic of Namibia", "NA"),
+    ("Republic of the Niger", "NE"),
+    ("Federal Republic of Nigeria", "NG"),
+    ("Republic of Nicaragua", "NI"),
+    ("Kingdom of the Netherlands", "NL"),
+    ("Kingdom of Norway", "NO"),
+    ("Federal Democratic Republic of Nepal", "NP"),
+    ("Republic of Nauru", "NR"),
+    ("Sultanate of Oman", "OM"),
+    ("Republic of Panama", "PA"),
+    ("Republic of Peru", "PE"),
+    ("Independent State of Papua New Guinea", "PG"),
+    ("Republic of the Philippines", "PH"),
+    ("Islamic Republic of Pakistan", "PK"),
+    ("Republic of Poland", "PL"),
+    ("the State of Palestine", "PS"),
+    ("Portuguese Republic", "PT"),
+    ("Republic of Palau", "PW"),
+    ("Republic of Paraguay", "PY"),
+    ("State of Qatar", "QA"),
+    ("Republic of Serbia", "RS"),
+    ("Rwandese Republic", "RW"),
+    ("Kingdom of Saudi Arabia", "SA"),
+    ("Republic of Seychelles", "SC"),
+    ("Republic of the Sudan", "SD"),
+    ("Kingdom of Sweden", "SE"),
+    ("Republic of Singapore", "SG"),
+    ("Republic of Slovenia", "SI"),
+    ("Slovak Republic", "SK"),
+    ("Republic of Sierra Leone", "SL"),
+    ("Republic of San Marino", "SM"),
+    ("Republic of Senegal", "SN"),
+    ("Federal Republic of Somalia", "SO"),
+    ("Republic of Suriname", "SR"),
+    ("Republic of South Sudan", "SS"),
+    ("Democratic Republic of Sao Tome and Principe", "ST"),
+    ("Republic of El Salvador", "SV"),
+    ("Syrian Arab Republic", "SY"),
+    ("Kingdom of Eswatini", "SZ"),
+    ("Republic of Chad", "TD"),
+    ("Togolese Republic", "TG"),
+    ("Kingdom of Thailand", "TH"),
+    ("Republic of Tajikistan", "TJ"),
+    ("Democratic Republic of Timor-Leste", "TL"),
+    ("Republic of Tunisia", "TN"),
+    ("Kingdom of Tonga", "TO"),
+    ("Republic of Türkiye", "TR"),
+    ("Republic of Trinidad and Tobago", "TT"),
+    ("Taiwan, Province of China", "TW"),
+    ("Tanzania, United Republic of", "TZ"),
+    ("United Republic of Tanzania", "TZ"),
+    ("Republic of Uganda", "UG"),
+    ("United States of America", "US"),
+    ("Eastern Republic of Uruguay", "UY"),
+    ("Republic of Uzbekistan", "UZ"),
+    ("Venezuela, Bolivarian Republic of", "VE"),
+    ("Bolivarian Republic of Venezuela", "VE"),
+    ("British Virgin Islands", "VG"),
+    ("Virgin Islands of the United States", "VI"),
+    ("Viet Nam", "VN"),
+    ("Socialist Republic of Viet Nam", "VN"),
+    ("Republic of Vanuatu", "VU"),
+    ("Independent State of Samoa", "WS"),
+    ("Republic of Yemen", "YE"),
+    ("Republic of South Africa", "ZA"),
+    ("Republic of Zambia", "ZM"),
+    ("Republic of Zimbabwe", "ZW"),
+    ("UK", "GB"),
+    ("Great Britain", "GB"),
+    ("England", "GB"),
+    ("Scotland", "GB"),
+    ("Wales", "GB"),
+    ("USA", "US"),
+    ("America", "US"),
+    ("Holland", "NL"),
+    ("Russia", "RU"),
+    ("Ivory Coast", "CI"),
+    ("Turkey", "TR"),
+    ("Swaziland", "SZ"),
+    ("Macedonia", "MK"),
+    ("Burma", "MM"),
+    ("Vatican", "VA"),
+    ("Palestine", "PS"),
+    ("Brunei", "BN"),
+    ("Micronesia", "FM"),
+    ("Cape Verde", "CV"),
+    ("East Timor", "TL"),
+    ("DR Congo", "CD"),
+];
@@ -0,0 +1,229 @@
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::blocking::RequestBuilder;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+use crate::api::{self, PROVIDER};
+
+/// Keyring entries are filed under this service, one account per provider.
+const SERVICE: &str = "cli_weather";
+
+/// Environment variable holding the API key.
+pub const ENV_VAR: &str = "API_KEY";
+
+fn entry() -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, PROVIDER)
+}
+
+pub fn store(api_key: &str) -> keyring::Result<()> {
+    entry()?.set_password(api_key)
+}
+
+pub fn stored() -> keyring::Result<Option<String>> {
+    match entry()?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns whether there was a key to remove.
+pub fn remove() -> keyring::Result<bool> {
+    match entry()?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// The first key found: the `--api-key` flag, then the environment, then
+/// the OS keyring, then `api_key` in the config file. A keyring that cannot
+/// be reached, as on headless machines, is skipped.
+pub fn lookup(flag: Option<&str>, configured: Option<&str>) -> Option<String> {
+    let found = |key: Option<String>| {
+        key.map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+    };
+
+    found(flag.map(String::from))
+        .or_else(|| found(std::env::var(ENV_VAR).ok()))
+        .or_else(|| found(stored().ok().flatten()))
+        .or_else(|| found(configured.map(String::from)))
+}
+
+/// How a provider wants its requests authenticated.
+#[derive(Debug)]
+pub enum Auth {
+    /// A key in a query parameter, like OpenWeatherMap's `appid`.
+    QueryKey { param: &'static str, key: String },
+    /// A fixed header, e.g. `Authorization: Bearer <token>` or `X-Api-Key`.
+    Header { name: String, value: String },
+    /// The key and a `timestamp` in the query, signed with `signature`:
+    /// the hex HMAC-SHA256 of the path, `?`, then the sorted `name=value`
+    /// pairs joined by `&`.
+    SignedQuery {
+        key_param: &'static str,
+        key: String,
+        secret: String,
+    },
+    /// A bearer token from an OAuth 2 client credentials grant.
+    ClientCredentials(ClientCredentials),
+}
+
+/// What `Auth` adds to a request: query parameters and headers.
+#[derive(Debug, Default, PartialEq)]
+pub struct Credentials {
+    pub query: Vec<(&'static str, String)>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Auth {
+    /// What the scheme needs added to a request about to be sent to `path`
+    /// with `params`.
+    pub fn credentials(
+        &self,
+        http: &reqwest::blocking::Client,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<Credentials, api::Error> {
+        let mut credentials = Credentials::default();
+        match self {
+            Auth::QueryKey { param, key } => credentials.query.push((param, key.clone())),
+            Auth::Header { name, value } => credentials.headers.push((name.clone(), value.clone())),
+            Auth::SignedQuery {
+                key_param,
+                key,
+                secret,
+            } => {
+                let timestamp = chrono::Utc::now().timestamp().to_string();
+                credentials.query = vec![(key_param, key.clone()), ("timestamp", timestamp)];
+                let mut signed: Vec<(&str, String)> = params.to_vec();
+                signed.extend(credentials.query.iter().cloned());
+                credentials
+                    .query
+                    .push(("signature", signature(secret, path, &signed)));
+            }
+            Auth::ClientCredentials(grant) => credentials.headers.push((
+                String::from("Authorization"),
+                format!("Bearer {}", grant.token(http)?),
+            )),
+        }
+        Ok(credentials)
+    }
+
+    /// Adds the credentials to `request`, which is about to be sent to
+    /// `path` with `params`.
+    pub fn apply(
+        &self,
+        http: &reqwest::blocking::Client,
+        request: RequestBuilder,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<RequestBuilder, api::Error> {
+        let credentials = self.credentials(http, path, params)?;
+        let request = request.query(&credentials.query);
+        Ok(credentials
+            .headers
+            .iter()
+            .fold(request, |request, (name, value)| {
+                request.header(name.as_str(), value.as_str())
+            }))
+    }
+}
+
+pub fn signature(secret: &str, path: &str, params: &[(&str, String)]) -> String {
+    let mut pairs: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    pairs.sort();
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("{}?{}", path, pairs.join("&")).as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Tokens are renewed this long before they expire, so none runs out in
+/// flight.
+const TOKEN_MARGIN: Duration = Duration::from_secs(30);
+/// Tokens are renewed at least this often, whatever `expires_in` says.
+const MAX_TOKEN_LIFETIME: Duration = Duration::from_secs(86400);
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// Seconds; a missing one is taken as an hour.
+    expires_in: Option<u64>,
+}
+
+/// The OAuth 2 client credentials grant, with the token kept until shortly
+/// before it expires.
+#[derive(Debug)]
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    token: RefCell<Option<(String, Instant)>>,
+}
+
+impl ClientCredentials {
+    pub fn new(
+        token_url: &str,
+        client_id: &str,
+        client_secret: &str,
+        scope: Option<&str>,
+    ) -> ClientCredentials {
+        ClientCredentials {
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            scope: scope.map(String::from),
+            token: RefCell::new(None),
+        }
+    }
+
+    fn token(&self, http: &reqwest::blocking::Client) -> Result<String, api::Error> {
+        if let Some((token, expires)) = &*self.token.borrow() {
+            if Instant::now() < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        let mut form = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        let network = |e: reqwest::Error| api::Error::Network(e.without_url());
+        let response = http
+            .post(&self.token_url)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&form)
+            .send()
+            .map_err(network)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(api::Error::Auth(format!(
+                "the token endpoint answered {}",
+                status
+            )));
+        }
+        let body: TokenResponse = response.json().map_err(|_| {
+            api::Error::Auth(String::from("the token response had no access_token"))
+        })?;
+
+        let lifetime = Duration::from_secs(body.expires_in.unwrap_or(3600)).min(MAX_TOKEN_LIFETIME);
+        let expires = Instant::now() + lifetime.saturating_sub(TOKEN_MARGIN);
+        *self.token.borrow_mut() = Some((body.access_token.clone(), expires));
+        Ok(body.access_token)
+    }
+}
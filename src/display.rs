@@ -0,0 +1,1245 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use colored::*;
+
+use crate::advice::{self, Conditions};
+use crate::api::{
+    self, ForecastCity, ForecastEntry, GeoCandidate, UvForecast, Weather, WeatherResponse, Wind,
+};
+use crate::art;
+use crate::astro::{Crossing, MoonPhase, SunPosition, SunTimes};
+use crate::chart;
+use crate::climate::{self, Normals};
+use crate::comfort::{self, Miss, Score};
+use crate::config::{Comfort, Thresholds};
+use crate::coords;
+use crate::energy;
+use crate::events::{self, Kind};
+use crate::forecast::{self, Day, RainSpell};
+use crate::ical::{self, Event};
+use crate::notify::Fired;
+use crate::recent::{DayRecord, Totals};
+use crate::snapshot::{self, Snapshot};
+use crate::telemetry;
+use crate::template::Template;
+use crate::theme::{paint, Role};
+use crate::trip::{self, Flag};
+use crate::units::{self, SpeedUnit, TempUnit};
+use crate::usage::{self, Usage};
+use crate::uv::{self, Risk};
+
+/// A part of the report that needs a request of its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Part<T> {
+    /// Not asked for, or not offered by the provider.
+    #[default]
+    Off,
+    Shown(T),
+    /// Asked for, but the request failed; the report says so in its place
+    /// and shows the rest.
+    Failed,
+}
+
+impl<T: Copy> Part<T> {
+    pub fn shown(&self) -> Option<T> {
+        match self {
+            Part::Shown(value) => Some(*value),
+            Part::Off | Part::Failed => None,
+        }
+    }
+}
+
+impl<T> From<Option<T>> for Part<T> {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Part::Off, Part::Shown)
+    }
+}
+
+/// Stands in for a part whose request failed.
+fn unavailable() -> ColoredString {
+    paint(Role::Warning, "⚠ unavailable, see the warning above")
+}
+
+/// Optional extras for the full report.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReportOptions {
+    /// Add a `geo:` link for opening the place in a map app.
+    pub geo_uri: bool,
+    /// Draw a picture of the sky next to the numbers.
+    pub art: bool,
+    /// The UV index, when One Call could be asked for it.
+    pub uv: Part<uv::Summary>,
+    /// Scores the weather against this profile, for `--score`.
+    pub comfort: Option<Comfort>,
+    /// Adds tips on what to wear and do, for `--advice`.
+    pub advice: bool,
+    /// The chance of rain over the next hours, 0 to 1, for the advice.
+    pub rain_chance: Part<f64>,
+    /// When the grid is greenest, for `energy.in_report`.
+    pub energy: Part<energy::Window>,
+    /// Marks what changed since `since`, for `--diff`.
+    pub diff: bool,
+    /// The last reading at the place before this one, when there is one.
+    pub since: Option<Reading>,
+}
+
+/// The numbers `--diff` compares, from an earlier report at the same place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Unix time it was fetched.
+    pub at: i64,
+    pub temp: f64,
+    pub pressure: f64,
+    pub humidity: f64,
+    /// m/s.
+    pub wind: f64,
+}
+
+impl Reading {
+    pub fn of(weather: &WeatherResponse, at: i64) -> Reading {
+        Reading {
+            at,
+            temp: weather.main.temp,
+            pressure: weather.main.pressure,
+            humidity: weather.main.humidity,
+            wind: weather.wind.speed,
+        }
+    }
+}
+
+/// Whether `now` is up on `before`, and by how much, e.g. " ↑2.1°C"; None
+/// when the two read the same to `decimals` places.
+fn change(now: f64, before: f64, decimals: usize, unit: &str) -> Option<(bool, String)> {
+    let diff = now - before;
+    let shown = format!("{:.*}", decimals, diff.abs());
+    if shown.trim_start_matches(['0', '.']).is_empty() {
+        return None;
+    }
+    let arrow = if diff > 0.0 { "↑" } else { "↓" };
+    Some((diff > 0.0, format!(" {}{}{}", arrow, shown, unit)))
+}
+
+pub fn print_weather_info(weather_info: &WeatherResponse, options: &ReportOptions) {
+    print!("{}", render_weather_info(weather_info, options));
+}
+
+pub fn render_weather_info(weather_info: &WeatherResponse, options: &ReportOptions) -> String {
+    let _span = telemetry::span("render");
+    let condition = weather_info.weather.first();
+    let description = condition
+        .map(get_description_emoji_and_color)
+        .unwrap_or_default();
+    let map = if options.geo_uri {
+        format!(
+            "> Map: {}\n",
+            paint(Role::Link, coords::geo_uri(&weather_info.coord))
+        )
+    } else {
+        String::new()
+    };
+    let uv = match options.uv {
+        Part::Shown(uv) => format!("> UV index: {}\n", uv_summary(&uv)),
+        Part::Failed => format!("> UV index: {}\n", unavailable()),
+        Part::Off => String::new(),
+    };
+    let comfort = options
+        .comfort
+        .map(|profile| {
+            let score = Score::current(&profile, weather_info);
+            format!("> Comfort: {}\n", comfort_summary(&score))
+        })
+        .unwrap_or_default();
+    let since = options.since.filter(|_| options.diff);
+    let temp_change = since
+        .and_then(|since| {
+            let unit = units::preferences().temperature;
+            change(
+                unit.from_celsius(weather_info.main.temp),
+                unit.from_celsius(since.temp),
+                1,
+                unit.symbol(),
+            )
+        })
+        .map(|(up, text)| paint(if up { Role::Warm } else { Role::Cold }, text).to_string())
+        .unwrap_or_default();
+    let marked = |now: f64, before: fn(&Reading) -> f64, decimals, unit: &str| {
+        since
+            .and_then(|since| change(now, before(&since), decimals, unit))
+            .map(|(_, text)| paint(Role::Value, text).bold().to_string())
+            .unwrap_or_default()
+    };
+    let pressure_change = marked(weather_info.main.pressure, |r| r.pressure, 0, " hPa");
+    let humidity_change = marked(weather_info.main.humidity, |r| r.humidity, 0, "%");
+    let wind_unit = units::preferences().wind;
+    let wind_change = since
+        .and_then(|since| {
+            let decimals = if wind_unit == SpeedUnit::MetersPerSecond {
+                1
+            } else {
+                0
+            };
+            change(
+                wind_unit.from_mps(weather_info.wind.speed),
+                wind_unit.from_mps(since.wind),
+                decimals,
+                &format!(" {}", wind_unit.symbol()),
+            )
+        })
+        .map(|(_, text)| paint(Role::Value, text).bold().to_string())
+        .unwrap_or_default();
+    let compared = match since {
+        Some(since) => {
+            let at = DateTime::from_timestamp(since.at, 0)
+                .unwrap_or_default()
+                .with_timezone(&utc_offset(weather_info.timezone))
+                .format(units::preferences().clock.format())
+                .to_string();
+            let changed = [
+                &temp_change,
+                &pressure_change,
+                &humidity_change,
+                &wind_change,
+            ]
+            .iter()
+            .any(|change| !change.is_empty());
+            if changed {
+                format!("> Changes since: {}\n", paint(Role::Muted, at))
+            } else {
+                format!("> Changes since {}: {}\n", at, paint(Role::Muted, "none"))
+            }
+        }
+        None => String::new(),
+    };
+
+    let energy = match options.energy {
+        Part::Shown(window) => format!(
+            "> Dryer: {}\n",
+            dryer_summary(&window, utc_offset(weather_info.timezone))
+        ),
+        Part::Failed => format!("> Dryer: {}\n", unavailable()),
+        Part::Off => String::new(),
+    };
+    let advice = if options.advice {
+        let conditions = Conditions::new(
+            weather_info,
+            options.rain_chance.shown(),
+            options.uv.shown(),
+        );
+        let mut tips: String = advice::advise(&conditions)
+            .into_iter()
+            .map(|(_, tip)| format!(">   • {}\n", tip))
+            .collect();
+        if options.rain_chance == Part::Failed {
+            tips.push_str(&format!(">   • Chance of rain: {}\n", unavailable()));
+        }
+        format!("> Advice:\n{}", tips)
+    } else {
+        String::new()
+    };
+
+    let wind = wind_lines(&weather_info.wind, units::preferences().wind, &wind_change);
+    let stats = format!(
+        "> Weather: {}\n> Temperature: {}{}\n> Pressure: {} hPa{}\n> Humidity: {}%{}\n{}{}{}{}{}{}{}",
+        description,
+        get_temp_emoji(weather_info.main.temp),
+        temp_change,
+        paint(Role::Value, weather_info.main.pressure.to_string()),
+        pressure_change,
+        paint(Role::Value, weather_info.main.humidity.to_string()),
+        humidity_change,
+        wind,
+        compared,
+        uv,
+        comfort,
+        advice,
+        energy,
+        map,
+    );
+    let body = if options.art {
+        let banner = art::banner(
+            condition.map(|c| c.id).unwrap_or_default(),
+            condition.is_some_and(is_night),
+        );
+        beside(&banner, &stats)
+    } else {
+        stats
+    };
+
+    format!(
+        "\n\n{}\n\n{}\n\n",
+        paint(Role::Heading, weather_info.name.to_uppercase()),
+        body
+    )
+}
+
+/// e.g. "82/100, 2°C too cold" or "100/100, just right".
+fn comfort_summary(score: &Score) -> String {
+    let preferences = units::preferences();
+    let misses: Vec<String> = score
+        .misses
+        .iter()
+        .map(|miss| match *miss {
+            Miss::TooCold(by) | Miss::TooWarm(by) => {
+                // A difference, so Fahrenheit has no offset.
+                let by = match preferences.temperature {
+                    TempUnit::Celsius => by,
+                    TempUnit::Fahrenheit => by * 1.8,
+                };
+                let side = if matches!(miss, Miss::TooCold(_)) {
+                    "cold"
+                } else {
+                    "warm"
+                };
+                format!("{:.0}{} too {}", by, preferences.temperature.symbol(), side)
+            }
+            Miss::TooWindy(by) => format!(
+                "{:.0} {} too windy",
+                preferences.wind.from_mps(by),
+                preferences.wind.symbol()
+            ),
+            Miss::TooHumid(by) => format!("{:.0}% too humid", by),
+        })
+        .collect();
+    let detail = if misses.is_empty() {
+        String::from("just right")
+    } else {
+        misses.join(", ")
+    };
+    format!(
+        "{}, {}",
+        paint(Role::Value, format!("{}/100", score.value)),
+        detail
+    )
+}
+
+fn speed(mps: f64, unit: SpeedUnit) -> ColoredString {
+    let value = unit.from_mps(mps);
+    // Metres per second are shown as the API sends them.
+    let value = if unit == SpeedUnit::MetersPerSecond {
+        value.to_string()
+    } else {
+        format!("{:.0}", value)
+    };
+    paint(Role::Value, format!("{} {}", value, unit.symbol()))
+}
+
+/// Speed with its Beaufort force, then direction and gusts when reported.
+/// `change` follows the speed, for `--diff`.
+fn wind_lines(wind: &Wind, unit: SpeedUnit, change: &str) -> String {
+    let force = units::beaufort(wind.speed);
+    let mut lines = format!(
+        "> Wind speed: {}{}, {} (Beaufort {})\n",
+        speed(wind.speed, unit),
+        change,
+        units::beaufort_description(force),
+        force
+    );
+    if let Some(deg) = wind.deg {
+        lines.push_str(&format!(
+            "> Wind direction: {} {} {}\n",
+            paint(Role::Value, units::compass_point(deg)),
+            units::compass_arrow(deg),
+            paint(Role::Muted, format!("({:.0}°)", deg))
+        ));
+    }
+    if let Some(gust) = wind.gust {
+        lines.push_str(&format!("> Gusts: {}\n", speed(gust, unit)));
+    }
+    lines
+}
+
+/// Puts `banner` to the left of `text`, line by line.
+fn beside(banner: &[String], text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let blank = " ".repeat(art::WIDTH);
+
+    (0..banner.len().max(lines.len()))
+        .map(|i| {
+            let picture = banner.get(i).unwrap_or(&blank);
+            let line = lines.get(i).copied().unwrap_or_default();
+            format!("{} {}", picture, line).trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+fn get_temp_emoji(temp: f64) -> ColoredString {
+    let role = Role::for_temperature(temp);
+    let emoji = match role {
+        Role::Freezing => "🫢",
+        Role::Cold => "🥶",
+        Role::Mild => "😊",
+        Role::Warm => "🌞",
+        _ => "🔥",
+    };
+    paint(role, format!("{} {}", temperature(temp), emoji))
+}
+
+/// In the preferred unit; Celsius is shown as the API sends it.
+fn temperature(celsius: f64) -> String {
+    match units::preferences().temperature {
+        TempUnit::Celsius => format!("{}°C", celsius),
+        unit => format!("{:.1}{}", unit.from_celsius(celsius), unit.symbol()),
+    }
+}
+
+/// Rounded to whole degrees in the preferred unit, e.g. "15°C".
+fn whole_degrees(celsius: f64) -> String {
+    let unit = units::preferences().temperature;
+    format!("{:.0}{}", unit.from_celsius(celsius), unit.symbol())
+}
+
+/// Keyed on the condition code rather than the text, which follows `--lang`.
+fn get_description_emoji_and_color(condition: &Weather) -> ColoredString {
+    let description = &condition.description;
+    match condition.id {
+        800 => paint(Role::Clear, format!("{} 🌄", description)),
+        801 | 804 => paint(Role::Cloudy, format!("{} 🌤️", description)),
+        802 => paint(Role::Cloudy, format!("{} 🌥️", description)),
+        803 => paint(Role::Cloudy, format!("{} 🌫️", description)),
+        300..=399 | 500..=599 => paint(Role::Wet, format!("{} 🌧️", description)),
+        600..=699 => paint(Role::Wet, format!("{} 🌨️", description)),
+        200..=299 => paint(Role::Wet, format!("{} ⛈️", description)),
+        701 => paint(Role::Muted, format!("{} 🌫️", description)),
+        _ => description.normal(),
+    }
+}
+
+/// One forecast step for `--at`: the weather expected at `at` rather than
+/// now.
+pub fn render_snapshot(city: &ForecastCity, entry: &ForecastEntry, at: DateTime<Utc>) -> String {
+    let offset = utc_offset(city.timezone);
+    let local = at.with_timezone(&offset);
+    let mut lines = vec![format!(
+        "> Forecast for: {} {}",
+        paint(Role::Value, local.format("%a %-d %b").to_string()),
+        paint(Role::Value, local_time(at, offset))
+    )];
+    if let Some(condition) = entry.weather.first() {
+        lines.push(format!(
+            "> Weather: {}",
+            get_description_emoji_and_color(condition)
+        ));
+    }
+    lines.push(format!(
+        "> Temperature: {}",
+        get_temp_emoji(entry.main.temp)
+    ));
+    lines.push(format!(
+        "> Humidity: {}%",
+        paint(Role::Value, entry.main.humidity.to_string())
+    ));
+    lines.push(format!(
+        "> Chance of rain: {}",
+        paint(Role::Value, format!("{:.0}%", entry.pop * 100.0))
+    ));
+    let mut stats = lines.join("\n") + "\n";
+    if let Some(wind) = &entry.wind {
+        stats.push_str(&wind_lines(wind, units::preferences().wind, ""));
+    }
+
+    format!(
+        "\n\n{}\n\n{}\n\n",
+        paint(Role::Heading, city.name.to_uppercase()),
+        stats
+    )
+}
+
+/// Placeholders understood by `--format`.
+pub const STATUS_PLACEHOLDERS: &[&str] = &[
+    "city",
+    "description",
+    "temp",
+    "feels_like",
+    "humidity",
+    "pressure",
+    "wind",
+    "wind_dir",
+    "gust",
+    "beaufort",
+    "icon",
+    "emoji",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IconSet {
+    Emoji,
+    NerdFont,
+}
+
+/// Renders the compact single-line form used by status bars and prompts.
+/// Output is plain text: status bars apply their own colors.
+pub fn render_status_line(
+    template: &Template,
+    weather_info: &WeatherResponse,
+    icons: IconSet,
+) -> String {
+    let condition = weather_info.weather.first();
+
+    template.render(|name| match name {
+        "city" => weather_info.name.clone(),
+        "description" => condition.map(|c| c.description.clone()).unwrap_or_default(),
+        "temp" => whole_degrees(weather_info.main.temp),
+        "feels_like" => whole_degrees(weather_info.main.feels_like),
+        "humidity" => format!("{}%", weather_info.main.humidity),
+        "pressure" => format!("{} hPa", weather_info.main.pressure),
+        "wind" => format!("{:.1} m/s", weather_info.wind.speed),
+        "wind_dir" => weather_info
+            .wind
+            .deg
+            .map(|deg| {
+                format!(
+                    "{} {}",
+                    units::compass_point(deg),
+                    units::compass_arrow(deg)
+                )
+            })
+            .unwrap_or_default(),
+        "gust" => weather_info
+            .wind
+            .gust
+            .map(|gust| format!("{:.1} m/s", gust))
+            .unwrap_or_default(),
+        "beaufort" => {
+            units::beaufort_description(units::beaufort(weather_info.wind.speed)).to_string()
+        }
+        "icon" if icons == IconSet::NerdFont => condition
+            .map(nerd_font_icon)
+            .unwrap_or_default()
+            .to_string(),
+        "icon" | "emoji" => condition
+            .map(condition_emoji)
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    })
+}
+
+fn is_night(condition: &Weather) -> bool {
+    condition.icon.ends_with('n')
+}
+
+/// Picks an emoji from the OpenWeatherMap condition code.
+fn condition_emoji(condition: &Weather) -> &'static str {
+    match condition.id {
+        200..=299 => "⛈️",
+        300..=399 => "🌦️",
+        500..=599 => "🌧️",
+        600..=699 => "🌨️",
+        700..=799 => "🌫️",
+        800 if is_night(condition) => "🌙",
+        800 => "☀️",
+        801 | 802 if !is_night(condition) => "🌤️",
+        _ => "☁️",
+    }
+}
+
+/// Glyphs from the Nerd Fonts weather icon range.
+fn nerd_font_icon(condition: &Weather) -> &'static str {
+    match condition.id {
+        200..=299 => "\u{e31d}",
+        300..=399 => "\u{e31b}",
+        500..=599 => "\u{e318}",
+        600..=699 => "\u{e31a}",
+        700..=799 => "\u{e313}",
+        800 if is_night(condition) => "\u{e32b}",
+        800 => "\u{e30d}",
+        801 | 802 if is_night(condition) => "\u{e37e}",
+        801 | 802 => "\u{e302}",
+        _ => "\u{e312}",
+    }
+}
+
+/// One line per geocoding match, e.g. "München (Munich), Bavaria, DE (48.14, 11.58)".
+/// The default name is shown alongside when `lang` gives a different one.
+pub fn describe_candidate(candidate: &GeoCandidate, lang: Option<&str>) -> String {
+    let localized = candidate.localized_name(lang);
+    let mut line = paint(Role::Heading, localized).to_string();
+
+    if localized != candidate.name {
+        line.push_str(&format!(" ({})", candidate.name));
+    }
+    if let Some(state) = &candidate.state {
+        line.push_str(&format!(", {}", state));
+    }
+    line.push_str(&format!(
+        ", {} {}",
+        candidate.country,
+        paint(
+            Role::Muted,
+            format!("({:.2}, {:.2})", candidate.lat, candidate.lon)
+        )
+    ));
+    line
+}
+
+/// The API reports timezones as seconds east of UTC.
+pub fn utc_offset(seconds: i32) -> FixedOffset {
+    FixedOffset::east_opt(seconds).unwrap_or(FixedOffset::east_opt(0).unwrap())
+}
+
+fn local_time(time: DateTime<Utc>, offset: FixedOffset) -> String {
+    let clock = units::preferences().clock;
+    time.with_timezone(&offset)
+        .format(clock.format())
+        .to_string()
+}
+
+fn duration(seconds: i64) -> String {
+    format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+}
+
+fn uv_role(risk: Risk) -> Role {
+    match risk {
+        Risk::Low => Role::UvLow,
+        Risk::Moderate => Role::UvModerate,
+        Risk::High => Role::UvHigh,
+        Risk::VeryHigh => Role::UvVeryHigh,
+        Risk::Extreme => Role::UvExtreme,
+    }
+}
+
+fn uv_index(uvi: f64) -> ColoredString {
+    let risk = Risk::from_index(uvi);
+    paint(uv_role(risk), format!("{:.1} {}", uvi, risk.name()))
+}
+
+/// e.g. "5.2 moderate, up to 7.1 high today; SPF 30+ sunscreen, ...", with
+/// the advice for the day's peak.
+fn uv_summary(uv: &uv::Summary) -> String {
+    let peak = uv.today_max.unwrap_or(uv.now).max(uv.now);
+    let mut summary = uv_index(uv.now).to_string();
+    if let Some(max) = uv.today_max.filter(|max| *max > uv.now) {
+        summary.push_str(&format!(", up to {} today", uv_index(max)));
+    }
+    summary.push_str(&format!("; {}", Risk::from_index(peak).advice()));
+    summary
+}
+
+/// e.g. "best 13:00–15:00, when the grid is greenest: 64 gCO₂/kWh, 48%
+/// wind and solar, with a breeze and sun forecast".
+fn dryer_summary(window: &energy::Window, offset: FixedOffset) -> String {
+    let clock = units::preferences().clock.format();
+    let at = |time| {
+        DateTime::from_timestamp(time, 0)
+            .unwrap_or_default()
+            .with_timezone(&offset)
+            .format(clock)
+    };
+    let mut summary = format!(
+        "best {}–{}, when the grid is greenest: {} gCO₂/kWh",
+        at(window.start),
+        at(window.end),
+        paint(Role::Value, format!("{:.0}", window.intensity))
+    );
+    if let Some(renewable) = window.renewable {
+        summary.push_str(&format!(", {:.0}% wind and solar", renewable));
+    }
+    let outlook = match (window.breezy, window.sunny) {
+        (true, true) => ", with a breeze and sun forecast",
+        (true, false) => ", with a breeze forecast",
+        (false, true) => ", with sun forecast",
+        (false, false) => "",
+    };
+    summary.push_str(outlook);
+    summary
+}
+
+/// The day's UV index hour by hour, as a bar per daylight hour.
+pub fn print_uv(place: &str, date: NaiveDate, forecast: &UvForecast) {
+    let offset = utc_offset(forecast.timezone_offset);
+    println!(
+        "\n\n{} {}\n",
+        paint(Role::Heading, place.to_uppercase()),
+        paint(Role::Muted, date.to_string())
+    );
+    println!("> UV index: {}\n", uv_summary(&uv::Summary::new(forecast)));
+
+    let hours: Vec<_> = uv::hours_on(forecast, date, offset)
+        .into_iter()
+        .filter(|hour| hour.uvi > 0.0)
+        .collect();
+    if hours.is_empty() {
+        println!(
+            "{}\n",
+            paint(Role::Muted, "No hourly UV forecast for this day.")
+        );
+        return;
+    }
+    for hour in hours {
+        let time = DateTime::from_timestamp(hour.dt, 0).unwrap_or_default();
+        let role = uv_role(Risk::from_index(hour.uvi));
+        // Two cells per index point, capped so a bad reading cannot flood
+        // the terminal.
+        let bar = chart::bar(hour.uvi, 2.0, 40);
+        println!(
+            "  {}  {} {}",
+            paint(Role::Muted, local_time(time, offset)),
+            paint(role, bar),
+            paint(role, format!("{:.1}", hour.uvi))
+        );
+    }
+    println!();
+}
+
+/// Cells for the temperature axis of `recent`, and at most for a rain bar.
+const RECENT_TEMP_CELLS: usize = 20;
+const RECENT_RAIN_CELLS: usize = 12;
+/// Percent of days from which `rules test` flags a rule as noisy.
+const NOISY_SHARE: f64 = 25.0;
+
+pub fn print_recent(place: &str, days: &[DayRecord], totals: &Totals) {
+    println!(
+        "\n\n{} {}\n",
+        paint(Role::Heading, place.to_uppercase()),
+        paint(Role::Muted, format!("past {} days", days.len()))
+    );
+
+    let wettest = totals.wettest.map_or(0.0, |day| day.rain);
+    let rain_scale = if wettest > 0.0 {
+        RECENT_RAIN_CELLS as f64 / wettest
+    } else {
+        0.0
+    };
+    let unit = units::preferences().temperature;
+    for day in days {
+        let role = Role::for_temperature(day.high);
+        let temps = format!(
+            "{:.0}–{:.0}{}",
+            unit.from_celsius(day.low),
+            unit.from_celsius(day.high),
+            unit.symbol()
+        );
+        let line = format!(
+            "  {}  {:>9}  {}  {:>7}  {}",
+            paint(Role::Muted, day.date.format("%a %e %b").to_string()),
+            temps,
+            paint(
+                role,
+                chart::span(
+                    day.low,
+                    day.high,
+                    totals.lowest,
+                    totals.highest,
+                    RECENT_TEMP_CELLS
+                )
+            ),
+            format!("{:.1} mm", day.rain),
+            paint(
+                Role::Wet,
+                chart::bar(day.rain, rain_scale, RECENT_RAIN_CELLS)
+            )
+        );
+        println!("{}", line.trim_end());
+    }
+
+    let wettest = totals
+        .wettest
+        .map(|day| {
+            format!(
+                "; wettest {} ({:.1} mm)",
+                day.date.format("%a %-d %b"),
+                day.rain
+            )
+        })
+        .unwrap_or_default();
+    println!(
+        "\n> Rain: {} over {} days, {} with 1 mm or more{}",
+        paint(Role::Value, format!("{:.1} mm", totals.rain)),
+        days.len(),
+        totals.rain_days,
+        wettest
+    );
+    let hottest = totals
+        .hottest
+        .map(|day| {
+            format!(
+                "; hottest {} ({})",
+                day.date.format("%a %-d %b"),
+                whole_degrees(day.high)
+            )
+        })
+        .unwrap_or_default();
+    println!(
+        "> Highs: {} on average{}",
+        paint(Role::Value, whole_degrees(totals.mean_high)),
+        hottest
+    );
+    println!("\n");
+}
+
+pub fn print_astro(
+    place: &str,
+    date: NaiveDate,
+    offset: FixedOffset,
+    sun: &SunTimes,
+    moon: &MoonPhase,
+) {
+    println!(
+        "\n\n{} {}\n",
+        paint(Role::Heading, place.to_uppercase()),
+        paint(Role::Muted, date.to_string())
+    );
+
+    match sun.sunrise {
+        Crossing::At { rising, setting } => {
+            println!(
+                "> Sunrise: {}",
+                paint(Role::Sun, local_time(rising, offset))
+            );
+            println!(
+                "> Sunset: {}",
+                paint(Role::Sun, local_time(setting, offset))
+            );
+        }
+        Crossing::AlwaysAbove => {
+            println!("> Sunrise: {}", paint(Role::Sun, "none, midnight sun 🌞"))
+        }
+        Crossing::AlwaysBelow => {
+            println!("> Sunrise: {}", paint(Role::Night, "none, polar night 🌌"))
+        }
+    }
+    println!(
+        "> Day length: {}",
+        paint(Role::Value, duration(sun.day_length()))
+    );
+    for (name, crossing) in [
+        ("Civil", sun.civil_twilight),
+        ("Nautical", sun.nautical_twilight),
+        ("Astronomical", sun.astronomical_twilight),
+    ] {
+        let twilight = match crossing {
+            Crossing::At { rising, setting } => format!(
+                "{} – {}",
+                local_time(rising, offset),
+                local_time(setting, offset)
+            ),
+            Crossing::AlwaysAbove => String::from("lasts all night"),
+            Crossing::AlwaysBelow => String::from("none"),
+        };
+        println!("> {} twilight: {}", name, paint(Role::Value, twilight));
+    }
+    println!(
+        "> Moon: {} {} ({}% lit)",
+        paint(Role::Moon, moon.name()),
+        moon.emoji(),
+        (moon.illumination * 100.0).round()
+    );
+    println!("\n");
+}
+
+pub fn print_sun(place: &str, at: DateTime<Utc>, offset: FixedOffset, sun: &SunPosition) {
+    let local = at.with_timezone(&offset);
+    println!(
+        "\n\n{} {} {}\n",
+        paint(Role::Heading, place.to_uppercase()),
+        paint(Role::Muted, local.format("%Y-%m-%d").to_string()),
+        paint(Role::Muted, local_time(at, offset))
+    );
+    println!(
+        "> Elevation: {}",
+        paint(Role::Sun, format!("{:.1}°", sun.elevation))
+    );
+    println!(
+        "> Azimuth: {} {}",
+        paint(Role::Value, format!("{:.0}°", sun.azimuth)),
+        paint(
+            Role::Muted,
+            format!("({})", units::compass_point(sun.azimuth))
+        )
+    );
+    match sun.shadow_ratio() {
+        Some(ratio) => println!(
+            "> Shadow: {} per metre of height, pointing {}",
+            paint(Role::Value, format!("{:.1} m", ratio)),
+            units::compass_point(sun.shadow_bearing())
+        ),
+        None => println!("> Shadow: {}", paint(Role::Night, "none, the sun is down")),
+    }
+    println!("\n");
+}
+
+/// e.g. "🌧️ 12–18°C, light rain", for calendar entries.
+pub fn day_summary(day: &Day) -> String {
+    let unit = units::preferences().temperature;
+    let mut summary = format!(
+        "{:.0}–{:.0}{}",
+        unit.from_celsius(day.min),
+        unit.from_celsius(day.max),
+        unit.symbol()
+    );
+    if let Some(condition) = day.condition {
+        summary = format!(
+            "{} {}, {}",
+            condition_emoji(condition),
+            summary,
+            condition.description
+        );
+    }
+    summary
+}
+
+/// One line of `trip`: the day's forecast and anything to plan around, or
+/// why there is none.
+pub fn print_trip_stop(place: &str, date: NaiveDate, day: Option<&Day>) {
+    let when = paint(Role::Muted, date.format("%a %-d %b").to_string());
+    let Some(day) = day else {
+        println!(
+            "  {}  {}  {}",
+            when,
+            place,
+            paint(Role::Muted, "outside the 5 day forecast")
+        );
+        return;
+    };
+
+    let flags: Vec<String> = trip::flags(day).iter().map(Flag::to_string).collect();
+    let flags = if flags.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "  {}",
+            paint(Role::Warning, format!("⚠ {}", flags.join(", ")))
+        )
+    };
+    println!("  {}  {}  {}{}", when, place, day_summary(day), flags);
+}
+
+/// e.g. "🌧️ 14°C, light rain, 60% chance of rain", for one forecast step.
+pub fn step_summary(entry: &ForecastEntry) -> String {
+    let unit = units::preferences().temperature;
+    let temp = format!("{:.0}{}", unit.from_celsius(entry.main.temp), unit.symbol());
+    let summary = match entry.weather.first() {
+        Some(condition) => format!(
+            "{} {}, {}",
+            condition_emoji(condition),
+            temp,
+            condition.description
+        ),
+        None => temp,
+    };
+    format!("{}, {:.0}% chance of rain", summary, entry.pop * 100.0)
+}
+
+/// One line of `agenda`: when, what and where, then the weather there
+/// then, or why there is none.
+pub fn print_agenda_item(when: &str, summary: &str, place: &str, outlook: Option<&str>) {
+    let outlook = match outlook {
+        Some(outlook) => outlook.normal(),
+        None => paint(Role::Muted, "outside the 5 day forecast"),
+    };
+    println!(
+        "  {}  {}  {}  {}",
+        paint(Role::Muted, when),
+        summary.bold(),
+        place,
+        outlook
+    );
+}
+
+/// Every place in a snapshot as it was then: the report, each day of the
+/// forecast and any alerts.
+pub fn print_snapshot(snapshot: &Snapshot) {
+    let taken = DateTime::from_timestamp(snapshot.taken_at, 0).unwrap_or_default();
+    println!(
+        "{}",
+        paint(
+            Role::Muted,
+            format!(
+                "Snapshot of {} taken {}",
+                plural(snapshot.places.len(), "place"),
+                taken.format("%Y-%m-%d %H:%M UTC")
+            )
+        )
+    );
+    for place in &snapshot.places {
+        print_snapshot_place(place);
+    }
+}
+
+fn print_snapshot_place(place: &snapshot::Place) {
+    let missing = || paint(Role::Muted, "not in the snapshot");
+    let report = render_weather_info(&place.weather, &ReportOptions::default());
+    println!("{}", report.trim_end());
+
+    match &place.forecast {
+        Some(response) => {
+            println!("> Forecast:");
+            let offset = utc_offset(response.city.timezone);
+            for day in forecast::days(response, offset) {
+                println!(
+                    ">   {}  {}",
+                    paint(Role::Muted, day.date.format("%a %-d %b").to_string()),
+                    day_summary(&day)
+                );
+            }
+        }
+        None => println!("> Forecast: {}", missing()),
+    }
+
+    let offset = utc_offset(place.weather.timezone);
+    match &place.alerts {
+        Some(alerts) if alerts.is_empty() => println!("> Alerts: {}", paint(Role::Value, "none")),
+        Some(alerts) => {
+            println!("> Alerts:");
+            for alert in alerts {
+                let until = DateTime::from_timestamp(alert.end, 0).unwrap_or_default();
+                println!(
+                    ">   {} {} ({}), until {}",
+                    paint(Role::Warning, "⚠"),
+                    paint(Role::Warning, &alert.event),
+                    alert.sender_name,
+                    local_time(until, offset)
+                );
+            }
+        }
+        None => println!("> Alerts: {}", missing()),
+    }
+    println!("\n");
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// The forecast as an iCalendar file with one all-day event per day.
+/// With `comfort`, each day is scored against it.
+pub fn render_forecast_calendar(
+    city: &ForecastCity,
+    days: &[Day],
+    comfort: Option<&Comfort>,
+    stamp: DateTime<Utc>,
+) -> String {
+    let events: Vec<Event> = days
+        .iter()
+        .map(|day| {
+            let mut description = Vec::new();
+            if let Some(score) = comfort.and_then(|profile| comfort::day_score(profile, day)) {
+                description.push(format!("Comfort: {}/100", score));
+            }
+            if day.pop > 0.0 {
+                description.push(format!("Chance of rain: {:.0}%", day.pop * 100.0));
+            }
+            if day.rain_mm > 0.0 {
+                description.push(format!("Rain: {:.1} mm", day.rain_mm));
+            }
+            description.push(format!(
+                "Forecast for {} from {}, {}",
+                city.name,
+                api::PROVIDER,
+                stamp.format("%Y-%m-%d %H:%M UTC")
+            ));
+
+            Event {
+                uid: format!(
+                    "{}-{}@{}",
+                    day.date.format("%Y%m%d"),
+                    city.name.to_lowercase().replace(char::is_whitespace, "-"),
+                    env!("CARGO_PKG_NAME")
+                ),
+                date: day.date,
+                summary: day_summary(day),
+                description: description.join("\n"),
+            }
+        })
+        .collect();
+
+    ical::calendar(&format!("Weather in {}", city.name), &events, stamp)
+}
+
+pub fn print_rain_answer(city: &ForecastCity, rain: Option<&RainSpell>, hours: u32) {
+    let offset = utc_offset(city.timezone);
+
+    match rain {
+        Some(rain) => {
+            let at = DateTime::from_timestamp(rain.entry.dt, 0).unwrap_or_default();
+            println!(
+                "{} 🌧️ {} in {} from {} ({} at {:.1} mm/h, {:.0}% chance)",
+                paint(Role::Wet, "Yes").bold(),
+                rain.description,
+                city.name,
+                local_time(at, offset).bold(),
+                rain.intensity(),
+                rain.mm_per_hour(),
+                rain.entry.pop * 100.0
+            );
+        }
+        None => println!(
+            "{} ☀️ no rain expected in {} for the next {} hours",
+            paint(Role::Clear, "No").bold(),
+            city.name,
+            hours
+        ),
+    }
+}
+
+/// Places side by side for `month`, as they are listed.
+pub fn print_scout(month: u32, places: &[(String, Normals)]) {
+    let unit = units::preferences().temperature;
+    println!(
+        "{}",
+        paint(
+            Role::Heading,
+            format!(
+                "{} normals, {}–{}",
+                climate::month_name(month),
+                climate::FIRST_YEAR,
+                climate::LAST_YEAR
+            )
+        )
+    );
+    let width = places
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(5);
+    println!(
+        "  {:<width$}  {:>8}  {:>9}  {:>8}",
+        "Place", "Avg high", "Rain days", "Humidity"
+    );
+    for (name, normals) in places {
+        let high = format!("{:.0}{}", unit.from_celsius(normals.high), unit.symbol());
+        println!(
+            "  {:<width$}  {}  {}  {}",
+            name,
+            paint(Role::for_temperature(normals.high), format!("{:>8}", high)),
+            paint(Role::Value, format!("{:>9.1}", normals.rain_days)),
+            paint(Role::Value, format!("{:>7.0}%", normals.humidity)),
+        );
+    }
+}
+
+/// The last `days` UTC days of API calls, newest first.
+pub fn print_usage(usage: &Usage, today: NaiveDate, days: u32, daily_limit: u32) {
+    let limit = if daily_limit == 0 {
+        String::from("no daily limit set")
+    } else {
+        format!("daily limit {}", daily_limit)
+    };
+    println!(
+        "{}",
+        paint(Role::Heading, format!("API calls per UTC day ({})", limit))
+    );
+
+    for day in today.iter_days().rev().take(days as usize) {
+        let key = day.format("%Y-%m-%d");
+        let Some(providers) = usage.day(day).filter(|p| !p.is_empty()) else {
+            println!("  {}  {}", key, paint(Role::Muted, "0"));
+            continue;
+        };
+
+        for (provider, &count) in providers {
+            let count_text = count.to_string();
+            let count_text = if daily_limit > 0 && count >= daily_limit {
+                paint(Role::Error, count_text).bold()
+            } else if daily_limit > 0
+                && f64::from(count) >= f64::from(daily_limit) * usage::WARN_RATIO
+            {
+                paint(Role::Warning, count_text)
+            } else {
+                paint(Role::Value, count_text)
+            };
+            println!("  {}  {:<16} {}", key, provider, count_text);
+        }
+    }
+
+    if usage.months.is_empty() {
+        return;
+    }
+    println!("{}", paint(Role::Heading, "Earlier, per month"));
+    for (month, providers) in usage.months.iter().rev() {
+        for (provider, count) in providers {
+            println!(
+                "  {}     {:<16} {}",
+                month,
+                provider,
+                paint(Role::Value, count.to_string())
+            );
+        }
+    }
+}
+
+/// How often each rule would have fired over the past `days` days.
+pub fn print_backtest(place: &str, days: usize, thresholds: &Thresholds, fired: &[Fired]) {
+    println!(
+        "\n\n{} {}\n",
+        paint(Role::Heading, place.to_uppercase()),
+        paint(Role::Muted, format!("past {} days", days))
+    );
+
+    let preferences = units::preferences();
+    for rule in fired {
+        let condition = match (&rule.expr, rule.rule.as_str()) {
+            (Some(expr), _) => expr.clone(),
+            (None, "freezing") => format!(
+                "below {:.0}{}",
+                preferences.temperature.from_celsius(thresholds.min_temp),
+                preferences.temperature.symbol()
+            ),
+            (None, "windy") => format!(
+                "above {:.0} {}",
+                preferences.wind.from_mps(thresholds.max_wind),
+                preferences.wind.symbol()
+            ),
+            (None, "rain") => format!("{} mm or more", climate::RAIN_DAY_MM),
+            _ => String::from("active alerts"),
+        };
+        let outcome = match (rule.judged, rule.days.last()) {
+            _ if !rule.replayable => paint(Role::Muted, "not replayable"),
+            (0, _) => paint(Role::Muted, "not in the history"),
+            (_, None) => paint(Role::Value, "never"),
+            (judged, Some(last)) => {
+                let share = rule.days.len() as f64 / judged as f64 * 100.0;
+                let role = if share >= NOISY_SHARE {
+                    Role::Warning
+                } else {
+                    Role::Value
+                };
+                let count = if judged < days {
+                    format!("{} of {} days with data", rule.days.len(), judged)
+                } else {
+                    plural(rule.days.len(), "day")
+                };
+                paint(
+                    role,
+                    format!(
+                        "{} ({:.0}%), last {}",
+                        count,
+                        share,
+                        last.format("%a %e %b")
+                    ),
+                )
+            }
+        };
+        println!("  {:<9} {:<16} {}", rule.rule, condition, outcome);
+    }
+}
+
+/// One line per event, e.g. `2025-10-12  Oslo  first frost, 30°F`.
+pub fn print_events(events: &[&events::Event]) {
+    let unit = units::preferences().temperature;
+    let width = events
+        .iter()
+        .map(|e| e.city.chars().count())
+        .max()
+        .unwrap_or(0);
+    for event in events {
+        let (role, detail) = match (event.kind, event.temp, &event.alert) {
+            (Kind::Storm, _, Some(alert)) => (Role::Warning, format!("storm, {}", alert)),
+            (kind, Some(temp), _) => (
+                Role::for_temperature(temp),
+                format!("{}, {:.0}{}", kind, unit.from_celsius(temp), unit.symbol()),
+            ),
+            (kind, None, _) => (Role::Value, kind.to_string()),
+        };
+        println!(
+            "  {}  {:<width$}  {}",
+            paint(Role::Muted, &event.date),
+            event.city,
+            paint(role, detail),
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! When the grid is greenest, for `energy.in_report`: National Grid ESO's
+//! carbon intensity forecast for Great Britain, for a postcode district or
+//! the whole country, and the window of a few hours in which running the
+//! dryer costs the least CO₂. Needs no API key.
+
+use chrono::{DateTime, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::api::{self, ApiError, Coord, ForecastResponse};
+use crate::astro;
+use crate::forecast::{self, STEP_SECS};
+use crate::units;
+
+const BASE_URL: &str = "https://api.carbonintensity.org.uk";
+
+pub const PROVIDER: &str = "National Grid ESO";
+
+/// How long a dryer load runs, and so how long the window is.
+pub const DRYER_HOURS: i64 = 2;
+/// How far ahead the window is looked for.
+pub const LOOKAHEAD_HOURS: i64 = 24;
+/// Beaufort force from which the wind is worth mentioning: a moderate
+/// breeze, when turbines run well.
+const BREEZY_FORCE: u8 = 4;
+/// Degrees the sun must be above the horizon for panels to do much.
+const SUNNY_ELEVATION: f64 = 20.0;
+
+/// The outward code of a UK postcode, e.g. "RG10" for "rg10 9ab", which is
+/// all the API takes.
+pub fn district(postcode: &str) -> Result<String, String> {
+    let outward = postcode
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let letters = outward
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .count();
+    let rest = &outward[letters..];
+    let valid = (1..=2).contains(&letters)
+        && (1..=2).contains(&rest.len())
+        && rest.starts_with(|c: char| c.is_ascii_digit())
+        && rest.chars().all(|c| c.is_ascii_alphanumeric());
+    if valid {
+        Ok(outward)
+    } else {
+        Err(format!(
+            "'{}' is not a UK postcode; give e.g. RG10 or SW1A 1AA",
+            postcode
+        ))
+    }
+}
+
+/// Half an hour of the forecast.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Period {
+    /// UTC, e.g. "2024-04-10T12:00Z".
+    pub from: String,
+    pub intensity: Intensity,
+    /// Only sent for regions.
+    #[serde(default, rename = "generationmix")]
+    pub generation_mix: Vec<Fuel>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Intensity {
+    /// gCO₂/kWh.
+    pub forecast: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Fuel {
+    pub fuel: String,
+    /// Share of generation, percent.
+    pub perc: f64,
+}
+
+impl Period {
+    pub fn start(&self) -> Option<i64> {
+        NaiveDateTime::parse_from_str(&self.from, "%Y-%m-%dT%H:%MZ")
+            .ok()
+            .map(|time| time.and_utc().timestamp())
+    }
+
+    /// Percent of generation from wind and solar, when the mix is known.
+    pub fn renewable(&self) -> Option<f64> {
+        if self.generation_mix.is_empty() {
+            return None;
+        }
+        Some(
+            self.generation_mix
+                .iter()
+                .filter(|fuel| fuel.fuel == "wind" || fuel.fuel == "solar")
+                .map(|fuel| fuel.perc)
+                .sum(),
+        )
+    }
+}
+
+const PERIOD_SECS: i64 = 1800;
+
+/// The time to run the dryer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub start: i64,
+    pub end: i64,
+    /// Mean gCO₂/kWh over the window.
+    pub intensity: f64,
+    /// Mean percent from wind and solar, for regions.
+    pub renewable: Option<f64>,
+    /// Whether the weather forecast has a breeze then, and sun.
+    pub breezy: bool,
+    pub sunny: bool,
+}
+
+/// The `hours` in the next `LOOKAHEAD_HOURS` with the lowest mean intensity,
+/// the earliest of equals; None when the forecast does not cover that long.
+pub fn greenest(periods: &[Period], now: i64, hours: i64) -> Option<Window> {
+    let needed = usize::try_from(hours * 3600 / PERIOD_SECS).ok()?.max(1);
+    let horizon = now.saturating_add(LOOKAHEAD_HOURS * 3600);
+    let ahead: Vec<(i64, f64, Option<f64>)> = periods
+        .iter()
+        .filter_map(|period| {
+            Some((
+                period.start()?,
+                period.intensity.forecast?,
+                period.renewable(),
+            ))
+        })
+        .filter(|(start, _, _)| start.saturating_add(PERIOD_SECS) > now && *start < horizon)
+        .collect();
+
+    ahead
+        .windows(needed)
+        // Gaps in the forecast would make the window longer than a load.
+        .filter(|run| run[needed - 1].0 - run[0].0 == (needed as i64 - 1) * PERIOD_SECS)
+        .map(|run| {
+            let intensity = run.iter().map(|(_, g, _)| g).sum::<f64>() / needed as f64;
+            let renewable = run
+                .iter()
+                .map(|(_, _, r)| *r)
+                .sum::<Option<f64>>()
+                .map(|total| total / needed as f64);
+            Window {
+                start: run[0].0,
+                end: run[needed - 1].0 + PERIOD_SECS,
+                intensity,
+                renewable,
+                breezy: false,
+                sunny: false,
+            }
+        })
+        .fold(None, |best: Option<Window>, window| match best {
+            Some(best) if best.intensity <= window.intensity => Some(best),
+            _ => Some(window),
+        })
+}
+
+impl Window {
+    /// Notes whether the weather at `coord` helps: a breeze in any step of
+    /// the window, or a clear sky with the sun well up halfway through.
+    pub fn with_weather(self, weather: &ForecastResponse, coord: &Coord) -> Window {
+        let steps: Vec<_> = weather
+            .list
+            .iter()
+            .filter(|entry| entry.dt < self.end && entry.dt + STEP_SECS > self.start)
+            .collect();
+        let breezy = steps.iter().any(|entry| {
+            entry
+                .wind
+                .as_ref()
+                .is_some_and(|wind| units::beaufort(wind.speed) >= BREEZY_FORCE)
+        });
+        let middle = self.start + (self.end - self.start) / 2;
+        let sun_up = DateTime::from_timestamp(middle, 0).is_some_and(|time| {
+            astro::sun_position(coord.lat, coord.lon, time).elevation >= SUNNY_ELEVATION
+        });
+        let clear = forecast::entry_at(weather, middle)
+            .and_then(|entry| entry.weather.first())
+            .is_some_and(|w| (800..=802).contains(&w.id));
+        Window {
+            breezy,
+            sunny: sun_up && clear,
+            ..self
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct National {
+    data: Vec<Period>,
+}
+
+#[derive(Deserialize)]
+struct Region {
+    data: Vec<Period>,
+}
+
+/// The regional endpoints send the region as an object, or in a list of
+/// one.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Regional {
+    One { data: Region },
+    Many { data: Vec<Region> },
+}
+
+/// What the API sends with a failed request, e.g.
+/// `{"error":{"code":"400 Bad Request","message":"Please enter a valid postcode"}}`.
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+pub struct Client {
+    http: reqwest::blocking::Client,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client {
+            http: api::http_client(api::USER_AGENT, &Default::default()).unwrap_or_default(),
+        }
+    }
+
+    /// Half-hourly forecast for the next day from `now`, for a `district`
+    /// as `district()` gives it or, without one, the national average.
+    pub fn forecast(&self, district: Option<&str>, now: i64) -> Result<Vec<Period>, api::Error> {
+        let network = |e: reqwest::Error| api::Error::Network(e.without_url());
+        let from = DateTime::from_timestamp(now, 0)
+            .unwrap_or_default()
+            .format("%Y-%m-%dT%H:%MZ");
+        let url = match district {
+            Some(district) => format!(
+                "{}/regional/intensity/{}/fw24h/postcode/{}",
+                BASE_URL, from, district
+            ),
+            None => format!("{}/intensity/{}/fw24h", BASE_URL, from),
+        };
+        let response = self
+            .http
+            .get(url)
+            .header("Accept", "application/json")
+            .send()
+            .map_err(network)?;
+        let status = response.status();
+        let body = response.text().map_err(network)?;
+
+        if !status.is_success() {
+            let message = match serde_json::from_str::<ErrorBody>(&body) {
+                Ok(body) => body.error.message,
+                Err(_) => status
+                    .canonical_reason()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            };
+            return Err(api::Error::Api {
+                status: status.as_u16(),
+                error: ApiError {
+                    cod: status.as_str().to_string(),
+                    message,
+                },
+            });
+        }
+
+        if district.is_none() {
+            let national: National = serde_json::from_str(&body).map_err(api::Error::Decode)?;
+            return Ok(national.data);
+        }
+        let periods = match serde_json::from_str(&body).map_err(api::Error::Decode)? {
+            Regional::One { data } => data.data,
+            Regional::Many { data } => data.into_iter().flat_map(|region| region.data).collect(),
+        };
+        Ok(periods)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 13:10 UTC on 10 April 2024, so the 13:00 period still counts.
+    const NOW: i64 = 1712754600;
+
+    const PARIS: Coord = Coord {
+        lat: 48.85,
+        lon: 2.35,
+    };
+
+    /// Half-hourly from 13:00 UTC.
+    fn periods() -> Vec<Period> {
+        [300, 250, 90, 80, 85, 70, 300, 310]
+            .iter()
+            .enumerate()
+            .map(|(i, forecast)| {
+                serde_json::from_value(serde_json::json!({
+                    "from": format!("2024-04-10T{:02}:{:02}Z", 13 + i / 2, i % 2 * 30),
+                    "to": "",
+                    "intensity": {"forecast": forecast, "index": "low"},
+                    "generationmix": [{"fuel": "wind", "perc": 30.0}, {"fuel": "solar", "perc": 12.5}]
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn breezy_forecast() -> ForecastResponse {
+        let mut forecast: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/forecast.json")).unwrap();
+        forecast["list"][0]["wind"]["speed"] = 8.0.into();
+        serde_json::from_value(forecast).unwrap()
+    }
+
+    #[test]
+    fn district_is_the_outward_code() {
+        assert_eq!(district("rg10 9ab"), Ok(String::from("RG10")));
+        assert_eq!(district("SW1A 1AA"), Ok(String::from("SW1A")));
+    }
+
+    #[test]
+    fn district_refuses_other_postcodes() {
+        assert!(district("10115").is_err());
+        assert!(district("").is_err());
+    }
+
+    #[test]
+    fn greenest_finds_the_lowest_window() {
+        let window = greenest(&periods(), NOW, DRYER_HOURS).unwrap();
+        assert_eq!((window.start, window.end), (1712757600, 1712764800));
+        assert!((window.intensity - 81.25).abs() < 1e-9);
+        assert_eq!(window.renewable, Some(42.5));
+    }
+
+    #[test]
+    fn greenest_needs_a_whole_load() {
+        assert_eq!(greenest(&periods()[..3], NOW, DRYER_HOURS), None);
+    }
+
+    #[test]
+    fn with_weather_by_day() {
+        let window = greenest(&periods(), NOW, DRYER_HOURS).unwrap();
+        let outlook = window.with_weather(&breezy_forecast(), &PARIS);
+        assert!(outlook.breezy && outlook.sunny);
+    }
+
+    #[test]
+    fn with_weather_gets_no_sun_at_night() {
+        let window = greenest(&periods(), NOW, DRYER_HOURS).unwrap();
+        let night = Window {
+            start: window.start + 8 * 3600,
+            end: window.end + 8 * 3600,
+            ..window
+        };
+        assert!(!night.with_weather(&breezy_forecast(), &PARIS).sunny);
+    }
+}
@@ -0,0 +1,152 @@
+use std::{env, fmt};
+
+use crate::{access, api, matrix, push, storage};
+
+type Source = Box<dyn std::error::Error + Send + Sync>;
+
+/// An error together with what the program was doing when it happened and
+/// what the user can try next. Every front end renders these the same way.
+#[derive(Debug)]
+pub struct Error {
+    source: Source,
+    /// Innermost first, e.g. "fetching the forecast", then "checking thresholds".
+    context: Vec<String>,
+    hints: Vec<String>,
+}
+
+impl Error {
+    pub fn new<E: Into<Source>>(source: E) -> Error {
+        Error {
+            source: source.into(),
+            context: Vec::new(),
+            hints: Vec::new(),
+        }
+    }
+
+    pub fn context<C: Into<String>>(mut self, context: C) -> Error {
+        self.context.push(context.into());
+        self
+    }
+
+    pub fn hint<H: Into<String>>(mut self, hint: H) -> Error {
+        self.hints.push(hint.into());
+        self
+    }
+
+    pub fn hints(&self) -> &[String] {
+        &self.hints
+    }
+
+    /// Outermost first, ending with the underlying error.
+    pub fn chain(&self) -> Vec<String> {
+        let mut chain: Vec<String> = self.context.iter().rev().cloned().collect();
+        chain.push(self.source.to_string());
+        chain
+    }
+
+    /// The underlying error, for callers that react to specific failures.
+    pub fn source_as<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        self.source.downcast_ref()
+    }
+
+    /// Multi-line form for terminals:
+    ///
+    /// ```text
+    /// Error: checking thresholds for Paris, FR
+    ///   while fetching the forecast from OpenWeatherMap
+    ///   caused by: request rejected (401): Invalid API key
+    /// Hint: check your API key
+    /// ```
+    pub fn render(&self) -> String {
+        let chain = self.chain();
+        let mut lines = vec![format!("Error: {}", chain[0])];
+
+        if let Some((cause, steps)) = chain[1..].split_last() {
+            lines.extend(steps.iter().map(|step| format!("  while {}", step)));
+            lines.push(format!("  caused by: {}", cause));
+        }
+        lines.extend(self.hints.iter().map(|hint| format!("Hint: {}", hint)));
+
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.chain().join(": "))
+    }
+}
+
+impl From<api::Error> for Error {
+    fn from(e: api::Error) -> Self {
+        let mut hints: Vec<String> = e.hint().into_iter().map(String::from).collect();
+
+        if let api::Error::Network(_) = e {
+            let proxy = ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]
+                .into_iter()
+                .find(|var| env::var_os(var).is_some());
+            if let Some(var) = proxy {
+                hints.push(format!("check your proxy settings ({} is set)", var));
+            }
+        }
+
+        Error {
+            hints,
+            ..Error::new(e)
+        }
+    }
+}
+
+impl From<access::Denied> for Error {
+    fn from(e: access::Denied) -> Self {
+        Error {
+            hints: e.hint().into_iter().map(String::from).collect(),
+            ..Error::new(e)
+        }
+    }
+}
+
+impl From<matrix::Error> for Error {
+    fn from(e: matrix::Error) -> Self {
+        Error {
+            hints: e.hint().into_iter().map(String::from).collect(),
+            ..Error::new(e)
+        }
+    }
+}
+
+impl From<push::Error> for Error {
+    fn from(e: push::Error) -> Self {
+        Error {
+            hints: e.hint().into_iter().map(String::from).collect(),
+            ..Error::new(e)
+        }
+    }
+}
+
+impl From<storage::Error> for Error {
+    fn from(e: storage::Error) -> Self {
+        let hint = match e {
+            storage::Error::Io(_) => "check the file permissions",
+            storage::Error::Parse(_) => "fix the file, or move it aside to start from defaults",
+            storage::Error::Newer { .. } => "install the latest cli_weather",
+        };
+        Error::new(e).hint(hint)
+    }
+}
+
+/// Adds a layer of context to a failed result, converting it on the way.
+pub trait Context<T> {
+    fn context<C: Into<String>>(self, context: C) -> Result<T, Error>;
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn context<C: Into<String>>(self, context: C) -> Result<T, Error> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Into<String>, F: FnOnce() -> C>(self, f: F) -> Result<T, Error> {
+        self.map_err(|e| e.into().context(f()))
+    }
+}
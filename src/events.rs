@@ -0,0 +1,291 @@
+//! Notable weather seen at each place, such as the year's first frost, kept
+//! for `events list`. The log is JSON Lines and only ever appended to, one
+//! event per line, so recording never rewrites what is already there.
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+use crate::api::{Alert, WeatherResponse};
+use crate::progress;
+use crate::storage;
+
+const DATA_DIR: &str = "cli_weather";
+const EVENTS_FILE: &str = "events.jsonl";
+/// °C at or below which a reading counts as frost.
+pub const FROST: f64 = 0.0;
+/// °C above which a reading counts as a hot day.
+pub const HOT: f64 = 30.0;
+/// Alerts whose name has one of these in it are storms.
+const STORM_WORDS: &[&str] = &[
+    "storm",
+    "thunder",
+    "gale",
+    "hurricane",
+    "typhoon",
+    "cyclone",
+    "tornado",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// The first reading of the year at or below `FROST`.
+    FirstFrost,
+    /// The first reading of the year above `HOT`.
+    FirstHot,
+    /// A storm the provider sent an alert for.
+    Storm,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::FirstFrost => write!(f, "first frost"),
+            Kind::FirstHot => write!(f, "first day over {}°C", HOT),
+            Kind::Storm => write!(f, "storm"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: Kind,
+    pub city: String,
+    /// The day at the place, as YYYY-MM-DD.
+    pub date: String,
+    /// Unix time it was seen, or the alert's start for storms.
+    pub at: i64,
+    /// °C, for frost and heat.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp: Option<f64>,
+    /// The alert's name, for storms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alert: Option<String>,
+}
+
+impl Event {
+    pub fn date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d").ok()
+    }
+
+    pub fn year(&self) -> Option<i32> {
+        self.date().map(|date| date.year())
+    }
+}
+
+fn local_date(at: i64, offset: i32) -> Option<NaiveDate> {
+    let offset = FixedOffset::east_opt(offset)?;
+    DateTime::from_timestamp(at, 0).map(|time| time.with_timezone(&offset).date_naive())
+}
+
+fn is_storm(alert: &Alert) -> bool {
+    let event = alert.event.to_lowercase();
+    STORM_WORDS.iter().any(|word| event.contains(word))
+}
+
+/// The events in `weather` and `alerts` at `now` that are not in `logged`
+/// yet: each first of the year once per place, each storm alert once.
+pub fn detect(
+    weather: &WeatherResponse,
+    alerts: &[Alert],
+    logged: &[Event],
+    now: i64,
+) -> Vec<Event> {
+    let city = &weather.name;
+    let mut events = Vec::new();
+    let Some(today) = local_date(now, weather.timezone) else {
+        return events;
+    };
+    let seen_this_year = |kind: Kind| {
+        logged
+            .iter()
+            .any(|e| e.kind == kind && e.city == *city && e.year() == Some(today.year()))
+    };
+
+    let temp = weather.main.temp;
+    for (kind, reached) in [
+        (Kind::FirstFrost, temp <= FROST),
+        (Kind::FirstHot, temp > HOT),
+    ] {
+        if reached && !seen_this_year(kind) {
+            events.push(Event {
+                kind,
+                city: city.clone(),
+                date: today.format("%Y-%m-%d").to_string(),
+                at: now,
+                temp: Some(temp),
+                alert: None,
+            });
+        }
+    }
+
+    for alert in alerts.iter().filter(|a| is_storm(a) && now < a.end) {
+        let known = |e: &Event| {
+            e.kind == Kind::Storm
+                && e.city == *city
+                && e.at == alert.start
+                && e.alert.as_deref() == Some(alert.event.as_str())
+        };
+        if logged.iter().chain(&events).any(known) {
+            continue;
+        }
+        let Some(date) = local_date(alert.start, weather.timezone) else {
+            continue;
+        };
+        events.push(Event {
+            kind: Kind::Storm,
+            city: city.clone(),
+            date: date.format("%Y-%m-%d").to_string(),
+            at: alert.start,
+            temp: None,
+            alert: Some(alert.event.clone()),
+        });
+    }
+
+    events
+}
+
+/// The events at `city`, ignoring case, and in `year`, oldest first.
+pub fn matching<'a>(events: &'a [Event], city: Option<&str>, year: Option<i32>) -> Vec<&'a Event> {
+    let mut found: Vec<&Event> = events
+        .iter()
+        .filter(|e| city.is_none_or(|city| e.city.eq_ignore_ascii_case(city)))
+        .filter(|e| year.is_none_or(|year| e.year() == Some(year)))
+        .collect();
+    found.sort_by_key(|e| e.at);
+    found
+}
+
+pub struct Log {
+    path: PathBuf,
+}
+
+impl Log {
+    pub fn new(path: PathBuf) -> Log {
+        Log { path }
+    }
+
+    pub fn open() -> Option<Log> {
+        dirs::data_dir().map(|dir| Log::new(dir.join(DATA_DIR).join(EVENTS_FILE)))
+    }
+
+    /// Every event logged, in the order recorded; empty before the first.
+    pub fn read(&self) -> Result<Vec<Event>, storage::Error> {
+        self.lines()?.into_iter().collect()
+    }
+
+    /// Each line read as an event, so one cut short by an interrupted
+    /// append can be told apart from the rest.
+    fn lines(&self) -> Result<Vec<Result<Event, storage::Error>>, storage::Error> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(storage::Error::Io(e)),
+        };
+        Ok(contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| storage::Error::Parse(format!("line {}: {}", number + 1, e)))
+            })
+            .collect())
+    }
+
+    pub fn append(&self, events: &[Event]) -> Result<(), storage::Error> {
+        let mut lines = String::new();
+        for event in events {
+            let line =
+                serde_json::to_string(event).map_err(|e| storage::Error::Parse(e.to_string()))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        // A line cut short by an interrupted append is ended first, so the
+        // new events do not run on from it.
+        if file.seek(SeekFrom::End(-1)).is_ok() {
+            let mut last = [0];
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                lines.insert(0, '\n');
+            }
+        }
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+
+    /// Logs whatever `detect` finds and returns it. Lines that cannot be
+    /// read are warned about and skipped, so one bad line does not stop
+    /// everything after it from being logged.
+    pub fn record(
+        &self,
+        weather: &WeatherResponse,
+        alerts: &[Alert],
+        now: i64,
+    ) -> Result<Vec<Event>, storage::Error> {
+        let logged: Vec<Event> = self
+            .lines()?
+            .into_iter()
+            .filter_map(|line| {
+                line.inspect_err(|e| {
+                    progress::suspend(|| {
+                        eprintln!(
+                            "Warning: skipped an unreadable event in {}: {}",
+                            self.path.display(),
+                            e
+                        )
+                    })
+                })
+                .ok()
+            })
+            .collect();
+        let events = detect(weather, alerts, &logged, now);
+        if !events.is_empty() {
+            self.append(&events)?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather_at(temp: f64) -> WeatherResponse {
+        let mut weather: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/weather.json")).unwrap();
+        weather["main"]["temp"] = temp.into();
+        serde_json::from_value(weather).unwrap()
+    }
+
+    #[test]
+    fn record_skips_a_line_cut_short() {
+        let dir =
+            std::env::temp_dir().join(format!("cli_weather-events-cut-{}", std::process::id()));
+        let log = Log::new(dir.join(EVENTS_FILE));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&log.path, "{\"kind\":\"first_fr").unwrap();
+        assert!(log.read().is_err());
+
+        let recorded = log.record(&weather_at(-1.5), &[], 1_760_010_000).unwrap();
+        assert_eq!(recorded.len(), 1);
+        let lines = log.lines().unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].is_err());
+        assert_eq!(lines[1].as_ref().unwrap(), &recorded[0]);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
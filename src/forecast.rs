@@ -0,0 +1,149 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::api::{ForecastEntry, ForecastResponse, Weather};
+
+/// Length of one forecast entry.
+pub const STEP_SECS: i64 = 3 * 3600;
+
+const RAINY_CONDITIONS: [&str; 3] = ["Rain", "Drizzle", "Thunderstorm"];
+
+/// The first forecast entry with rain in it.
+pub struct RainSpell<'a> {
+    pub entry: &'a ForecastEntry,
+    pub description: String,
+}
+
+impl RainSpell<'_> {
+    pub fn mm_per_hour(&self) -> f64 {
+        let total = self.entry.rain.as_ref().map_or(0.0, |r| r.three_hours);
+        total / (STEP_SECS / 3600) as f64
+    }
+
+    /// Rates as used by the Met Office: light up to 2.5 mm/h, heavy from 7.6 mm/h.
+    pub fn intensity(&self) -> &'static str {
+        match self.mm_per_hour() {
+            rate if rate < 2.5 => "light",
+            rate if rate < 7.6 => "moderate",
+            _ => "heavy",
+        }
+    }
+}
+
+/// Looks through entries still in progress or starting within `hours`.
+pub fn first_rain(forecast: &ForecastResponse, now: i64, hours: u32) -> Option<RainSpell<'_>> {
+    let horizon = now.saturating_add(i64::from(hours) * 3600);
+
+    forecast
+        .list
+        .iter()
+        .filter(|entry| entry.dt.saturating_add(STEP_SECS) > now && entry.dt <= horizon)
+        .find_map(|entry| {
+            let condition = entry
+                .weather
+                .iter()
+                .find(|w| RAINY_CONDITIONS.contains(&w.main.as_str()));
+            let measured = entry.rain.as_ref().is_some_and(|r| r.three_hours > 0.0);
+
+            match condition {
+                Some(w) => Some(RainSpell {
+                    entry,
+                    description: w.description.clone(),
+                }),
+                None if measured => Some(RainSpell {
+                    entry,
+                    description: String::from("rain"),
+                }),
+                None => None,
+            }
+        })
+}
+
+/// The highest probability of precipitation, 0 to 1, in entries still in
+/// progress or starting within `hours`.
+pub fn rain_chance(forecast: &ForecastResponse, now: i64, hours: u32) -> f64 {
+    let horizon = now.saturating_add(i64::from(hours) * 3600);
+
+    forecast
+        .list
+        .iter()
+        .filter(|entry| entry.dt.saturating_add(STEP_SECS) > now && entry.dt <= horizon)
+        .map(|entry| entry.pop)
+        .fold(0.0, f64::max)
+}
+
+/// The entry nearest `time`, when one starts within a step of it.
+pub fn entry_at(forecast: &ForecastResponse, time: i64) -> Option<&ForecastEntry> {
+    forecast
+        .list
+        .iter()
+        .min_by_key(|entry| (entry.dt - time).abs())
+        .filter(|entry| (entry.dt - time).abs() < STEP_SECS)
+}
+
+/// One local calendar day of the forecast, which may cover only part of
+/// the day at either end.
+pub struct Day<'a> {
+    pub date: NaiveDate,
+    pub min: f64,
+    pub max: f64,
+    /// The worst weather of the day, so a rainy afternoon is not hidden
+    /// by a clear morning.
+    pub condition: Option<&'a Weather>,
+    pub rain_mm: f64,
+    /// Highest probability of precipitation, 0 to 1.
+    pub pop: f64,
+    /// The 3-hour steps that fall on this day.
+    pub entries: Vec<&'a ForecastEntry>,
+}
+
+/// How bad a condition is, for picking each day's worst.
+fn severity(condition: &Weather) -> (u8, u32) {
+    let group = match condition.id {
+        200..=299 => 6,
+        600..=699 => 5,
+        500..=599 => 4,
+        300..=399 => 3,
+        700..=799 => 2,
+        801..=899 => 1,
+        _ => 0,
+    };
+    (group, condition.id)
+}
+
+/// The forecast grouped by local date at `offset`, in order.
+pub fn days(forecast: &ForecastResponse, offset: FixedOffset) -> Vec<Day<'_>> {
+    let mut days: Vec<Day> = Vec::new();
+
+    for entry in &forecast.list {
+        let Some(date) = DateTime::from_timestamp(entry.dt, 0)
+            .map(|time| time.with_timezone(&offset).date_naive())
+        else {
+            continue;
+        };
+        let condition = entry.weather.iter().max_by_key(|w| severity(w));
+        let rain = entry.rain.as_ref().map_or(0.0, |r| r.three_hours);
+
+        match days.last_mut() {
+            Some(day) if day.date == date => {
+                day.min = day.min.min(entry.main.temp_min);
+                day.max = day.max.max(entry.main.temp_max);
+                day.rain_mm += rain;
+                day.pop = day.pop.max(entry.pop);
+                day.entries.push(entry);
+                if condition.map(severity) > day.condition.map(severity) {
+                    day.condition = condition;
+                }
+            }
+            _ => days.push(Day {
+                date,
+                min: entry.main.temp_min,
+                max: entry.main.temp_max,
+                condition,
+                rain_mm: rain,
+                pop: entry.pop,
+                entries: vec![entry],
+            }),
+        }
+    }
+    days
+}
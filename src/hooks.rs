@@ -0,0 +1,84 @@
+//! Commands `watch` runs when the sky changes, such as from clear to rain,
+//! to swap a wallpaper or dim the lights. Each gets the weather as JSON on
+//! stdin and the old and new conditions in its environment.
+
+use std::{
+    io::{self, Write},
+    process::{Child, Command, Stdio},
+};
+
+use crate::api::WeatherResponse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Clear,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Storm,
+}
+
+impl Category {
+    /// By OpenWeatherMap condition code; None for codes it does not use.
+    pub fn of(weather: &WeatherResponse) -> Option<Category> {
+        let category = match weather.weather.first()?.id {
+            200..=299 => Category::Storm,
+            300..=399 | 500..=599 => Category::Rain,
+            600..=699 => Category::Snow,
+            700..=799 => Category::Fog,
+            800 => Category::Clear,
+            801..=804 => Category::Cloudy,
+            _ => return None,
+        };
+        Some(category)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Clear => "clear",
+            Category::Cloudy => "cloudy",
+            Category::Fog => "fog",
+            Category::Rain => "rain",
+            Category::Snow => "snow",
+            Category::Storm => "storm",
+        }
+    }
+}
+
+/// `line` run by the shell, as `sh -c` or `cmd /C` on Windows.
+pub fn shell(line: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut command = Command::new(shell);
+    command.args([flag, line]);
+    command
+}
+
+/// Starts `line` with `weather` on stdin, CLI_WEATHER_CONDITION set to
+/// `now` and CLI_WEATHER_PREVIOUS to `before`, empty on the first refresh.
+/// It is not waited for.
+pub fn run(
+    line: &str,
+    weather: &WeatherResponse,
+    before: Option<Category>,
+    now: Category,
+) -> io::Result<Child> {
+    let mut child = shell(line)
+        .env("CLI_WEATHER_CONDITION", now.name())
+        .env("CLI_WEATHER_PREVIOUS", before.map_or("", Category::name))
+        .stdin(Stdio::piped())
+        .spawn()?;
+    let report = serde_json::to_vec(weather).map_err(io::Error::other)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that does not read its input is fine.
+        match stdin.write_all(&report) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(child)
+}
@@ -0,0 +1,275 @@
+//! Just enough of iCalendar (RFC 5545) to write all-day events that
+//! calendar apps can subscribe to or import, and to read when and where
+//! the events in an exported calendar are.
+
+use chrono::{DateTime, Days, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use std::fmt;
+
+/// Content lines longer than this many bytes are folded.
+const LINE_LIMIT: usize = 75;
+
+pub struct Event {
+    /// Stays the same across exports, so re-importing replaces the event.
+    pub uid: String,
+    pub date: NaiveDate,
+    pub summary: String,
+    pub description: String,
+}
+
+/// A whole calendar; `stamp` is when it was generated.
+pub fn calendar(name: &str, events: &[Event], stamp: DateTime<Utc>) -> String {
+    let stamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!(
+            "PRODID:-//{}//{}//EN",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ),
+        String::from("CALSCALE:GREGORIAN"),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+
+    for event in events {
+        let end = event
+            .date
+            .checked_add_days(Days::new(1))
+            .unwrap_or(event.date);
+        lines.extend([
+            String::from("BEGIN:VEVENT"),
+            format!("UID:{}", escape(&event.uid)),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")),
+            format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")),
+            format!("SUMMARY:{}", escape(&event.summary)),
+            format!("DESCRIPTION:{}", escape(&event.description)),
+            // Weather should not make anyone look busy.
+            String::from("TRANSP:TRANSPARENT"),
+            String::from("END:VEVENT"),
+        ]);
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Escapes text values: backslashes, commas, semicolons and newlines.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits `line` into pieces of at most `LINE_LIMIT` bytes, never inside
+/// a character; continuation lines start with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > LINE_LIMIT {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+/// When an imported event starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Start {
+    At(DateTime<Utc>),
+    /// A time on the wall clock, either floating or in a named zone, which
+    /// is taken as local time wherever the event is.
+    Local(NaiveDateTime),
+    AllDay(NaiveDate),
+}
+
+impl Start {
+    /// The moment it starts at a place `offset` from UTC; all-day events
+    /// count from noon.
+    pub fn at(&self, offset: FixedOffset) -> DateTime<Utc> {
+        let local = match self {
+            Start::At(time) => return *time,
+            Start::Local(time) => *time,
+            Start::AllDay(date) => date.and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+        };
+        local
+            .and_local_timezone(offset)
+            .single()
+            .map_or_else(|| local.and_utc(), |time| time.with_timezone(&Utc))
+    }
+}
+
+/// An event read from a calendar. Only its first occurrence is kept, as
+/// repeat rules are not followed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Appointment {
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: Start,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// 1-based, of the content line's first physical line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Every event in `text` that says when it starts, in file order. Alarms
+/// and other components inside events are skipped.
+pub fn read(text: &str) -> Result<Vec<Appointment>, ParseError> {
+    let mut appointments = Vec::new();
+    // Summary, location and start of the event being read.
+    let mut event: Option<(String, Option<String>, Option<Start>)> = None;
+    let mut nested = 0;
+
+    for (line, content) in unfold(text) {
+        let error = |message: String| ParseError { line, message };
+        let Some((name, params, value)) = split(&content) else {
+            return Err(error(format!("'{}' is not a content line", content)));
+        };
+        match (name.as_str(), event.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some((String::new(), None, None));
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some((summary, location, Some(start))) = event.take() {
+                    appointments.push(Appointment {
+                        summary,
+                        location,
+                        start,
+                    });
+                }
+            }
+            (_, Some(_)) if nested > 0 => {}
+            ("SUMMARY", Some((summary, _, _))) => *summary = unescape(value),
+            ("LOCATION", Some((_, location, _))) => {
+                let place = unescape(value);
+                *location = Some(place).filter(|place| !place.trim().is_empty());
+            }
+            ("DTSTART", Some((_, _, start))) => {
+                *start = Some(parse_start(params, value).map_err(error)?);
+            }
+            _ => {}
+        }
+    }
+    Ok(appointments)
+}
+
+/// Content lines with their line numbers, continuation lines joined on.
+fn unfold(text: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some((_, last))) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push((number + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// The upper-cased name, the parameters and the value, split at the first
+/// colon outside quotes.
+fn split(line: &str) -> Option<(String, &str, &str)> {
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(i),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let (name, params) = head.split_once(';').unwrap_or((head, ""));
+    Some((name.to_ascii_uppercase(), params, value))
+}
+
+fn parse_start(params: &str, value: &str) -> Result<Start, String> {
+    let value = value.trim();
+    let invalid = || format!("'{}' is not a date or time", value);
+    let all_day = params
+        .split(';')
+        .any(|param| param.eq_ignore_ascii_case("VALUE=DATE"));
+    if all_day || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(Start::AllDay)
+            .map_err(|_| invalid());
+    }
+    let (local, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(local) => (local, true),
+        None => (value, false),
+    };
+    let time = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    Ok(if utc {
+        Start::At(time.and_utc())
+    } else {
+        Start::Local(time)
+    })
+}
+
+/// Undoes `escape`, also taking `\N` for a newline.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// What to give the geocoder for an event's location, best first: all of
+/// it, then each part before the last with the last, as in "Paris,
+/// France" from "Café de Flore, 172 Bd Saint-Germain, 75006 Paris,
+/// France". Words with digits in them, such as postcodes, are dropped
+/// from the parts.
+pub fn place_queries(location: &str) -> Vec<String> {
+    let parts: Vec<&str> = location
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    let mut queries = vec![location.trim().to_string()];
+    if let Some((last, rest)) = parts.split_last() {
+        for part in rest.iter().rev() {
+            let words: Vec<&str> = part
+                .split_whitespace()
+                .filter(|word| !word.chars().any(|c| c.is_ascii_digit()))
+                .collect();
+            if words.is_empty() {
+                continue;
+            }
+            let query = format!("{}, {}", words.join(" "), last);
+            if !queries.contains(&query) {
+                queries.push(query);
+            }
+        }
+    }
+    queries
+}
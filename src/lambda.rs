@@ -0,0 +1,332 @@
+//! `lambda`: one JSON request on stdin and one JSON response on stdout,
+//! so the binary can back a serverless function or a chatbot that runs it
+//! per request. Nothing is asked for and nothing but the response goes to
+//! stdout; it exits 1 when there is no report to give.
+//!
+//! ```json
+//! {"location": {"city": "Paris", "country": "FR"},
+//!  "layers": ["forecast", "uv", "advice"],
+//!  "units": {"temperature": "fahrenheit"}}
+//! ```
+//!
+//! The current weather is always in the response. A layer that cannot be
+//! fetched is left out and its error is given under `errors` instead.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    io::{self, Read},
+    process,
+};
+
+use cli_weather::advice::{self, Conditions};
+use cli_weather::api::{self, Alert, WeatherResponse};
+use cli_weather::config::{self, Config};
+use cli_weather::display::{self, Part, ReportOptions};
+use cli_weather::error::{self, Context};
+use cli_weather::units::{self, Preferences};
+use cli_weather::uv::{self, Risk};
+use cli_weather::{credentials, forecast};
+
+use crate::serve::{location_from_json, ErrorBody, Units};
+use crate::{current_weather, http_client, new_client, now, ADVICE_HOURS};
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+enum Layer {
+    Forecast,
+    Uv,
+    Alerts,
+    Advice,
+    /// The report as the terminal shows it, without colors.
+    Text,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    /// `city` and `country`, `coords`, or `lat` and `lon`, as for `serve`.
+    location: BTreeMap<String, Value>,
+    #[serde(default)]
+    layers: Vec<Layer>,
+    /// Any of `temperature`, `wind` and `clock`, over the config's.
+    #[serde(default)]
+    units: BTreeMap<String, String>,
+    #[serde(default)]
+    lang: Option<String>,
+}
+
+/// The current weather in the units asked for.
+#[derive(Serialize)]
+struct Current {
+    city: String,
+    description: Option<String>,
+    temp: f64,
+    feels_like: f64,
+    humidity: f64,
+    /// hPa.
+    pressure: f64,
+    wind: f64,
+    wind_direction: Option<&'static str>,
+    gust: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ForecastDay {
+    date: String,
+    low: f64,
+    high: f64,
+    description: Option<String>,
+    rain_mm: f64,
+    /// Percent.
+    rain_chance: f64,
+}
+
+#[derive(Serialize)]
+struct Uv {
+    now: f64,
+    today_max: Option<f64>,
+    risk: &'static str,
+}
+
+#[derive(Serialize)]
+struct Tip {
+    rule: &'static str,
+    tip: String,
+}
+
+#[derive(Serialize)]
+struct Response {
+    location: String,
+    units: Units,
+    current: Current,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    forecast: Option<Vec<ForecastDay>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uv: Option<Uv>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alerts: Option<Vec<Alert>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    advice: Option<Vec<Tip>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    /// By layer, for those that could not be fetched.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    errors: BTreeMap<&'static str, ErrorBody>,
+}
+
+/// To one decimal, as the terminal report shows.
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+pub fn run(api_key: Option<&str>, lang: Option<String>) {
+    colored::control::set_override(false);
+    match respond(api_key, lang) {
+        Ok(response) => println!("{}", to_json(&response)),
+        Err(e) => {
+            println!("{}", to_json(&ErrorBody::of(&e)));
+            process::exit(1);
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("responses always serialize")
+}
+
+fn read_request() -> Result<Request, error::Error> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(error::Error::new)
+        .context("reading the request from stdin")?;
+    serde_json::from_str(&input)
+        .map_err(|e| {
+            error::Error::new(e).hint(
+                r#"send e.g. {"location":{"city":"Paris","country":"FR"},"layers":["forecast"]}"#,
+            )
+        })
+        .context("reading the request")
+}
+
+fn respond(api_key: Option<&str>, lang: Option<String>) -> Result<Response, error::Error> {
+    let request = read_request()?;
+    let config = config::load()?;
+    let preferences = preferences(&config, &request.units)?;
+    units::init(preferences);
+
+    let location = location_from_json(&request.location)
+        .map_err(error::Error::new)
+        .context("reading the request's location")?;
+
+    let api_key = credentials::lookup(api_key, config.api_key.as_deref()).ok_or_else(|| {
+        error::Error::new("no API key found").hint(format!(
+            "set {} in the function's environment",
+            credentials::ENV_VAR
+        ))
+    })?;
+    let client = new_client(
+        &api_key,
+        request.lang.clone().or(lang),
+        http_client(&config)?,
+        &config,
+    )?;
+
+    let weather = current_weather(&location, &client)?;
+    let mut errors = BTreeMap::new();
+    let mut failed = |layer: &'static str, e: error::Error| {
+        errors.insert(layer, ErrorBody::of(&e));
+    };
+    let wants = |layer: Layer| request.layers.contains(&layer);
+
+    let fetched_forecast = (wants(Layer::Forecast) || wants(Layer::Advice)).then(|| {
+        client
+            .forecast(&api::Location::Coordinates(weather.coord))
+            .context(format!("fetching the forecast from {}", api::PROVIDER))
+    });
+    let fetched_uv = (wants(Layer::Uv) || wants(Layer::Advice) || wants(Layer::Text)).then(|| {
+        client
+            .uv(&weather.coord)
+            .map(|forecast| uv::Summary::new(&forecast))
+            .context(format!("fetching the UV index from {}", api::PROVIDER))
+    });
+
+    let offset = display::utc_offset(weather.timezone);
+    let mut forecast_days = None;
+    let mut rain_chance = None;
+    match fetched_forecast {
+        Some(Ok(fetched)) => {
+            rain_chance = Some(forecast::rain_chance(&fetched, now(), ADVICE_HOURS));
+            forecast_days = wants(Layer::Forecast).then(|| days(&fetched, offset, preferences));
+        }
+        Some(Err(e)) => failed("forecast", e),
+        None => {}
+    }
+    let uv = match fetched_uv {
+        Some(Ok(summary)) => Some(summary),
+        Some(Err(e)) => {
+            failed("uv", e);
+            None
+        }
+        None => None,
+    };
+
+    let alerts = if wants(Layer::Alerts) {
+        match client
+            .alerts(&weather.coord)
+            .context(format!("fetching weather alerts from {}", api::PROVIDER))
+        {
+            Ok(alerts) => Some(alerts),
+            Err(e) => {
+                failed("alerts", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let advice = wants(Layer::Advice).then(|| {
+        advice::advise(&Conditions::new(&weather, rain_chance, uv))
+            .into_iter()
+            .map(|(rule, tip)| Tip { rule, tip })
+            .collect()
+    });
+    let text = wants(Layer::Text).then(|| {
+        let options = ReportOptions {
+            uv: Part::from(uv),
+            ..ReportOptions::default()
+        };
+        display::render_weather_info(&weather, &options)
+            .trim()
+            .to_string()
+    });
+
+    Ok(Response {
+        location: location.to_string(),
+        units: Units::of(preferences),
+        current: current(&weather, preferences),
+        forecast: forecast_days,
+        uv: wants(Layer::Uv).then_some(uv).flatten().map(|uv| Uv {
+            now: uv.now,
+            today_max: uv.today_max,
+            risk: Risk::from_index(uv.today_max.unwrap_or(uv.now).max(uv.now)).name(),
+        }),
+        alerts,
+        advice,
+        text,
+        errors,
+    })
+}
+
+/// The config's units with those in the request over them.
+fn preferences(
+    config: &Config,
+    asked: &BTreeMap<String, String>,
+) -> Result<Preferences, error::Error> {
+    let mut preferences = config.units.preferences()?;
+    for (name, value) in asked {
+        let context = || format!("reading units.{} in the request", name);
+        match name.as_str() {
+            "temperature" => {
+                preferences.temperature = value
+                    .parse()
+                    .map_err(error::Error::new)
+                    .with_context(context)?
+            }
+            "wind" => {
+                preferences.wind = value
+                    .parse()
+                    .map_err(error::Error::new)
+                    .with_context(context)?
+            }
+            "clock" => {
+                preferences.clock = value
+                    .parse()
+                    .map_err(error::Error::new)
+                    .with_context(context)?
+            }
+            _ => {
+                return Err(error::Error::new(format!("unknown unit '{}'", name))
+                    .hint("units are temperature, wind and clock"))
+            }
+        }
+    }
+    Ok(preferences)
+}
+
+fn current(weather: &WeatherResponse, preferences: Preferences) -> Current {
+    let temp = |celsius: f64| round(preferences.temperature.from_celsius(celsius));
+    let speed = |mps: f64| round(preferences.wind.from_mps(mps));
+    Current {
+        city: weather.name.clone(),
+        description: weather.weather.first().map(|w| w.description.clone()),
+        temp: temp(weather.main.temp),
+        feels_like: temp(weather.main.feels_like),
+        humidity: weather.main.humidity,
+        pressure: weather.main.pressure,
+        wind: speed(weather.wind.speed),
+        wind_direction: weather.wind.deg.map(units::compass_point),
+        gust: weather.wind.gust.map(speed),
+    }
+}
+
+fn days(
+    fetched: &api::ForecastResponse,
+    offset: chrono::FixedOffset,
+    preferences: Preferences,
+) -> Vec<ForecastDay> {
+    let temp = |celsius: f64| round(preferences.temperature.from_celsius(celsius));
+    forecast::days(fetched, offset)
+        .into_iter()
+        .map(|day| ForecastDay {
+            date: day.date.to_string(),
+            low: temp(day.min),
+            high: temp(day.max),
+            description: day.condition.map(|w| w.description.clone()),
+            rain_mm: round(day.rain_mm),
+            rain_chance: (day.pop * 100.0).round(),
+        })
+        .collect()
+}
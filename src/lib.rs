@@ -0,0 +1,44 @@
+pub mod access;
+pub mod advice;
+pub mod api;
+pub mod art;
+pub mod astro;
+pub mod cache;
+pub mod chart;
+pub mod circuit;
+pub mod climate;
+pub mod comfort;
+pub mod config;
+pub mod coords;
+pub mod countries;
+pub mod credentials;
+pub mod display;
+pub mod energy;
+pub mod error;
+pub mod events;
+pub mod forecast;
+pub mod hooks;
+pub mod ical;
+pub mod locale;
+pub mod matrix;
+pub mod metrics;
+pub mod middleware;
+pub mod moment;
+pub mod notify;
+pub mod progress;
+pub mod push;
+pub mod recent;
+pub mod rule;
+pub mod snapshot;
+pub mod speech;
+pub mod storage;
+pub mod telemetry;
+pub mod template;
+pub mod theme;
+pub mod tint;
+pub mod tour;
+pub mod trip;
+pub mod units;
+pub mod usage;
+pub mod uv;
+pub mod what3words;
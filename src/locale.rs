@@ -0,0 +1,100 @@
+//! Guesses at sensible defaults from the system locale, for the config file
+//! written on first run.
+
+use crate::units::{Clock, Preferences, SpeedUnit, TempUnit};
+
+/// Regions that still measure in Fahrenheit and miles.
+const IMPERIAL: &[&str] = &["US", "LR", "MM"];
+
+/// Regions where the 12-hour clock is the usual one.
+const TWELVE_HOUR: &[&str] = &[
+    "US", "CA", "AU", "NZ", "IN", "PH", "PK", "EG", "SA", "BD", "MY",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Locale {
+    /// e.g. "en", lowercase.
+    pub language: Option<String>,
+    /// e.g. "US", uppercase.
+    pub region: Option<String>,
+}
+
+impl Locale {
+    /// The first of `LC_ALL`, `LC_MESSAGES` and `LANG` that is set.
+    pub fn from_env() -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.trim().is_empty())
+            .map(|value| Locale::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parses POSIX names such as "en_US.UTF-8" or "de_DE@euro". "C" and
+    /// "POSIX" name no language.
+    pub fn parse(name: &str) -> Locale {
+        let name = name.split(['.', '@']).next().unwrap_or_default().trim();
+        if name.is_empty() || name == "C" || name == "POSIX" {
+            return Locale::default();
+        }
+
+        let mut parts = name.split(['_', '-']);
+        let language = parts
+            .next()
+            .filter(|lang| lang.len() >= 2 && lang.chars().all(|c| c.is_ascii_alphabetic()))
+            .map(str::to_lowercase);
+        let region = parts
+            .next()
+            .filter(|region| region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+            .map(str::to_uppercase);
+        Locale { language, region }
+    }
+
+    /// The language a client prefers most from an HTTP `Accept-Language`
+    /// header, e.g. "de-CH, de;q=0.9, en;q=0.5"; None when it names none.
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut best: Option<(f64, Locale)> = None;
+        for range in header.split(',') {
+            let mut parts = range.split(';');
+            let tag = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f64>().ok());
+            let Some(quality) = quality.filter(|q| *q > 0.0) else {
+                continue;
+            };
+            let locale = Locale::parse(tag);
+            if locale.language.is_none() {
+                continue;
+            }
+            if best.as_ref().is_none_or(|(most, _)| quality > *most) {
+                best = Some((quality, locale));
+            }
+        }
+        best.map(|(_, locale)| locale)
+    }
+
+    /// Imperial units in the US and the like, metric elsewhere; the clock by
+    /// region, 24-hour when there is none.
+    pub fn preferences(&self) -> Preferences {
+        let region = self.region.as_deref().unwrap_or_default();
+        let imperial = IMPERIAL.contains(&region);
+        Preferences {
+            temperature: if imperial {
+                TempUnit::Fahrenheit
+            } else {
+                TempUnit::Celsius
+            },
+            wind: if imperial {
+                SpeedUnit::MilesPerHour
+            } else {
+                SpeedUnit::MetersPerSecond
+            },
+            clock: if TWELVE_HOUR.contains(&region) {
+                Clock::TwelveHour
+            } else {
+                Clock::TwentyFourHour
+            },
+        }
+    }
+}
@@ -1,186 +1,2457 @@
+mod lambda;
+mod prompt;
+mod serve;
+
+use chrono::{NaiveDate, Utc};
+use clap::{
+    builder::{PossibleValue, TypedValueParser},
+    Args, CommandFactory, Parser, Subcommand,
+};
+use clap_complete::Shell;
 use colored::*;
-use serde::{Deserialize, Serialize};
-use std::{env::VarError, io};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    ffi::OsStr,
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use prompt::Prompt;
 
-const API_NAME_KEY: &str = "API_KEY";
+use cli_weather::cache::Cache;
+use cli_weather::circuit::Breaker;
+use cli_weather::climate;
+use cli_weather::config::{self, Comfort, Config, MonitorPlace, RetentionSettings, Thresholds};
+use cli_weather::display::{self, IconSet, Part, Reading, ReportOptions};
+use cli_weather::energy;
+use cli_weather::error::{self, Context};
+use cli_weather::events;
+use cli_weather::forecast;
+use cli_weather::hooks::{self, Category};
+use cli_weather::ical;
+use cli_weather::matrix;
+use cli_weather::metrics::Metrics;
+use cli_weather::moment::{self, Moment};
+use cli_weather::notify::{self, PastDay, Trigger};
+use cli_weather::progress;
+use cli_weather::push;
+use cli_weather::recent::{self, DayRecord, Totals};
+use cli_weather::snapshot::{self, Snapshot};
+use cli_weather::telemetry::{self, Tracer};
+use cli_weather::template::Template;
+use cli_weather::theme::{self, paint, Builtin, Role, Theme};
+use cli_weather::tint::{self, Tint};
+use cli_weather::tour::{self, Input, Step, Tour};
+use cli_weather::trip::{self, Waypoint};
+use cli_weather::units::{self, Preferences, SpeedUnit};
+use cli_weather::usage::Tracker;
+use cli_weather::uv;
+use cli_weather::what3words;
+use cli_weather::{api, astro};
+use cli_weather::{coords, countries, credentials};
 
-#[derive(Serialize, Deserialize, Debug)]
-struct WeatherResponse {
-    weather: Vec<Weather>,
-    main: Main,
-    wind: Wind,
-    name: String,
+const W3W_API_NAME_KEY: &str = "W3W_API_KEY";
+/// Longest `watch` waits between refreshes while rate limited.
+const MAX_BACKOFF_SECS: u64 = 3600;
+/// How far ahead `--advice` looks for rain.
+const ADVICE_HOURS: u32 = 12;
+
+/// Current weather in your terminal. Runs interactively without a subcommand.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Place to show instead of prompting for one
+    #[command(flatten)]
+    location: LocationArgs,
+    /// Print one line from a template instead of the full report, e.g. '{temp}{icon} {city}'.
+    /// Placeholders: city, description, temp, feels_like, humidity, pressure, wind, wind_dir,
+    /// gust, beaufort, icon, emoji
+    #[arg(long, value_name = "TEMPLATE")]
+    format: Option<String>,
+    /// Show the forecast for a time at the place instead of now, e.g.
+    /// "tomorrow 09:00", "2025-07-01 18:30" or "07:00 utc"
+    #[arg(long, value_name = "WHEN", conflicts_with = "format", value_parser = moment::parse)]
+    at: Option<Moment>,
+    /// Render {icon} with Nerd Font glyphs instead of emoji
+    #[arg(long)]
+    nerd_font: bool,
+    /// Add a geo: link to the report for opening the place in a map app
+    #[arg(long, global = true)]
+    geo_uri: bool,
+    /// Draw a picture of the current sky next to the report
+    #[arg(long, global = true)]
+    art: bool,
+    /// Add tips on what to wear and whether to cycle, from the conditions
+    #[arg(long, global = true)]
+    advice: bool,
+    /// Score the weather out of 100 against your ideal conditions [config: comfort]
+    #[arg(long, global = true)]
+    score: bool,
+    /// Mark what changed since the last reading at the place, e.g. ↑2.1°C
+    #[arg(long, global = true)]
+    diff: bool,
+    /// Unit for wind speeds: m/s, km/h, mph or kn [config: units.wind]
+    #[arg(long, global = true, value_name = "UNIT", hide_possible_values = true, value_parser = Suggest(|s: &str| s.parse::<SpeedUnit>(), speed_units))]
+    wind_unit: Option<SpeedUnit>,
+    /// Language for place names and descriptions, e.g. "de" [config: lang]
+    #[arg(long, global = true)]
+    lang: Option<String>,
+    /// Color theme: default, solarized or monochrome [config: theme.name]
+    #[arg(long, global = true, value_name = "NAME", hide_possible_values = true, value_parser = Suggest(|s: &str| Ok::<_, String>(s.to_string()), themes))]
+    theme: Option<String>,
+    /// API key to use instead of API_KEY, the OS keyring or the config file
+    #[arg(long, global = true, value_name = "KEY")]
+    api_key: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Weather {
-    description: String,
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch once and print any crossed thresholds; suited to cron
+    #[command(mut_group("location", |g| g.required(true)))]
+    Check(CheckArgs),
+    /// Show the weather on an interval, reporting newly crossed thresholds
+    #[command(mut_group("location", |g| g.required(true)))]
+    Watch {
+        #[command(flatten)]
+        check: CheckArgs,
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 600)]
+        interval: u64,
+    },
+    /// Try out the notification rules against past weather
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+    /// Answer whether it will rain soon. Exits 0 if rain is expected, 1 if
+    /// dry, 2 on errors
+    #[command(mut_group("location", |g| g.required(true)))]
+    Rain {
+        #[command(flatten)]
+        location: LocationArgs,
+        /// How many hours ahead to look
+        #[arg(long, default_value_t = 12)]
+        hours: u32,
+    },
+    /// Chart the daily highs, lows and rain of the past week; needs a One
+    /// Call subscription
+    #[command(mut_group("location", |g| g.required(true)))]
+    Recent {
+        #[command(flatten)]
+        location: LocationArgs,
+    },
+    /// Show sunrise, sunset, twilight and the moon phase
+    #[command(mut_group("location", |g| g.required(true)))]
+    Astro {
+        #[command(flatten)]
+        location: LocationArgs,
+        /// Day to show as YYYY-MM-DD; defaults to today at the location
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Show where the sun is in the sky and how long shadows are, e.g. for
+    /// photos, solar panels or a sunny terrace
+    #[command(mut_group("location", |g| g.required(true)))]
+    Sun {
+        #[command(flatten)]
+        location: LocationArgs,
+        /// Time at the place, e.g. "15:00" or "2025-07-01 18:30"; defaults
+        /// to now
+        #[arg(long, value_name = "WHEN", value_parser = moment::parse)]
+        at: Option<Moment>,
+    },
+    /// Write the 5 day forecast as an iCalendar file with an all-day event
+    /// per day, for overlaying on a calendar
+    #[command(mut_group("location", |g| g.required(true)))]
+    Export {
+        #[command(flatten)]
+        location: LocationArgs,
+        /// File to write, or - for stdout
+        #[arg(long, short, default_value = "forecast.ics")]
+        output: PathBuf,
+    },
+    /// Show the UV index now and hour by hour today, with sun protection
+    /// advice. Needs a One Call subscription
+    #[command(mut_group("location", |g| g.required(true)))]
+    Uv {
+        #[command(flatten)]
+        location: LocationArgs,
+    },
+    /// Compare places for a month by their 1991–2020 climate normals, for
+    /// planning a move or a holiday
+    Scout {
+        /// File with a place per line, as "City, Country", "City" or
+        /// coordinates; - for stdin
+        #[arg(long, value_name = "FILE")]
+        cities: PathBuf,
+        /// Month to compare, e.g. "july", "jul" or "7"
+        #[arg(long, value_parser = climate::parse_month)]
+        month: u32,
+    },
+    /// Show the forecast at each stop of a trip on its date, flagging rain
+    /// and extreme heat, e.g. `trip "Lyon@2025-07-01" "Nice, FR@2025-07-03"`
+    Trip {
+        /// Stops in order, as a place with an optional @YYYY-MM-DD; a stop
+        /// without a date is the day after the one before, the first today
+        #[arg(required = true, value_name = "PLACE[@DATE]", value_parser = trip::parse_waypoint)]
+        waypoints: Vec<Waypoint>,
+    },
+    /// Show the weather at each event in a calendar file over the next
+    /// days, from its location and start time
+    Agenda {
+        /// An .ics file, e.g. exported from a calendar app; - for stdin
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+        /// How many days ahead to list events for
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Answer weather queries over local HTTP as JSON, e.g.
+    /// /weather?city=Paris&country=FR, sharing one API key and cache;
+    /// Prometheus metrics are at /metrics. With [[serve.clients]] in the
+    /// config, each client needs its own token
+    Serve {
+        /// Address to listen on; keep it on localhost unless you mean to share
+        #[arg(long, default_value = "127.0.0.1:8674")]
+        listen: String,
+    },
+    /// Save the weather, forecast and alerts for some places to one file,
+    /// or show a saved one offline
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Answer one JSON request on stdin with a JSON response on stdout, for
+    /// serverless functions and chatbots, e.g.
+    /// `echo '{"location":{"city":"Paris","country":"FR"},"layers":["forecast"]}' | cli_weather lambda`
+    Lambda,
+    /// Save an API key to the OS keyring, so it needs no .env file
+    Login,
+    /// Remove the API key from the OS keyring
+    Logout,
+    /// Read or change settings in the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print a completion script for a shell, e.g.
+    /// `cli_weather completions bash > ~/.local/share/bash-completion/completions/cli_weather`
+    Completions { shell: Shell },
+    /// Notable weather recorded at the places looked up: the year's first
+    /// frost and first day over 30°C, and storms with alerts
+    Events {
+        #[command(subcommand)]
+        action: EventsAction,
+    },
+    /// Show how many API calls were made per day [config: usage.daily_limit]
+    Usage {
+        /// How many days back to show
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Main {
-    temp: f64,
-    pressure: f64,
-    humidity: f64,
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Open the config file in $VISUAL or $EDITOR, checking it before saving
+    Edit,
+    /// Print a setting, e.g. `units.wind`, or every setting when none is given
+    Get {
+        #[arg(hide_possible_values = true, value_parser = Suggest(|s: &str| Ok::<_, String>(s.to_string()), config_keys))]
+        key: Option<String>,
+    },
+    /// Change a setting, e.g. `config set units.wind km/h`
+    Set {
+        #[arg(hide_possible_values = true, value_parser = Suggest(|s: &str| Ok::<_, String>(s.to_string()), config_keys))]
+        key: String,
+        value: String,
+    },
+    /// Print where the config file is
+    Path,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Wind {
-    speed: f64,
+#[derive(Subcommand)]
+enum RulesAction {
+    /// Replay the past days through the rules and show how often each would
+    /// have fired, e.g. `rules test --history 90d --min-temp -2 Oslo`;
+    /// needs a One Call subscription
+    #[command(mut_group("location", |g| g.required(true)))]
+    Test {
+        #[command(flatten)]
+        location: LocationArgs,
+        /// How far back to replay, in days, e.g. 90d; one request per day
+        #[arg(long, default_value = "30d", value_parser = parse_history)]
+        history: u32,
+        #[command(flatten)]
+        limits: ThresholdArgs,
+    },
 }
 
-fn get_weather_info(
-    city: &str,
-    country_code: &str,
-    api_key: &str,
-) -> Result<WeatherResponse, reqwest::Error> {
-    let url = format!(
-        "http://api.openweathermap.org/data/2.5/weather?q={},{}&appid={}&units=metric",
-        city, country_code, api_key
-    );
-    let response = reqwest::blocking::get(&url)?.json::<WeatherResponse>()?;
-    Ok(response)
+#[derive(Subcommand)]
+enum EventsAction {
+    /// List the recorded events, oldest first, e.g. `events list --city Oslo --year 2025`
+    List {
+        /// Only events at this city
+        #[arg(long)]
+        city: Option<String>,
+        /// Only events in this year, by the date at the place
+        #[arg(long)]
+        year: Option<i32>,
+    },
 }
 
-fn print_weather_info(weather_info: WeatherResponse) {
-    let description = weather_info.weather[0].description.clone();
+#[derive(Subcommand)]
+enum SnapshotAction {
+    /// Fetch the reports now and write them to FILE, e.g.
+    /// `snapshot save trip.wsnap "Lisbon, PT" "Porto, PT"`
+    Save {
+        file: PathBuf,
+        /// Places as a city with an optional country, or coordinates
+        #[arg(required = true, value_name = "PLACE", value_parser = parse_place)]
+        places: Vec<api::Location>,
+    },
+    /// Show the reports saved in FILE; needs no API key or network
+    View { file: PathBuf },
+}
 
-    println!(
-        "\n\n{}\n",
-        weather_info.name.to_uppercase().bright_white().bold()
-    );
-    println!(
-        "> Weather: {}",
-        get_description_emoji_and_color(description)
-    );
-    println!("> Temperature: {}", get_temp_emoji(weather_info.main.temp));
-    println!(
-        "> Pressure: {} hPa",
-        weather_info.main.pressure.to_string().green().bold()
-    );
-    println!(
-        "> Humidity: {}%",
-        weather_info.main.humidity.to_string().green().bold()
-    );
-    println!(
-        "> Wind speed: {} m/s",
-        weather_info.wind.speed.to_string().green().bold()
-    );
-    println!("\n");
-}
-
-fn get_temp_emoji(temp: f64) -> ColoredString {
-    if temp < 0.0 {
-        return format!("{}°C 🫢", temp).cyan();
-    } else if temp < 10.0 {
-        return format!("{}°C 🥶", temp).blue();
-    } else if temp < 20.0 {
-        return format!("{}°C 😊", temp).bright_green();
-    } else if temp < 30.0 {
-        return format!("{}°C 🌞", temp).yellow();
+/// A city and country, coordinates or a what3words address. Subcommands
+/// require one.
+#[derive(Args)]
+#[group(id = "location", multiple = true)]
+struct LocationArgs {
+    /// City name, e.g. "Paris"
+    #[arg(long, requires = "country")]
+    city: Option<String>,
+    /// Country as a code or name, e.g. "FR" or "France"
+    #[arg(long, requires = "city", hide_possible_values = true, value_parser = Suggest(country_code, country_codes))]
+    country: Option<String>,
+    /// Coordinates in decimal degrees, degrees-minutes-seconds, a geo: URI or
+    /// a full plus code, e.g. "48.8566,2.3522", "48°51'24\"N 2°21'08\"E",
+    /// "geo:48.85,2.35" or "8FW4V75V+8Q"
+    #[arg(long, value_name = "LAT,LON", conflicts_with_all = ["city", "country"], value_parser = coords::parse, allow_hyphen_values = true)]
+    coords: Option<api::Coord>,
+    /// A what3words address, e.g. "///filled.count.soap"; needs W3W_API_KEY
+    #[arg(long, value_name = "WORDS", conflicts_with_all = ["city", "country", "coords"], value_parser = what3words::parse_address)]
+    what3words: Option<String>,
+}
+
+impl LocationArgs {
+    /// None when no location was given. what3words addresses are looked up
+    /// here, so this can fail.
+    fn location(&self, config: &Config) -> Result<Option<api::Location>, error::Error> {
+        match (&self.coords, &self.what3words, &self.city, &self.country) {
+            (Some(coord), _, _, _) => Ok(Some(api::Location::Coordinates(*coord))),
+            (None, Some(words), _, _) => {
+                let coord = what3words_client(config)?
+                    .convert(words)
+                    .context(format!(
+                        "converting the address with {}",
+                        what3words::PROVIDER
+                    ))
+                    .with_context(|| format!("finding ///{}", words))?;
+                Ok(Some(api::Location::Coordinates(coord)))
+            }
+            (None, None, Some(city), Some(country_code)) => Ok(Some(api::Location::Place {
+                city: city.clone(),
+                country_code: country_code.clone(),
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// For subcommands, where clap has already checked a location was given.
+    /// Exits with `code` if it cannot be resolved.
+    fn required(&self, code: i32, config: &Config) -> api::Location {
+        match self.location(config) {
+            Ok(location) => location.expect("location is a required argument group"),
+            Err(e) => {
+                print_error(&e);
+                process::exit(code);
+            }
+        }
+    }
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    #[command(flatten)]
+    location: LocationArgs,
+    /// Follow every place in the config instead of one, each sending its
+    /// alerts to its own channels [config: monitor.places]
+    #[arg(long, group = "location", conflicts_with_all = ["city", "country", "coords", "what3words"])]
+    group: bool,
+    /// Send a desktop notification for each crossed threshold
+    #[arg(long)]
+    notify: bool,
+    /// Post each crossed threshold to the Matrix room in the config [config: matrix.*]
+    #[arg(long)]
+    matrix: bool,
+    /// Push each crossed threshold to the Gotify server in the config [config: gotify.*]
+    #[arg(long)]
+    gotify: bool,
+    /// Publish each crossed threshold to the ntfy topic in the config [config: ntfy.*]
+    #[arg(long)]
+    ntfy: bool,
+    #[command(flatten)]
+    limits: ThresholdArgs,
+}
+
+/// Overrides for the configured thresholds.
+#[derive(Args)]
+struct ThresholdArgs {
+    /// Temperature in °C below which to report [config: notify.min_temp]
+    #[arg(long, allow_hyphen_values = true)]
+    min_temp: Option<f64>,
+    /// Wind speed in m/s above which to report [config: notify.max_wind]
+    #[arg(long)]
+    max_wind: Option<f64>,
+    /// Hours ahead to look for rain, 0 to skip [config: notify.rain_within_hours]
+    #[arg(long)]
+    rain_within: Option<u32>,
+    /// Report active weather alerts; needs a One Call subscription [config: notify.alerts]
+    #[arg(long)]
+    alerts: bool,
+}
+
+impl ThresholdArgs {
+    fn thresholds(&self, configured: &Thresholds) -> Thresholds {
+        Thresholds {
+            min_temp: self.min_temp.unwrap_or(configured.min_temp),
+            max_wind: self.max_wind.unwrap_or(configured.max_wind),
+            rain_within_hours: self.rain_within.unwrap_or(configured.rain_within_hours),
+            alerts: self.alerts || configured.alerts,
+            rules: configured.rules.clone(),
+        }
+    }
+}
+
+/// The longest `--history`, so a typo cannot spend a day's API calls.
+const MAX_HISTORY_DAYS: u32 = 366;
+
+fn parse_history(input: &str) -> Result<u32, String> {
+    let days = input
+        .strip_suffix('d')
+        .unwrap_or(input)
+        .parse::<u32>()
+        .map_err(|_| format!("'{}' is not a number of days; use e.g. 90d", input))?;
+    match days {
+        1..=MAX_HISTORY_DAYS => Ok(days),
+        _ => Err(format!("use between 1 and {} days", MAX_HISTORY_DAYS)),
+    }
+}
+
+fn country_code(input: &str) -> Result<String, countries::UnknownCountry> {
+    countries::resolve(input).map(String::from)
+}
+
+/// Parses with the first parser, but offers the listed values to shell
+/// completion, for arguments that also take aliases and other spellings.
+#[derive(Clone)]
+struct Suggest<P>(P, fn() -> Vec<PossibleValue>);
+
+impl<P: TypedValueParser> TypedValueParser for Suggest<P> {
+    type Value = P::Value;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<P::Value, clap::Error> {
+        self.0.parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(self.1().into_iter()))
+    }
+}
+
+fn speed_units() -> Vec<PossibleValue> {
+    SpeedUnit::ALL
+        .iter()
+        .map(|unit| PossibleValue::new(unit.symbol()))
+        .collect()
+}
+
+fn themes() -> Vec<PossibleValue> {
+    Builtin::ALL
+        .iter()
+        .map(|builtin| PossibleValue::new(builtin.name()))
+        .collect()
+}
+
+fn config_keys() -> Vec<PossibleValue> {
+    Config::default()
+        .entries()
+        .into_iter()
+        .map(|(key, _)| PossibleValue::new(key))
+        .collect()
+}
+
+fn country_codes() -> Vec<PossibleValue> {
+    countries::COUNTRIES
+        .iter()
+        .map(|(code, name)| PossibleValue::new(*code).help(*name))
+        .collect()
+}
+
+/// None when the user quits with Ctrl-C or Ctrl-D.
+fn get_city_name(prompt: &mut Prompt) -> Option<String> {
+    loop {
+        let city = prompt.read(&paint(Role::Prompt, "Enter city name: ").to_string())?;
+        if !city.is_empty() {
+            return Some(city);
+        }
+    }
+}
+
+/// What was typed and the code it resolved to, or None when the user quits.
+fn get_country_code(prompt: &mut Prompt) -> Option<(String, String)> {
+    loop {
+        let country =
+            prompt.read(&paint(Role::Prompt, "Enter country code or name: ").to_string())?;
+        if country.is_empty() {
+            continue;
+        }
+        match countries::resolve(&country) {
+            Ok(code) => return Some((country, code.to_string())),
+            Err(e) => println!("{}", paint(Role::Error, e.to_string())),
+        }
+    }
+}
+
+/// Asks which place was meant when the geocoder finds several, e.g.
+/// Frankfurt am Main and Frankfurt (Oder). None when the user cancels.
+fn pick_place(
+    mut candidates: Vec<api::GeoCandidate>,
+    lang: Option<&str>,
+    prompt: &mut Prompt,
+) -> Option<api::GeoCandidate> {
+    if candidates.len() == 1 {
+        return Some(candidates.remove(0));
+    }
+
+    println!("{}", paint(Role::Prompt, "Several places match:"));
+    for (i, candidate) in candidates.iter().enumerate() {
+        println!(
+            "  {}) {}",
+            i + 1,
+            display::describe_candidate(candidate, lang)
+        );
+    }
+
+    loop {
+        let question = paint(Role::Prompt, format!("Pick one [1-{}]: ", candidates.len()));
+        let choice = prompt.read(&question.to_string())?;
+
+        match choice.parse::<usize>() {
+            Ok(n) if (1..=candidates.len()).contains(&n) => return Some(candidates.remove(n - 1)),
+            _ => continue,
+        }
+    }
+}
+
+fn is_repeat(prompt: &mut Prompt) -> bool {
+    prompt
+        .read("Do you want to get weather info for another city? (y/n) ")
+        .is_some_and(|choice| choice == "y")
+}
+
+fn get_api_key(flag: Option<&str>, config: &Config) -> Option<String> {
+    let api_key = credentials::lookup(flag, config.api_key.as_deref());
+
+    if api_key.is_none() {
+        let err_message = paint(
+            Role::Error,
+            format!(
+                "Error: no API key found. Run `cli_weather login`, or set {} in the environment or a .env file. Visit openweathermap.org to get an API key.",
+                credentials::ENV_VAR,
+            ),
+        );
+        println!("{}", err_message);
+    }
+    api_key
+}
+
+fn keyring_error(e: keyring::Error, doing: &str) -> error::Error {
+    error::Error::new(e).context(doing).hint(format!(
+        "on Linux the keyring needs a Secret Service such as GNOME Keyring or KeePassXC; set {} instead otherwise",
+        credentials::ENV_VAR
+    ))
+}
+
+fn login() {
+    let mut api_key = String::new();
+    while api_key.is_empty() {
+        println!(
+            "{}",
+            paint(
+                Role::Prompt,
+                format!("Enter your {} API key: ", api::PROVIDER)
+            )
+        );
+        if io::stdin().read_line(&mut api_key).unwrap_or_default() == 0 {
+            process::exit(1);
+        }
+        api_key = api_key.trim().to_string();
+    }
+
+    match credentials::store(&api_key) {
+        Ok(()) => println!("Saved the API key to the OS keyring."),
+        Err(e) => {
+            print_error(&keyring_error(e, "saving the API key to the OS keyring"));
+            process::exit(1);
+        }
+    }
+}
+
+/// Offered after `login`, when there is someone at the terminal to answer.
+fn take_tour(options: &ReportOptions, client: &api::Client, config: &Config) {
+    if !io::stdin().is_terminal() {
+        return;
+    }
+    let mut prompt = match Prompt::new(None) {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            print_error(&error::Error::new(e).context("setting up the prompt"));
+            return;
+        }
+    };
+
+    let mut tour = Tour::new(units::preferences());
+    while tour.step != Step::Done {
+        if let Some(question) = tour.question() {
+            let Some(answer) = prompt.read(&paint(Role::Prompt, question).to_string()) else {
+                return;
+            };
+            tour.advance(Input::Answer(&answer));
+            continue;
+        }
+        let ran = match &tour.step {
+            Step::Weather(place) => tour_weather(place, options, client),
+            Step::Rain(place) => tour_rain(place, client),
+            Step::Write(units) => tour_config(*units, config),
+            _ => true,
+        };
+        tour.advance(Input::Ran(ran));
+    }
+}
+
+/// The flags that name `location`, for showing the command a step ran.
+fn location_flags(location: &api::Location) -> String {
+    match location {
+        api::Location::Place { city, country_code } if country_code.is_empty() => {
+            format!("--city \"{}\"", city)
+        }
+        api::Location::Place { city, country_code } => {
+            format!("--city \"{}\" --country {}", city, country_code)
+        }
+        api::Location::Coordinates(coord) => format!("--coords {},{}", coord.lat, coord.lon),
+    }
+}
+
+fn tour_weather(place: &str, options: &ReportOptions, client: &api::Client) -> bool {
+    let location = match parse_place(place) {
+        Ok(location) => location,
+        Err(e) => {
+            eprintln!("{}", paint(Role::Error, format!("Error: {}", e)));
+            return false;
+        }
+    };
+    match current_weather(&location, client) {
+        Ok(weather) => {
+            display::print_weather_info(&weather, options);
+            println!(
+                "{}\n",
+                paint(
+                    Role::Muted,
+                    format!(
+                        "That is `cli_weather {}`. Add --advice for what to wear, or --format for a status bar line.",
+                        location_flags(&location)
+                    )
+                )
+            );
+            true
+        }
+        Err(e) => {
+            print_error(&e);
+            false
+        }
+    }
+}
+
+fn tour_rain(place: &str, client: &api::Client) -> bool {
+    let Ok(location) = parse_place(place) else {
+        return false;
+    };
+    match client.forecast(&location) {
+        Ok(forecast) => {
+            let rain = forecast::first_rain(&forecast, now(), tour::RAIN_HOURS);
+            display::print_rain_answer(&forecast.city, rain.as_ref(), tour::RAIN_HOURS);
+            println!(
+                "{}\n",
+                paint(
+                    Role::Muted,
+                    format!(
+                        "That is `cli_weather rain {} --hours {}`, which exits 0 when rain is expected. \
+                         --at \"tomorrow 09:00\" shows the forecast for a time instead of now.",
+                        location_flags(&location),
+                        tour::RAIN_HOURS
+                    )
+                )
+            );
+            true
+        }
+        Err(e) => {
+            print_error(
+                &error::Error::from(e)
+                    .context(format!("fetching the forecast from {}", api::PROVIDER)),
+            );
+            false
+        }
+    }
+}
+
+fn tour_config(preferences: Preferences, config: &Config) -> bool {
+    let units = config::UnitSettings::from(preferences);
+    let updated = config
+        .set("units.temperature", &units.temperature)
+        .and_then(|config| config.set("units.wind", &units.wind))
+        .and_then(|config| config.set("units.clock", &units.clock))
+        .map_err(error::Error::new)
+        .and_then(|config| config::save(&config));
+    match updated {
+        Ok(path) => {
+            println!(
+                "Saved to {}; the units apply from the next run. `cli_weather config edit` changes the rest.",
+                path.display()
+            );
+            true
+        }
+        Err(e) => {
+            print_error(&e.context("saving the units"));
+            false
+        }
+    }
+}
+
+fn logout() {
+    match credentials::remove() {
+        Ok(true) => println!("Removed the API key from the OS keyring."),
+        Ok(false) => println!("No API key was stored in the OS keyring."),
+        Err(e) => {
+            print_error(&keyring_error(
+                e,
+                "removing the API key from the OS keyring",
+            ));
+            process::exit(1);
+        }
+    }
+}
+
+fn what3words_client(config: &Config) -> Result<what3words::Client, error::Error> {
+    let key = match std::env::var(W3W_API_NAME_KEY) {
+        Ok(key) if !key.is_empty() => key,
+        _ => {
+            return Err(
+                error::Error::new(format!("{} is not set", W3W_API_NAME_KEY))
+                    .hint("get a key at what3words.com/select-plan and add it to your .env file"),
+            )
+        }
+    };
+    // The [http] headers are meant for the weather provider.
+    let user_agent = config.http.user_agent.as_deref().unwrap_or(api::USER_AGENT);
+    let http = api::http_client(user_agent, &BTreeMap::new()).map_err(|e| {
+        error::Error::new(e)
+            .context("setting up HTTP requests")
+            .hint("check the [http] section of the config file")
+    })?;
+    Ok(what3words::Client::new(&key).with_http(http))
+}
+
+fn print_error(e: &error::Error) {
+    eprintln!("{}", paint(Role::Error, e.render()));
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn fetch_triggers(
+    location: &api::Location,
+    thresholds: &Thresholds,
+    client: &api::Client,
+) -> Result<(api::WeatherResponse, Vec<Trigger>), error::Error> {
+    let checking = || format!("checking thresholds for {}", location);
+
+    let weather = client
+        .latest_weather(location)
+        .context(format!("fetching current weather from {}", api::PROVIDER))
+        .with_context(checking)?;
+
+    let forecast = if thresholds.needs_forecast() {
+        let forecast = client
+            .forecast(location)
+            .context(format!("fetching the forecast from {}", api::PROVIDER))
+            .with_context(checking)?;
+        Some(forecast)
+    } else {
+        None
+    };
+
+    let alerts = if thresholds.alerts {
+        match client.alerts(&weather.coord) {
+            Err(e @ api::Error::Unsupported(_)) => {
+                skipped(&e);
+                Vec::new()
+            }
+            alerts => alerts
+                .context(format!(
+                    "fetching weather alerts from {} One Call",
+                    api::PROVIDER
+                ))
+                .with_context(checking)?,
+        }
+    } else {
+        Vec::new()
+    };
+
+    record_events(&weather, &alerts);
+    let triggers = notify::evaluate(&weather, forecast.as_ref(), &alerts, thresholds, now());
+    Ok((weather, triggers))
+}
+
+/// Where crossed thresholds go besides stdout.
+struct Sinks {
+    desktop: bool,
+    matrix: Option<matrix::Room>,
+    push: Vec<push::Push>,
+}
+
+/// A token from `var`, or failing that the config file.
+fn token(var: &str, configured: &Option<String>) -> Option<String> {
+    std::env::var(var)
+        .ok()
+        .or_else(|| configured.clone())
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// The sinks the flags ask for, each checked to have what it needs. A
+/// `place` that names channels of its own uses those instead of the
+/// Matrix, Gotify and ntfy ones the flags would.
+fn sinks(
+    args: &CheckArgs,
+    config: &Config,
+    place: Option<&MonitorPlace>,
+) -> Result<Sinks, error::Error> {
+    let mut sinks = Sinks {
+        desktop: args.notify,
+        matrix: None,
+        push: Vec::new(),
+    };
+    let own = place.filter(|place| place.has_channels());
+    let (matrix, gotify, ntfy) = match own {
+        Some(place) => (
+            place.matrix_room.is_some(),
+            place.gotify_token.is_some(),
+            place.ntfy_topic.is_some(),
+        ),
+        None => (args.matrix, args.gotify, args.ntfy),
+    };
+
+    if matrix {
+        let settings = &config.matrix;
+        let (Some(homeserver), Some(room), Some(token)) = (
+            &settings.homeserver,
+            own.and_then(|place| place.matrix_room.as_ref())
+                .or(settings.room.as_ref()),
+            token(matrix::TOKEN_VAR, &settings.access_token),
+        ) else {
+            return Err(
+                error::Error::new("--matrix needs a homeserver, a room and an access token").hint(
+                    format!(
+                        "run `cli_weather config set matrix.homeserver https://matrix.org`, the same for matrix.room, and set {}",
+                        matrix::TOKEN_VAR
+                    ),
+                ),
+            );
+        };
+        let room =
+            matrix::Room::new(homeserver, room, &token).context("reading the [matrix] section")?;
+        sinks.matrix = Some(room);
+    }
+
+    if gotify {
+        let settings = &config.gotify;
+        let (Some(server), Some(token)) = (
+            &settings.server,
+            own.and_then(|place| place.gotify_token.clone())
+                .or_else(|| token(push::GOTIFY_TOKEN_VAR, &settings.token)),
+        ) else {
+            return Err(
+                error::Error::new("--gotify needs a server and an application token").hint(
+                    format!(
+                        "run `cli_weather config set gotify.server https://gotify.example.com` and set {}",
+                        push::GOTIFY_TOKEN_VAR
+                    ),
+                ),
+            );
+        };
+        let gotify = push::Push::gotify(server, &token).context("reading the [gotify] section")?;
+        sinks.push.push(gotify);
+    }
+
+    if ntfy {
+        let settings = &config.ntfy;
+        let Some(topic) = own
+            .and_then(|place| place.ntfy_topic.as_ref())
+            .or(settings.topic.as_ref())
+        else {
+            return Err(error::Error::new("--ntfy needs a topic")
+                .hint("run `cli_weather config set ntfy.topic <topic>`"));
+        };
+        let server = settings.server.as_deref().unwrap_or(push::NTFY_SERVER);
+        let ntfy = push::Push::ntfy(
+            server,
+            topic,
+            token(push::NTFY_TOKEN_VAR, &settings.token).as_deref(),
+        )
+        .context("reading the [ntfy] section")?;
+        sinks.push.push(ntfy);
+    }
+
+    Ok(sinks)
+}
+
+/// `named` also says which place on stdout, for when several are followed.
+fn report(city: &str, trigger: &Trigger, sinks: &Sinks, named: bool) {
+    let mark = paint(Role::Error, "!").bold();
+    if named {
+        println!("{} {}: {}", mark, city, trigger);
     } else {
-        return format!("{}°C 🔥", temp).red();
+        println!("{} {}", mark, trigger);
+    }
+
+    if sinks.desktop {
+        if let Err(e) = notify::send(city, trigger) {
+            eprintln!("Error: could not send notification: {}", e);
+        }
+    }
+    if let Some(room) = &sinks.matrix {
+        if let Err(e) = room.send(&matrix::Message::new(city, trigger)) {
+            eprintln!("Error: could not post to Matrix: {}", e);
+        }
+    }
+    for push in &sinks.push {
+        if let Err(e) = push.send(city, trigger) {
+            eprintln!("Error: could not push to {}: {}", push.service().name(), e);
+        }
     }
 }
 
-fn get_description_emoji_and_color(description: String) -> ColoredString {
-    match description.as_str() {
-        "clear sky" => format!("{} 🌄", description).bright_yellow(),
-        "few clouds" => format!("{} 🌤️", description).bright_blue(),
-        "overcast clouds" => format!("{} 🌤️", description).bright_blue(),
-        "scattered clouds" => format!("{} 🌥️", description).bright_blue(),
-        "broken clouds" => format!("{} 🌫️", description).bright_blue(),
-        "shower rain" => format!("{} 🌧️", description).bright_cyan(),
-        "light rain" => format!("{} 🌧️", description).bright_cyan(),
-        "light snow" => format!("{} 🌨️", description).bright_cyan(),
-        "rain" => format!("{} 🌧️", description).bright_cyan(),
-        "thunderstorm" => format!("{} ⛈️", description).bright_cyan(),
-        "snow" => format!("{} 🌨️", description).bright_cyan(),
-        "mist" => format!("{} 🌫️", description).dimmed(),
-        _ => description.normal(),
+/// A place `check` or `watch` follows, and where its alerts go.
+struct Followed {
+    location: api::Location,
+    name: Option<String>,
+    sinks: Sinks,
+}
+
+impl Followed {
+    /// How alerts name the place, e.g. "Grandma (Lyon)".
+    fn label(&self, weather: &api::WeatherResponse) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, weather.name),
+            None => weather.name.clone(),
+        }
     }
 }
 
-fn get_city_name() -> String {
-    let mut city_name = String::new();
-    while city_name.is_empty() {
-        let q = format!("{}", String::from("Enter city name: ").white());
-        println!("{}", q);
-        io::stdin().read_line(&mut city_name).unwrap();
-        city_name = city_name.trim().to_string();
+/// The location given, or with `--group` every place in the config.
+fn followed(args: &CheckArgs, config: &Config) -> Result<Vec<Followed>, error::Error> {
+    if !args.group {
+        return Ok(vec![Followed {
+            location: args.location.required(1, config),
+            name: None,
+            sinks: sinks(args, config, None)?,
+        }]);
     }
+    if config.monitor.places.is_empty() {
+        return Err(error::Error::new("--group needs places to follow")
+            .hint("add [[monitor.places]] entries with a place each to the config file"));
+    }
+    config
+        .monitor
+        .places
+        .iter()
+        .map(|place| {
+            let reading = || format!("reading monitor.places entry \"{}\"", place.place);
+            let location = parse_place(&place.place)
+                .map_err(error::Error::new)
+                .with_context(reading)?;
+            Ok(Followed {
+                location,
+                name: place.name.clone(),
+                sinks: sinks(args, config, Some(place)).with_context(reading)?,
+            })
+        })
+        .collect()
+}
 
-    city_name
+/// Exits with 1 if any place could not be checked, after trying them all.
+fn check(places: &[Followed], thresholds: &Thresholds, client: &api::Client) {
+    let mut failed = false;
+    for place in places {
+        match fetch_triggers(&place.location, thresholds, client) {
+            Ok((weather, triggers)) => {
+                for trigger in &triggers {
+                    report(
+                        &place.label(&weather),
+                        trigger,
+                        &place.sinks,
+                        places.len() > 1,
+                    );
+                }
+            }
+            Err(e) => {
+                print_error(&e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
 }
 
-fn get_country_code() -> String {
-    let mut country_code = String::new();
+/// Starts each of `commands` and warns from the background if it fails,
+/// so a slow one does not hold up the next refresh.
+fn run_hooks(
+    commands: &[String],
+    weather: &api::WeatherResponse,
+    before: Option<Category>,
+    now: Category,
+) {
+    for command in commands {
+        match hooks::run(command, weather, before, now) {
+            Ok(mut child) => {
+                let command = command.clone();
+                thread::spawn(move || match child.wait() {
+                    Ok(status) if !status.success() => {
+                        eprintln!("Warning: hook `{}` exited with {}", command, status)
+                    }
+                    Err(e) => eprintln!("Warning: hook `{}` failed: {}", command, e),
+                    Ok(_) => {}
+                });
+            }
+            Err(e) => eprintln!("Warning: could not run hook `{}`: {}", command, e),
+        }
+    }
+}
+
+/// What `watch` remembers about a place between refreshes.
+struct Watched<'a> {
+    place: &'a Followed,
+    /// Keys of the triggers crossed at the last refresh, which are not
+    /// reported again until they clear.
+    active: HashSet<String>,
+    category: Option<Category>,
+    since: Option<Reading>,
+}
+
+/// Refreshes every place in turn, then waits at least `interval`, doubling
+/// the wait while the API keeps answering 429.
+fn watch(
+    places: &[Followed],
+    thresholds: &Thresholds,
+    interval: u64,
+    config: &Config,
+    options: &ReportOptions,
+    client: &api::Client,
+) {
+    let mut watched: Vec<Watched> = places
+        .iter()
+        .map(|place| Watched {
+            place,
+            active: HashSet::new(),
+            category: None,
+            since: None,
+        })
+        .collect();
+    let mut wait = interval;
+    let mut tidied = None;
+
+    loop {
+        tidy(&config.retention, &mut tidied);
+        let mut limited = None;
+        for watched in &mut watched {
+            let mut span = telemetry::span("watch");
+            span.attr("location", watched.place.location.to_string());
+
+            match fetch_triggers(&watched.place.location, thresholds, client) {
+                Ok((weather, triggers)) => {
+                    // The first refresh goes by what was cached before it.
+                    let since = watched.since.or_else(|| {
+                        options
+                            .diff
+                            .then(|| earlier(&watched.place.location, client))
+                            .flatten()
+                    });
+                    let options = ReportOptions { since, ..*options };
+                    display::print_weather_info(&weather, &options);
+                    watched.since = Some(Reading::of(&weather, now()));
+                    if let Some(now) =
+                        Category::of(&weather).filter(|now| watched.category != Some(*now))
+                    {
+                        run_hooks(&config.hooks.on_change, &weather, watched.category, now);
+                        watched.category = Some(now);
+                    }
 
-    while country_code.is_empty() {
-        let q = format!("{}", String::from("Enter country code: ").white());
-        println!("{}", q);
-        io::stdin().read_line(&mut country_code).unwrap();
-        country_code = country_code.trim().to_string();
+                    let keys: HashSet<String> = triggers.iter().map(Trigger::key).collect();
+                    let label = watched.place.label(&weather);
+                    for trigger in triggers
+                        .iter()
+                        .filter(|t| !watched.active.contains(&t.key()))
+                    {
+                        report(&label, trigger, &watched.place.sinks, places.len() > 1);
+                    }
+                    watched.active = keys;
+                }
+                Err(e) => {
+                    span.fail(&e);
+                    print_error(&e);
+                    if let Some(api::Error::RateLimited { retry_after, .. }) = e.source_as() {
+                        limited = limited.max(Some(retry_after.unwrap_or_default()));
+                    }
+                }
+            }
+        }
+
+        wait = match limited {
+            Some(retry_after) => {
+                let wait = wait
+                    .saturating_mul(2)
+                    .max(retry_after)
+                    .min(MAX_BACKOFF_SECS);
+                eprintln!("Backing off; next try in {} seconds", wait);
+                wait
+            }
+            None => interval,
+        };
+        thread::sleep(Duration::from_secs(wait));
     }
+}
 
-    country_code
+/// The reading at `location` before the one last fetched, for `--diff`.
+/// It has to be read after the weather is.
+fn earlier(location: &api::Location, client: &api::Client) -> Option<Reading> {
+    let (weather, at) = Cache::open()?.previous(&client.weather_request(location))?;
+    Some(Reading::of(&weather, at))
 }
 
-fn is_repeat() -> bool {
-    println!("Do you want to get weather info for another city? (y/n)");
-    let mut choice = String::new();
-    io::stdin().read_line(&mut choice).unwrap();
-    choice = choice.trim().to_string();
+/// Clears out cache entries older than `retention.cache_days`, for commands
+/// that run for days: at most once a day, `last` being when it last did.
+fn tidy(retention: &RetentionSettings, last: &mut Option<i64>) {
+    let now = now();
+    if retention.cache_days == 0 || last.is_some_and(|last| now - last < 24 * 3600) {
+        return;
+    }
+    *last = Some(now);
+    let Some(cache) = Cache::open() else {
+        return;
+    };
+    match cache.prune(retention.cache_days, SystemTime::now()) {
+        Ok(0) => {}
+        Ok(removed) => eprintln!(
+            "Note: removed the cached weather of {} place(s) not fetched in {} days",
+            removed, retention.cache_days
+        ),
+        Err(e) => eprintln!("Warning: could not clear out the cache: {}", e),
+    }
+}
 
-    return choice.eq("y");
+/// The current weather, which the client answers from the cache while it
+/// is fresh, and noting any events in it.
+fn current_weather(
+    location: &api::Location,
+    client: &api::Client,
+) -> Result<api::WeatherResponse, error::Error> {
+    let weather = client
+        .weather(location)
+        .context(format!("fetching current weather from {}", api::PROVIDER))
+        .with_context(|| format!("showing the weather for {}", location))?;
+    record_events(&weather, &[]);
+    Ok(weather)
 }
 
-fn get_api_key() -> Result<String, VarError> {
-    dotenv::dotenv().ok();
+fn status_line(
+    location: &api::Location,
+    template: &Template,
+    icons: IconSet,
+    client: &api::Client,
+) {
+    match current_weather(location, client) {
+        Ok(weather) => println!("{}", display::render_status_line(template, &weather, icons)),
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    }
+}
+
+fn show_astro(
+    location: &LocationArgs,
+    date: Option<NaiveDate>,
+    client: &api::Client,
+    config: &Config,
+) {
+    let weather = match current_weather(&location.required(1, config), client) {
+        Ok(weather) => weather,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+
+    let offset = display::utc_offset(weather.timezone);
+    let date = date.unwrap_or_else(|| Utc::now().with_timezone(&offset).date_naive());
+    let sun = astro::sun_times(weather.coord.lat, weather.coord.lon, date);
+    let moon = astro::moon_phase(sun.noon);
+
+    display::print_astro(&weather.name, date, offset, &sun, &moon);
+}
+
+fn show_sun(location: &LocationArgs, at: Option<Moment>, client: &api::Client, config: &Config) {
+    let weather = match current_weather(&location.required(1, config), client) {
+        Ok(weather) => weather,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+
+    let offset = display::utc_offset(weather.timezone);
+    let now = Utc::now();
+    let time = at.map_or(now, |at| at.resolve(now, offset));
+    let sun = astro::sun_position(weather.coord.lat, weather.coord.lon, time);
+
+    display::print_sun(&weather.name, time, offset, &sun);
+}
+
+/// The UV index for the report, unless turned off in the config. Without
+/// a One Call subscription it is left out with a note; other failures warn
+/// and leave a gap in the report.
+fn uv_summary(
+    weather: &api::WeatherResponse,
+    client: &api::Client,
+    config: &Config,
+) -> Part<uv::Summary> {
+    if !config.uv.in_report {
+        return Part::Off;
+    }
+    match client.uv(&weather.coord) {
+        Ok(forecast) => Part::Shown(uv::Summary::new(&forecast)),
+        Err(e @ api::Error::Unsupported(_)) => {
+            skipped(&e);
+            Part::Off
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: no UV index: {}; set uv.in_report = false in the config to stop asking",
+                e
+            );
+            Part::Failed
+        }
+    }
+}
+
+/// For `energy.in_report`: the greenest time to run the dryer, and whether
+/// the weather helps then. A failed weather forecast only loses the latter.
+fn dryer_window(
+    weather: &api::WeatherResponse,
+    client: &api::Client,
+    config: &Config,
+) -> Part<energy::Window> {
+    if !config.energy.in_report {
+        return Part::Off;
+    }
+    // `config::load` does not check the postcode, so a bad one gets the
+    // national forecast rather than no report.
+    let district = match config.energy.postcode.as_deref().map(energy::district) {
+        Some(Ok(district)) => Some(district),
+        Some(Err(e)) => {
+            eprintln!(
+                "Warning: energy.postcode: {}; using the national carbon intensity forecast",
+                e
+            );
+            None
+        }
+        None => None,
+    };
+    let periods = match energy::Client::new().forecast(district.as_deref(), now()) {
+        Ok(periods) => periods,
+        Err(e) => {
+            eprintln!(
+                "Warning: no carbon intensity forecast from {}: {}; set energy.in_report = false in the config to stop asking",
+                energy::PROVIDER,
+                e
+            );
+            return Part::Failed;
+        }
+    };
+    let Some(window) = energy::greenest(&periods, now(), energy::DRYER_HOURS) else {
+        eprintln!(
+            "Note: the carbon intensity forecast does not cover the next {} hours, so that part is left out",
+            energy::DRYER_HOURS
+        );
+        return Part::Off;
+    };
+    match client.forecast(&api::Location::Coordinates(weather.coord)) {
+        Ok(forecast) => Part::Shown(window.with_weather(&forecast, &weather.coord)),
+        Err(_) => Part::Shown(window),
+    }
+}
+
+/// For sections of a report the provider cannot give: says so and goes on
+/// without them.
+fn skipped(e: &api::Error) {
+    let hint = e
+        .hint()
+        .map(|hint| format!("; {}", hint))
+        .unwrap_or_default();
+    eprintln!("Note: {}, so that part is left out{}", e, hint);
+}
+
+/// For `--advice`, which needs the forecast to know whether to bring an
+/// umbrella; costs a call, so only fetched then.
+fn rain_chance(
+    weather: &api::WeatherResponse,
+    options: &ReportOptions,
+    client: &api::Client,
+) -> Part<f64> {
+    if !options.advice {
+        return Part::Off;
+    }
+    match client.forecast(&api::Location::Coordinates(weather.coord)) {
+        Ok(forecast) => Part::Shown(forecast::rain_chance(&forecast, now(), ADVICE_HOURS)),
+        Err(e @ api::Error::Unsupported(_)) => {
+            skipped(&e);
+            Part::Off
+        }
+        Err(e) => {
+            eprintln!("Warning: no chance of rain for the advice: {}", e);
+            Part::Failed
+        }
+    }
+}
+
+fn show_snapshot(location: &api::Location, at: Moment, client: &api::Client) {
+    let forecast = match client
+        .forecast(location)
+        .context(format!("fetching the forecast from {}", api::PROVIDER))
+        .with_context(|| format!("getting the weather at {}", location))
+    {
+        Ok(forecast) => forecast,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+
+    let time = at.resolve(Utc::now(), display::utc_offset(forecast.city.timezone));
+    match forecast::entry_at(&forecast, time.timestamp()) {
+        Some(entry) => print!("{}", display::render_snapshot(&forecast.city, entry, time)),
+        None => {
+            eprintln!(
+                "{}",
+                paint(
+                    Role::Error,
+                    "Error: that time is outside the 5 day forecast"
+                )
+            );
+            process::exit(1);
+        }
+    }
+}
+
+fn show_uv(location: &LocationArgs, client: &api::Client, config: &Config) {
+    let weather = current_weather(&location.required(1, config), client);
+    let forecast = weather.and_then(|weather| {
+        let forecast = client
+            .uv(&weather.coord)
+            .context(format!(
+                "fetching the UV index from {} One Call",
+                api::PROVIDER
+            ))
+            .with_context(|| format!("showing the UV index for {}", weather.name))?;
+        Ok((weather, forecast))
+    });
+    let (weather, forecast) = match forecast {
+        Ok(found) => found,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+
+    let offset = display::utc_offset(forecast.timezone_offset);
+    let today = Utc::now().with_timezone(&offset).date_naive();
+    display::print_uv(&weather.name, today, &forecast);
+}
+
+/// The weather at `location` and One Call's summary of each of the `count`
+/// days before today there, one request a day. Days that fail are warned
+/// about and left out, unless every one does.
+fn past_summaries(
+    location: &api::Location,
+    count: u32,
+    doing: &str,
+    client: &api::Client,
+) -> Result<(api::WeatherResponse, Vec<(NaiveDate, api::DaySummary)>), error::Error> {
+    let weather = current_weather(location, client)?;
+    let offset = display::utc_offset(weather.timezone);
+    let today = Utc::now().with_timezone(&offset).date_naive();
+    let dates = recent::past_days(today, count);
+
+    let mut fetched = Vec::new();
+    let mut failed = Vec::new();
+    for (date, summary) in dates
+        .iter()
+        .zip(client.day_summaries(&weather.coord, &dates))
+    {
+        match summary {
+            Ok(summary) => fetched.push((*date, summary)),
+            Err(e) => failed.push((*date, e)),
+        }
+    }
+    if fetched.is_empty() && !failed.is_empty() {
+        let (date, e) = failed.swap_remove(0);
+        return Err(e)
+            .context(format!(
+                "fetching the weather of {} from {} One Call",
+                date,
+                api::PROVIDER
+            ))
+            .with_context(|| format!("{} at {}", doing, weather.name));
+    }
+    for (date, e) in &failed {
+        eprintln!(
+            "Warning: left out {}, which could not be fetched: {}",
+            date, e
+        );
+    }
+    Ok((weather, fetched))
+}
+
+fn show_recent(location: &LocationArgs, client: &api::Client, config: &Config) {
+    let location = location.required(1, config);
+    let (weather, summaries) =
+        match past_summaries(&location, recent::DAYS, "showing the past week", client) {
+            Ok(found) => found,
+            Err(e) => {
+                print_error(&e);
+                process::exit(1);
+            }
+        };
+    let days: Vec<DayRecord> = summaries
+        .into_iter()
+        .map(|(date, summary)| DayRecord {
+            date,
+            low: summary.temperature.min,
+            high: summary.temperature.max,
+            rain: summary.precipitation.total,
+        })
+        .collect();
+
+    if let Some(totals) = Totals::of(&days) {
+        display::print_recent(&weather.name, &days, &totals);
+    }
+}
+
+fn test_rules(
+    location: &LocationArgs,
+    history: u32,
+    thresholds: &Thresholds,
+    client: &api::Client,
+    config: &Config,
+) {
+    let location = location.required(1, config);
+    eprintln!(
+        "Note: no history is kept locally, so replaying {} days costs one {} One Call request each",
+        history,
+        api::PROVIDER
+    );
+    let (weather, summaries) = match past_summaries(&location, history, "testing the rules", client)
+    {
+        Ok(found) => found,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+    let days: Vec<PastDay> = summaries
+        .into_iter()
+        .map(|(date, summary)| PastDay {
+            date,
+            low: summary.temperature.min,
+            wind: summary.wind.map(|wind| wind.max.speed),
+            rain: summary.precipitation.total,
+        })
+        .collect();
+
+    // Days left out count as not judged.
+    let fired = notify::backtest(&days, thresholds);
+    display::print_backtest(&weather.name, history as usize, thresholds, &fired);
+}
+
+fn will_it_rain(location: &LocationArgs, hours: u32, client: &api::Client, config: &Config) {
+    let location = location.required(2, config);
+    let forecast = match client
+        .forecast(&location)
+        .context(format!("fetching the forecast from {}", api::PROVIDER))
+        .with_context(|| format!("looking for rain at {}", location))
+    {
+        Ok(forecast) => forecast,
+        Err(e) => {
+            print_error(&e);
+            process::exit(2);
+        }
+    };
+
+    let rain = forecast::first_rain(&forecast, now(), hours);
+    display::print_rain_answer(&forecast.city, rain.as_ref(), hours);
+    process::exit(if rain.is_some() { 0 } else { 1 });
+}
+
+/// Coordinates, or a city with an optional country after the last comma,
+/// e.g. "Lisbon, PT" or "Frankfurt am Main, Germany". Without a country the
+/// geocoder searches everywhere.
+fn parse_place(input: &str) -> Result<api::Location, String> {
+    if let Ok(coord) = coords::parse(input) {
+        return Ok(api::Location::Coordinates(coord));
+    }
+    let (city, country_code) = match input.rsplit_once(',') {
+        Some((city, country)) => (
+            city,
+            countries::resolve(country).map_err(|e| e.to_string())?,
+        ),
+        None => (input, ""),
+    };
+    Ok(api::Location::Place {
+        city: city.trim().to_string(),
+        country_code: country_code.to_string(),
+    })
+}
+
+/// The place's name and coordinates; cities go through the geocoder.
+fn resolve_place(
+    location: &api::Location,
+    client: &api::Client,
+) -> Result<(String, api::Coord), error::Error> {
+    match location {
+        api::Location::Coordinates(coord) => Ok((location.to_string(), *coord)),
+        api::Location::Place { city, country_code } => {
+            let place = client
+                .geocode(city, country_code)
+                .context(format!("looking up places from {}", api::PROVIDER))?
+                .into_iter()
+                .next()
+                .ok_or_else(|| error::Error::new(format!("no place called {} found", location)))?;
+            let name = format!(
+                "{}, {}",
+                place.localized_name(client.lang().as_deref()),
+                place.country
+            );
+            Ok((name, place.coord()))
+        }
+    }
+}
 
-    let env_api_key = std::env::var(API_NAME_KEY);
+fn scout(cities: &Path, month: u32, client: &api::Client) {
+    let contents = if cities == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(cities)
+    };
+    let contents = match contents {
+        Ok(contents) => contents,
+        Err(e) => {
+            let e = error::Error::new(e).context(format!("reading {}", cities.display()));
+            print_error(&e);
+            process::exit(2);
+        }
+    };
 
-    match env_api_key {
-        Ok(key) => return Ok(key),
+    let climate = climate::Client::new();
+    let mut places = Vec::new();
+    let lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for line in lines {
+        let normals = parse_place(line)
+            .map_err(error::Error::new)
+            .and_then(|location| resolve_place(&location, client))
+            .and_then(|(name, coord)| {
+                let _spinner =
+                    progress::Spinner::start(format!("Fetching climate normals for {}", name));
+                let daily = climate.daily(&coord).context(format!(
+                    "fetching climate normals from {}",
+                    climate::PROVIDER
+                ))?;
+                climate::Normals::for_month(&daily, month)
+                    .map(|normals| (name, normals))
+                    .ok_or_else(|| error::Error::new("no climate data for this month"))
+            })
+            .with_context(|| format!("scouting {}", line));
+        match normals {
+            Ok(place) => places.push(place),
+            Err(e) => print_error(&e),
+        }
+    }
+
+    if places.is_empty() {
+        eprintln!("{}", paint(Role::Error, "Error: no places to compare"));
+        process::exit(1);
+    }
+    display::print_scout(month, &places);
+}
+
+fn plan_trip(waypoints: &[Waypoint], client: &api::Client) {
+    let dates = trip::schedule(waypoints, chrono::Local::now().date_naive());
+    let mut failed = false;
+
+    println!("{}", paint(Role::Heading, "TRIP"));
+    for (waypoint, date) in waypoints.iter().zip(dates) {
+        let stop = parse_place(&waypoint.place)
+            .map_err(error::Error::new)
+            .and_then(|location| resolve_place(&location, client))
+            .and_then(|(name, coord)| {
+                let forecast = client
+                    .forecast(&api::Location::Coordinates(coord))
+                    .context(format!("fetching the forecast from {}", api::PROVIDER))?;
+                Ok((name, forecast))
+            })
+            .with_context(|| format!("planning the stop at {}", waypoint.place));
+
+        match stop {
+            Ok((name, forecast)) => {
+                let days = forecast::days(&forecast, display::utc_offset(forecast.city.timezone));
+                let day = days.iter().find(|day| day.date == date);
+                display::print_trip_stop(&name, date, day);
+            }
+            Err(e) => {
+                print_error(&e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Geocoding lookups to try per event location before giving up on it.
+const MAX_PLACE_LOOKUPS: usize = 3;
+
+/// The first of the location's queries the geocoder finds a place for.
+fn event_place(location: &str, client: &api::Client) -> Result<(String, api::Coord), error::Error> {
+    if let Ok(coord) = coords::parse(location) {
+        return Ok((api::Location::Coordinates(coord).to_string(), coord));
+    }
+    let mut last = None;
+    for query in ical::place_queries(location).iter().take(MAX_PLACE_LOOKUPS) {
+        let found = parse_place(query)
+            .map_err(error::Error::new)
+            .and_then(|place| resolve_place(&place, client));
+        match found {
+            Ok(found) => return Ok(found),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last
+        .unwrap_or_else(|| error::Error::new("no place given"))
+        .hint("give the event a city, e.g. \"Lyon, France\", as its location"))
+}
+
+fn show_agenda(file: &Path, days: u32, client: &api::Client) {
+    let contents = if file == Path::new("-") {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(file)
+    };
+    let appointments = contents
+        .map_err(error::Error::new)
+        .and_then(|contents| ical::read(&contents).map_err(error::Error::new))
+        .with_context(|| format!("reading {}", file.display()));
+    let appointments = match appointments {
+        Ok(appointments) => appointments,
         Err(e) => {
-            let err_message = format!(
-                "{}: {} is not set in .env file. Visit openweathermap.org to get an API key.",
-                e.to_string().to_uppercase(),
-                API_NAME_KEY,
+            print_error(&e);
+            process::exit(2);
+        }
+    };
+
+    let now = Utc::now();
+    let until = now + chrono::Duration::days(i64::from(days));
+    // Places are only looked up for events that may fall in the window,
+    // going by the widest offsets from UTC there are.
+    let slack = chrono::Duration::hours(14);
+    let (located, unlocated): (Vec<_>, Vec<_>) = appointments
+        .into_iter()
+        .filter(|appointment| {
+            let start = appointment.start.at(display::utc_offset(0));
+            start
+                .checked_add_signed(slack)
+                .is_some_and(|end| end >= now)
+                && start
+                    .checked_sub_signed(slack)
+                    .is_some_and(|begin| begin <= until)
+        })
+        .partition(|appointment| appointment.location.is_some());
+
+    // Places and forecasts by location, since events tend to repeat them.
+    let mut places: HashMap<String, Result<(String, api::ForecastResponse), error::Error>> =
+        HashMap::new();
+    for appointment in &located {
+        let location = appointment.location.as_deref().expect("partitioned above");
+        places.entry(location.to_string()).or_insert_with(|| {
+            event_place(location, client)
+                .and_then(|(name, coord)| {
+                    let forecast = client
+                        .forecast(&api::Location::Coordinates(coord))
+                        .context(format!("fetching the forecast from {}", api::PROVIDER))?;
+                    Ok((name, forecast))
+                })
+                .with_context(|| format!("finding the weather at {}", location))
+        });
+    }
+
+    let mut items = Vec::new();
+    for appointment in &located {
+        let location = appointment.location.as_deref().expect("partitioned above");
+        let Some(Ok((name, forecast))) = places.get(location) else {
+            continue;
+        };
+        let offset = display::utc_offset(forecast.city.timezone);
+        let start = appointment.start.at(offset);
+        if start < now || start > until {
+            continue;
+        }
+        items.push((start, offset, appointment, name, forecast));
+    }
+    items.sort_by_key(|(start, ..)| *start);
+
+    println!("{}", paint(Role::Heading, "AGENDA"));
+    for (start, offset, appointment, name, forecast) in &items {
+        let local = start.with_timezone(offset);
+        let (when, outlook) = match appointment.start {
+            ical::Start::AllDay(date) => {
+                let days = forecast::days(forecast, *offset);
+                let outlook = days
+                    .iter()
+                    .find(|day| day.date == date)
+                    .map(display::day_summary);
+                (local.format("%a %-d %b, all day").to_string(), outlook)
+            }
+            _ => (
+                format!(
+                    "{} {}",
+                    local.format("%a %-d %b"),
+                    local.format(units::preferences().clock.format())
+                ),
+                forecast::entry_at(forecast, start.timestamp()).map(display::step_summary),
+            ),
+        };
+        display::print_agenda_item(&when, &appointment.summary, name, outlook.as_deref());
+    }
+    if items.is_empty() {
+        println!(
+            "  {}",
+            paint(
+                Role::Muted,
+                format!("no events with a location in the next {} days", days)
             )
-            .red();
-            println!("{}", err_message);
+        );
+    }
+
+    let mut failed = false;
+    for e in places.values().filter_map(|place| place.as_ref().err()) {
+        print_error(e);
+        failed = true;
+    }
+    if !unlocated.is_empty() {
+        eprintln!(
+            "Note: skipped {} event(s) without a location",
+            unlocated.len()
+        );
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Whatever of the forecast and alerts cannot be fetched is left out of
+/// the snapshot with a warning; a place without current weather fails it.
+fn save_snapshot(file: &Path, places: &[api::Location], client: &api::Client) {
+    let mut snapshot = Snapshot {
+        taken_at: now(),
+        places: Vec::new(),
+    };
+    for location in places {
+        let weather = match current_weather(location, client)
+            .with_context(|| format!("saving a snapshot to {}", file.display()))
+        {
+            Ok(weather) => weather,
+            Err(e) => {
+                print_error(&e);
+                process::exit(1);
+            }
+        };
+        let forecast = client
+            .forecast(location)
+            .inspect_err(|e| eprintln!("Warning: no forecast for {}: {}", location, e))
+            .ok();
+        let alerts = match client.alerts(&weather.coord) {
+            Ok(alerts) => Some(alerts),
+            Err(e @ api::Error::Unsupported(_)) => {
+                skipped(&e);
+                None
+            }
+            Err(e) => {
+                eprintln!("Warning: no alerts for {}: {}", location, e);
+                None
+            }
+        };
+        snapshot.places.push(snapshot::Place {
+            weather,
+            forecast,
+            alerts,
+        });
+    }
+
+    if let Err(e) = snapshot::save(file, &snapshot) {
+        let e = error::Error::from(e).context(format!("saving a snapshot to {}", file.display()));
+        print_error(&e);
+        process::exit(1);
+    }
+    println!(
+        "Saved {} to {}",
+        places
+            .iter()
+            .map(api::Location::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+        file.display()
+    );
+}
 
-            return Err(VarError::NotPresent);
+fn view_snapshot(file: &Path) {
+    match snapshot::load(file) {
+        Ok(snapshot) => display::print_snapshot(&snapshot),
+        Err(e) => {
+            let e =
+                error::Error::from(e).context(format!("reading the snapshot {}", file.display()));
+            print_error(&e);
+            process::exit(1);
         }
     }
 }
 
-fn main() -> () {
-    let api_key = get_api_key().unwrap_or("".to_string());
-    if api_key.is_empty() {
+fn export_calendar(
+    location: &LocationArgs,
+    output: &Path,
+    comfort: Option<&Comfort>,
+    client: &api::Client,
+    config: &Config,
+) {
+    let location = location.required(1, config);
+    let forecast = match client
+        .forecast(&location)
+        .context(format!("fetching the forecast from {}", api::PROVIDER))
+        .with_context(|| format!("exporting the forecast for {}", location))
+    {
+        Ok(forecast) => forecast,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+
+    let days = forecast::days(&forecast, display::utc_offset(forecast.city.timezone));
+    let calendar = display::render_forecast_calendar(&forecast.city, &days, comfort, Utc::now());
+
+    if output == Path::new("-") {
+        print!("{}", calendar);
         return;
     }
+    if let Err(e) = fs::write(output, calendar) {
+        let e = error::Error::new(e).context(format!("writing {}", output.display()));
+        print_error(&e);
+        process::exit(1);
+    }
+    eprintln!(
+        "Wrote {} days of forecast for {} to {}",
+        days.len(),
+        forecast.city.name,
+        output.display()
+    );
+}
 
-    loop {
-        let city = get_city_name();
-        let country_code = get_country_code();
+fn show_usage(days: u32, config: &Config) {
+    let Some(tracker) = Tracker::open(config.usage.daily_limit) else {
+        eprintln!(
+            "{}",
+            paint(Role::Error, "Error: no data directory to read usage from")
+        );
+        process::exit(1);
+    };
+
+    match tracker.load().context("reading API usage") {
+        Ok(usage) => {
+            display::print_usage(&usage, Utc::now().date_naive(), days, tracker.daily_limit())
+        }
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    }
+}
 
-        let weather_info = get_weather_info(&city, &country_code, &api_key);
+fn list_events(city: Option<&str>, year: Option<i32>) {
+    let Some(log) = events::Log::open() else {
+        eprintln!(
+            "{}",
+            paint(Role::Error, "Error: no data directory to read events from")
+        );
+        process::exit(1);
+    };
 
-        match weather_info {
-            Ok(response) => {
-                print_weather_info(response);
+    match log.read().context("reading the event log") {
+        Ok(logged) => {
+            let found = events::matching(&logged, city, year);
+            if logged.is_empty() {
+                println!("No events recorded yet");
+            } else if found.is_empty() {
+                println!("No events recorded that match");
+            } else {
+                display::print_events(&found);
+            }
+        }
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Best effort, like the cache; a failure to record never fails a report.
+fn record_events(weather: &api::WeatherResponse, alerts: &[api::Alert]) {
+    if let Some(Err(e)) = events::Log::open().map(|log| log.record(weather, alerts, now())) {
+        eprintln!("Warning: could not record weather events: {}", e);
+    }
+}
+
+fn configure(action: &ConfigAction) {
+    let result = match action {
+        ConfigAction::Edit => edit_config(),
+        ConfigAction::Get { key } => get_setting(key.as_deref()),
+        ConfigAction::Set { key, value } => set_setting(key, value),
+        ConfigAction::Path => config::path()
+            .map(|path| println!("{}", path.display()))
+            .ok_or_else(|| error::Error::new("no config directory on this system")),
+    };
+    if let Err(e) = result {
+        print_error(&e);
+        process::exit(1);
+    }
+}
+
+fn get_setting(key: Option<&str>) -> Result<(), error::Error> {
+    let config = config::load()?;
+    let entries = match key {
+        None => config.entries(),
+        Some(key) => match config.get(key).map_err(error::Error::new)? {
+            // A section: everything under it.
+            serde_json::Value::Object(_) => config
+                .entries()
+                .into_iter()
+                .filter(|(entry, _)| entry.starts_with(&format!("{}.", key)))
+                .collect(),
+            value => {
+                println!("{}", config::display_value(&value));
+                return Ok(());
+            }
+        },
+    };
+    for (key, value) in entries {
+        println!("{} = {}", key, value);
+    }
+    Ok(())
+}
+
+fn set_setting(key: &str, value: &str) -> Result<(), error::Error> {
+    let config = config::load()?
+        .set(key, value)
+        .map_err(|e| error::Error::new(e).hint("run `cli_weather config get` to list the keys"))?;
+    config
+        .validate()
+        .with_context(|| format!("setting {}", key))?;
+    let path = config::save(&config)?;
+    println!("Set {} in {}", key, path.display());
+    Ok(())
+}
+
+/// Edits a copy, so the real file only changes once the edit checks out.
+fn edit_config() -> Result<(), error::Error> {
+    let path =
+        config::path().ok_or_else(|| error::Error::new("no config directory on this system"))?;
+    if !path.exists() {
+        config::load()?;
+    }
+    let draft = env::temp_dir().join(format!("cli_weather-config-{}.toml", process::id()));
+    fs::copy(&path, &draft)
+        .map_err(error::Error::new)
+        .with_context(|| format!("copying {}", path.display()))?;
+
+    let result = loop {
+        if let Err(e) = run_editor(&draft) {
+            break Err(e);
+        }
+        match config::check(&draft) {
+            Ok(_) => {
+                break fs::copy(&draft, &path)
+                    .map(|_| println!("Saved {}", path.display()))
+                    .map_err(error::Error::new)
+                    .with_context(|| format!("saving {}", path.display()));
             }
             Err(e) => {
-                eprintln!("Error: {}", e);
+                print_error(&e.context("checking the edited config"));
+                println!("Edit again? (y/n)");
+                let mut choice = String::new();
+                io::stdin().read_line(&mut choice).unwrap_or_default();
+                if !choice.trim().eq_ignore_ascii_case("y") {
+                    println!("Left {} unchanged", path.display());
+                    break Ok(());
+                }
             }
         }
+    };
+    let _ = fs::remove_file(&draft);
+    result
+}
+
+/// Tints the terminal by the weather just shown, when `tint.enabled` is
+/// set: through `tint.hook` if there is one, else with OSC 11 when stdout
+/// is a terminal.
+fn apply_tint(weather: &api::WeatherResponse, config: &Config) {
+    let settings = &config.tint;
+    if !settings.enabled {
+        return;
+    }
+    let tint = Tint::of(weather.main.temp, settings);
+    let color = tint.color(settings);
+
+    let Some(hook) = &settings.hook else {
+        if !io::stdout().is_terminal() {
+            return;
+        }
+        match tint::osc(color) {
+            Ok(osc) => print!("{}", osc),
+            Err(e) => eprintln!("Warning: could not tint the terminal: {}", e),
+        }
+        return;
+    };
+    let status = hooks::shell(hook)
+        .env("CLI_WEATHER_TINT", tint.name())
+        .env("CLI_WEATHER_TEMP", weather.main.temp.to_string())
+        .env("CLI_WEATHER_COLOR", color.unwrap_or_default())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: tint hook exited with {}", status),
+        Err(e) => eprintln!("Warning: could not run the tint hook: {}", e),
+    }
+}
+
+/// $VISUAL, then $EDITOR, which may carry arguments such as "code --wait".
+fn run_editor(file: &Path) -> Result<(), error::Error> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from(if cfg!(windows) { "notepad" } else { "vi" }));
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(file)
+        .status()
+        .map_err(error::Error::new)
+        .with_context(|| format!("starting the editor {}", program))
+        .map_err(|e| e.hint("set VISUAL or EDITOR to the editor you use"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(error::Error::new(format!(
+            "{} exited with {}",
+            program, status
+        )))
+    }
+}
+
+/// Resolves the typed place through the geocoder, so localized spellings
+/// work and ambiguous names can be narrowed down.
+fn interactive_weather(
+    city: &str,
+    country_code: &str,
+    prompt: &mut Prompt,
+    client: &api::Client,
+) -> Result<Option<(api::Location, api::WeatherResponse)>, error::Error> {
+    let candidates = client
+        .geocode(city, country_code)
+        .context(format!("looking up places from {}", api::PROVIDER))
+        .with_context(|| format!("finding {}, {}", city, country_code))?;
+    if candidates.is_empty() {
+        println!(
+            "{}",
+            paint(
+                Role::Error,
+                format!("No place called {} found in {}.", city, country_code)
+            )
+        );
+        return Ok(None);
+    }
+
+    let Some(place) = pick_place(candidates, client.lang().as_deref(), prompt) else {
+        return Ok(None);
+    };
+    let location = api::Location::Coordinates(place.coord());
+    let mut weather = current_weather(&location, client)?;
+    weather.name = place.localized_name(client.lang().as_deref()).to_string();
+    Ok(Some((location, weather)))
+}
+
+/// Loops until the user says no or presses Ctrl-C or Ctrl-D. Cities and
+/// countries that were found are offered again with the up arrow and tab.
+fn interactive(options: &ReportOptions, client: &api::Client, config: &Config) {
+    let prompts = Prompt::new(Some("cities"))
+        .and_then(|cities| Ok((cities, Prompt::new(Some("countries"))?, Prompt::new(None)?)));
+    let (mut cities, mut countries, mut choices) = match prompts {
+        Ok(prompts) => prompts,
+        Err(e) => {
+            print_error(&error::Error::new(e).context("setting up the prompt"));
+            process::exit(1);
+        }
+    };
 
-        let choice = is_repeat();
+    while let Some(city) = get_city_name(&mut cities) {
+        let Some((country, country_code)) = get_country_code(&mut countries) else {
+            break;
+        };
 
-        if !choice {
+        match interactive_weather(&city, &country_code, &mut choices, client) {
+            Ok(Some((location, response))) => {
+                cities.remember(&city);
+                countries.remember(&country);
+                let options = ReportOptions {
+                    since: options.diff.then(|| earlier(&location, client)).flatten(),
+                    uv: uv_summary(&response, client, config),
+                    rain_chance: rain_chance(&response, options, client),
+                    energy: dryer_window(&response, client, config),
+                    ..*options
+                };
+                display::print_weather_info(&response, &options);
+                apply_tint(&response, config);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                print_error(&e);
+            }
+        }
+
+        if !is_repeat(&mut choices) {
             break;
         }
     }
 }
+
+fn http_client(config: &Config) -> Result<reqwest::blocking::Client, error::Error> {
+    let user_agent = config.http.user_agent.as_deref().unwrap_or(api::USER_AGENT);
+    api::http_client(user_agent, &config.http.headers).map_err(|e| {
+        error::Error::new(e)
+            .context("setting up HTTP requests")
+            .hint("check the [http] section of the config file")
+    })
+}
+
+/// A client set up from the config; `lang` overrides the configured one.
+fn new_client(
+    api_key: &str,
+    lang: Option<String>,
+    http: reqwest::blocking::Client,
+    config: &Config,
+) -> Result<api::Client, error::Error> {
+    let auth = config
+        .auth
+        .auth(api_key)
+        .map_err(|e| e.hint("check the [auth] section of the config file"))?;
+    let client = api::Client::new(api_key);
+    let client = match &config.http.base_url {
+        Some(base_url) => client.with_base_url(base_url),
+        None => client,
+    };
+    Ok(client
+        .with_auth(auth)
+        .with_http(http)
+        .with_lang(lang.or(config.lang.clone()))
+        .with_usage(
+            Tracker::open(config.usage.daily_limit)
+                .map(|tracker| tracker.with_keep_days(config.retention.usage_days)),
+        )
+        .with_cache(Cache::open().map(|cache| cache.with_max_age(config.cache.ttl_secs)))
+        .with_rate_limit(config.usage.per_minute)
+        .with_breaker(Breaker::new(
+            config.breaker.failures,
+            Duration::from_secs(config.breaker.cooldown_secs),
+        ))
+        .with_log(config.http.log))
+}
+
+fn main() {
+    let cli = Cli::parse();
+    dotenv::dotenv().ok();
+
+    let template = match cli
+        .format
+        .as_deref()
+        .map(|f| Template::parse(f, display::STATUS_PLACEHOLDERS))
+    {
+        Some(Ok(template)) => Some(template),
+        Some(Err(e)) => {
+            eprintln!(
+                "{}",
+                paint(Role::Error, format!("Error: invalid --format: {}", e))
+            );
+            process::exit(2);
+        }
+        None => None,
+    };
+
+    // These must work even when the config file does not load.
+    match &cli.command {
+        Some(Command::Config { action }) => return configure(action),
+        // Reports a config that does not load as JSON too.
+        Some(Command::Lambda) => return lambda::run(cli.api_key.as_deref(), cli.lang.clone()),
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut io::stdout());
+            return;
+        }
+        _ => {}
+    }
+
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            print_error(&e);
+            process::exit(1);
+        }
+    };
+
+    let theme_name = cli.theme.as_deref().unwrap_or(&config.theme.name);
+    match Theme::new(theme_name, &config.theme.colors) {
+        Ok(theme) => theme::init(theme),
+        Err(e) => {
+            let e = error::Error::new(e)
+                .context("loading the color theme")
+                .hint("check --theme and the [theme] section of the config file");
+            print_error(&e);
+            process::exit(2);
+        }
+    }
+
+    match config.units.preferences() {
+        Ok(preferences) => units::init(Preferences {
+            wind: cli.wind_unit.unwrap_or(preferences.wind),
+            ..preferences
+        }),
+        Err(e) => {
+            print_error(&e.hint("check the [units] section of the config file"));
+            process::exit(2);
+        }
+    }
+
+    // These need no API key.
+    match &cli.command {
+        Some(Command::Usage { days }) => return show_usage(*days, &config),
+        Some(Command::Events {
+            action: EventsAction::List { city, year },
+        }) => return list_events(city.as_deref(), *year),
+        // Goes on to the tour once the key is saved.
+        Some(Command::Login) => login(),
+        Some(Command::Logout) => return logout(),
+        Some(Command::Snapshot {
+            action: SnapshotAction::View { file },
+        }) => return view_snapshot(file),
+        _ => {}
+    }
+
+    let Some(api_key) = get_api_key(cli.api_key.as_deref(), &config) else {
+        return;
+    };
+
+    let http = match http_client(&config) {
+        Ok(http) => http,
+        Err(e) => {
+            print_error(&e);
+            process::exit(2);
+        }
+    };
+
+    let serving = matches!(cli.command, Some(Command::Serve { .. }));
+    let daemon = matches!(
+        cli.command,
+        Some(Command::Serve { .. } | Command::Watch { .. })
+    );
+    if let Some(endpoint) = daemon
+        .then(|| telemetry::traces_endpoint(config.tracing.otlp_endpoint.as_deref()))
+        .flatten()
+    {
+        telemetry::init(Tracer::new(&endpoint));
+    }
+
+    let client = match new_client(&api_key, cli.lang, http, &config) {
+        Ok(client) => client,
+        Err(e) => {
+            print_error(&e);
+            process::exit(2);
+        }
+    };
+    let client = client
+        .with_metrics(serving.then(Metrics::default))
+        .with_progress(!serving);
+    let icons = if cli.nerd_font {
+        IconSet::NerdFont
+    } else {
+        IconSet::Emoji
+    };
+    let report = ReportOptions {
+        geo_uri: cli.geo_uri,
+        art: cli.art,
+        uv: Part::Off,
+        comfort: cli.score.then_some(config.comfort),
+        advice: cli.advice,
+        rain_chance: Part::Off,
+        energy: Part::Off,
+        diff: cli.diff,
+        since: None,
+    };
+
+    match cli.command {
+        None => match (cli.location.location(&config), template) {
+            (Err(e), _) => {
+                print_error(&e);
+                process::exit(1);
+            }
+            (Ok(Some(location)), Some(template)) => {
+                status_line(&location, &template, icons, &client)
+            }
+            (Ok(Some(location)), None) if cli.at.is_some() => {
+                show_snapshot(&location, cli.at.expect("checked above"), &client)
+            }
+            (Ok(Some(location)), None) => match current_weather(&location, &client) {
+                Ok(weather) => {
+                    let report = ReportOptions {
+                        since: report.diff.then(|| earlier(&location, &client)).flatten(),
+                        uv: uv_summary(&weather, &client, &config),
+                        rain_chance: rain_chance(&weather, &report, &client),
+                        energy: dryer_window(&weather, &client, &config),
+                        ..report
+                    };
+                    display::print_weather_info(&weather, &report);
+                    apply_tint(&weather, &config);
+                }
+                Err(e) => {
+                    print_error(&e);
+                    process::exit(1);
+                }
+            },
+            (Ok(None), _) => interactive(&report, &client, &config),
+        },
+        Some(Command::Check(args)) => {
+            let thresholds = args.limits.thresholds(&config.notify);
+            let places = followed(&args, &config).unwrap_or_else(|e| {
+                print_error(&e);
+                process::exit(1);
+            });
+            check(&places, &thresholds, &client);
+        }
+        Some(Command::Watch { check, interval }) => {
+            let thresholds = check.limits.thresholds(&config.notify);
+            let places = followed(&check, &config).unwrap_or_else(|e| {
+                print_error(&e);
+                process::exit(1);
+            });
+            watch(&places, &thresholds, interval, &config, &report, &client);
+        }
+        Some(Command::Rain { location, hours }) => will_it_rain(&location, hours, &client, &config),
+        Some(Command::Recent { location }) => show_recent(&location, &client, &config),
+        Some(Command::Rules {
+            action:
+                RulesAction::Test {
+                    location,
+                    history,
+                    limits,
+                },
+        }) => test_rules(
+            &location,
+            history,
+            &limits.thresholds(&config.notify),
+            &client,
+            &config,
+        ),
+        Some(Command::Astro { location, date }) => show_astro(&location, date, &client, &config),
+        Some(Command::Sun { location, at }) => show_sun(&location, at, &client, &config),
+        Some(Command::Uv { location }) => show_uv(&location, &client, &config),
+        Some(Command::Export { location, output }) => export_calendar(
+            &location,
+            &output,
+            report.comfort.as_ref(),
+            &client,
+            &config,
+        ),
+        Some(Command::Trip { waypoints }) => plan_trip(&waypoints, &client),
+        Some(Command::Agenda { file, days }) => show_agenda(&file, days, &client),
+        Some(Command::Login) => take_tour(&report, &client, &config),
+        Some(Command::Snapshot {
+            action: SnapshotAction::Save { file, places },
+        }) => save_snapshot(&file, &places, &client),
+        Some(Command::Scout { cities, month }) => scout(&cities, month, &client),
+        Some(Command::Serve { listen }) => serve::serve(&listen, &client, &config),
+        Some(
+            Command::Usage { .. }
+            | Command::Events { .. }
+            | Command::Lambda
+            | Command::Logout
+            | Command::Config { .. }
+            | Command::Completions { .. }
+            | Command::Snapshot {
+                action: SnapshotAction::View { .. },
+            },
+        ) => {
+            unreachable!("handled before the client is built")
+        }
+    }
+}
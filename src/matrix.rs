@@ -0,0 +1,180 @@
+//! Posts notifications to a Matrix room through the client-server API, for
+//! machines without a desktop to show them on. Needs an access token for
+//! an account that has joined the room.
+
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, fmt};
+
+use crate::api;
+use crate::notify::Trigger;
+
+/// Environment variable holding the access token; it takes precedence
+/// over `matrix.access_token` in the config file.
+pub const TOKEN_VAR: &str = "MATRIX_ACCESS_TOKEN";
+
+/// The `m.room.message` event body, with an HTML version for clients that
+/// show formatting.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Message {
+    pub msgtype: &'static str,
+    pub body: String,
+    pub format: &'static str,
+    pub formatted_body: String,
+}
+
+impl Message {
+    pub fn new(city: &str, trigger: &Trigger) -> Message {
+        let summary = format!("{}: {}", city, trigger.summary());
+        let detail = trigger.to_string();
+        Message {
+            msgtype: "m.text",
+            body: format!("{}\n{}", summary, detail),
+            format: "org.matrix.custom.html",
+            formatted_body: format!(
+                "<strong>{}</strong><br>{}",
+                escape(&summary),
+                escape(&detail)
+            ),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// What a homeserver sends with a failed request, e.g.
+/// `{"errcode":"M_FORBIDDEN","error":"..."}`.
+#[derive(Deserialize)]
+struct ErrorBody {
+    errcode: String,
+    error: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// `matrix.homeserver` cannot be read as a URL.
+    Homeserver(String),
+    Network(reqwest::Error),
+    Rejected {
+        status: u16,
+        errcode: String,
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::Homeserver(_) => Some("give the homeserver's base URL, e.g. https://matrix.org"),
+            Error::Network(_) => Some("check matrix.homeserver and your internet connection"),
+            Error::Rejected { status: 401, .. } => {
+                Some("the access token is wrong or expired; log in again to get a new one")
+            }
+            Error::Rejected { status: 403, .. } => {
+                Some("the account has to join the room before it can post there")
+            }
+            Error::Rejected { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Homeserver(url) => write!(f, "'{}' is not a homeserver URL", url),
+            Error::Network(e) => write!(f, "request failed: {}", e),
+            Error::Rejected {
+                status,
+                errcode,
+                message,
+            } => write!(f, "rejected ({}, {}): {}", status, errcode, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub struct Room {
+    http: reqwest::blocking::Client,
+    homeserver: reqwest::Url,
+    room: String,
+    token: String,
+    /// Transaction IDs have to be new for each message sent with the
+    /// token; starting them with the start time keeps them so across runs.
+    txn_prefix: i64,
+    sent: Cell<u64>,
+}
+
+impl Room {
+    /// `homeserver` is its base URL, e.g. "https://matrix.org", and `room`
+    /// an ID like "!abc123:matrix.org".
+    pub fn new(homeserver: &str, room: &str, token: &str) -> Result<Room, Error> {
+        let url = reqwest::Url::parse(homeserver)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| Error::Homeserver(homeserver.to_string()))?;
+        Ok(Room {
+            http: api::http_client(api::USER_AGENT, &Default::default()).unwrap_or_default(),
+            homeserver: url,
+            room: room.to_string(),
+            token: token.to_string(),
+            txn_prefix: chrono::Utc::now().timestamp_millis(),
+            sent: Cell::new(0),
+        })
+    }
+
+    pub fn with_http(self, http: reqwest::blocking::Client) -> Self {
+        Room { http, ..self }
+    }
+
+    pub fn send(&self, message: &Message) -> Result<(), Error> {
+        let txn = self.sent.get();
+        self.sent.set(txn + 1);
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .expect("checked in new")
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room,
+                "send",
+                "m.room.message",
+            ])
+            .push(&format!("cli_weather-{}-{}", self.txn_prefix, txn));
+        let response = self
+            .http
+            .put(url)
+            .bearer_auth(&self.token)
+            .json(message)
+            .send()
+            .map_err(|e| Error::Network(e.without_url()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().unwrap_or_default();
+        let (errcode, message) = match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(body) => (body.errcode, body.error),
+            Err(_) => (
+                status.as_str().to_string(),
+                status
+                    .canonical_reason()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            ),
+        };
+        Err(Error::Rejected {
+            status: status.as_u16(),
+            errcode,
+            message,
+        })
+    }
+}
@@ -0,0 +1,269 @@
+//! What `serve` reports at `/metrics`, in the Prometheus text format: the
+//! weather it last served for each place, and how the server itself is
+//! doing.
+
+use std::{cell::RefCell, collections::BTreeMap, fmt::Write, time::Duration};
+
+use crate::api::WeatherResponse;
+
+/// Upper bounds in seconds; the provider usually answers in 0.1 to 1 s.
+const LATENCY_BUCKETS: [f64; 10] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; the last one is `+Inf`.
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| secs <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += secs;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Gauges {
+    temp: f64,
+    feels_like: f64,
+    humidity: f64,
+    pressure: f64,
+    wind_speed: f64,
+}
+
+/// A weather gauge's name, help text and reading.
+type Gauge = (&'static str, &'static str, fn(&Gauges) -> f64);
+
+#[derive(Debug, Default)]
+struct Inner {
+    served: BTreeMap<String, Histogram>,
+    responses: BTreeMap<(String, u16), u64>,
+    upstream: BTreeMap<String, Histogram>,
+    errors: BTreeMap<&'static str, u64>,
+    cache_hits: u64,
+    cache_misses: u64,
+    weather: BTreeMap<String, Gauges>,
+}
+
+/// Calls made to the provider today against the configured daily limit.
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub used: u32,
+    pub limit: u32,
+}
+
+/// Collected in memory for the life of the process.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    inner: RefCell<Inner>,
+}
+
+impl Metrics {
+    /// A request `serve` answered, by route such as `/weather`.
+    pub fn observe_served(&self, route: &str, status: u16, elapsed: Duration) {
+        let mut inner = self.inner.borrow_mut();
+        inner
+            .served
+            .entry(route.to_string())
+            .or_default()
+            .observe(elapsed);
+        *inner
+            .responses
+            .entry((route.to_string(), status))
+            .or_default() += 1;
+    }
+
+    /// A call to the provider, by API path, whatever its outcome.
+    pub fn observe_upstream(&self, endpoint: &str, elapsed: Duration) {
+        self.inner
+            .borrow_mut()
+            .upstream
+            .entry(endpoint.to_string())
+            .or_default()
+            .observe(elapsed);
+    }
+
+    /// A failed provider call, by `api::Error::kind`.
+    pub fn upstream_error(&self, kind: &'static str) {
+        *self.inner.borrow_mut().errors.entry(kind).or_default() += 1;
+    }
+
+    pub fn cache_lookup(&self, hit: bool) {
+        let mut inner = self.inner.borrow_mut();
+        if hit {
+            inner.cache_hits += 1;
+        } else {
+            inner.cache_misses += 1;
+        }
+    }
+
+    /// Remembers the latest weather served for `location`.
+    pub fn observe_weather(&self, location: &str, weather: &WeatherResponse) {
+        let gauges = Gauges {
+            temp: weather.main.temp,
+            feels_like: weather.main.feels_like,
+            humidity: weather.main.humidity,
+            pressure: weather.main.pressure,
+            wind_speed: weather.wind.speed,
+        };
+        self.inner
+            .borrow_mut()
+            .weather
+            .insert(location.to_string(), gauges);
+    }
+
+    pub fn render(&self, quota: Option<Quota>) -> String {
+        let inner = self.inner.borrow();
+        let mut out = String::new();
+
+        let weather: [Gauge; 5] = [
+            ("temperature_celsius", "Air temperature.", |g| g.temp),
+            ("feels_like_celsius", "Apparent temperature.", |g| {
+                g.feels_like
+            }),
+            ("humidity_percent", "Relative humidity.", |g| g.humidity),
+            ("pressure_hpa", "Air pressure at sea level.", |g| g.pressure),
+            ("wind_speed_meters_per_second", "Wind speed.", |g| {
+                g.wind_speed
+            }),
+        ];
+        if !inner.weather.is_empty() {
+            for (name, help, value) in weather {
+                let name = format!("cli_weather_{}", name);
+                header(&mut out, &name, help, "gauge");
+                for (location, gauges) in &inner.weather {
+                    let labels = labels(&[("location", location)]);
+                    let _ = writeln!(out, "{}{} {}", name, labels, value(gauges));
+                }
+            }
+        }
+
+        histograms(
+            &mut out,
+            "cli_weather_http_request_duration_seconds",
+            "Time taken to answer requests to serve.",
+            "route",
+            &inner.served,
+        );
+        header(
+            &mut out,
+            "cli_weather_http_responses_total",
+            "Requests answered by serve, by status.",
+            "counter",
+        );
+        for ((route, status), count) in &inner.responses {
+            let status = status.to_string();
+            let labels = labels(&[("route", route), ("status", &status)]);
+            let _ = writeln!(out, "cli_weather_http_responses_total{} {}", labels, count);
+        }
+
+        histograms(
+            &mut out,
+            "cli_weather_upstream_request_duration_seconds",
+            "Time taken by calls to the weather provider.",
+            "endpoint",
+            &inner.upstream,
+        );
+        header(
+            &mut out,
+            "cli_weather_upstream_errors_total",
+            "Failed calls to the weather provider, by kind.",
+            "counter",
+        );
+        for (kind, count) in &inner.errors {
+            let labels = labels(&[("kind", kind)]);
+            let _ = writeln!(out, "cli_weather_upstream_errors_total{} {}", labels, count);
+        }
+
+        header(
+            &mut out,
+            "cli_weather_cache_lookups_total",
+            "Current weather lookups in the cache, by result.",
+            "counter",
+        );
+        for (result, count) in [("hit", inner.cache_hits), ("miss", inner.cache_misses)] {
+            let labels = labels(&[("result", result)]);
+            let _ = writeln!(out, "cli_weather_cache_lookups_total{} {}", labels, count);
+        }
+        let lookups = inner.cache_hits + inner.cache_misses;
+        if lookups > 0 {
+            header(
+                &mut out,
+                "cli_weather_cache_hit_ratio",
+                "Share of lookups answered from the cache.",
+                "gauge",
+            );
+            let ratio = inner.cache_hits as f64 / lookups as f64;
+            let _ = writeln!(out, "cli_weather_cache_hit_ratio {}", ratio);
+        }
+
+        if let Some(quota) = quota {
+            header(
+                &mut out,
+                "cli_weather_api_calls_today",
+                "Calls made to the weather provider today (UTC).",
+                "gauge",
+            );
+            let _ = writeln!(out, "cli_weather_api_calls_today {}", quota.used);
+            header(
+                &mut out,
+                "cli_weather_api_daily_limit",
+                "Configured usage.daily_limit; 0 means none.",
+                "gauge",
+            );
+            let _ = writeln!(out, "cli_weather_api_daily_limit {}", quota.limit);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn histograms(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    histograms: &BTreeMap<String, Histogram>,
+) {
+    header(out, name, help, "histogram");
+    for (key, histogram) in histograms {
+        let mut cumulative = 0;
+        for (i, count) in histogram.counts.iter().enumerate() {
+            cumulative += count;
+            let bound = LATENCY_BUCKETS
+                .get(i)
+                .map_or_else(|| String::from("+Inf"), f64::to_string);
+            let labels = labels(&[(label, key), ("le", &bound)]);
+            let _ = writeln!(out, "{}_bucket{} {}", name, labels, cumulative);
+        }
+        let labels = labels(&[(label, key)]);
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, histogram.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, cumulative);
+    }
+}
+
+/// `{key="value",...}` with quotes, backslashes and newlines escaped.
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
@@ -0,0 +1,395 @@
+//! The layers a provider request passes through on its way to the network
+//! and back, so behaviour such as retrying or counting calls is composed
+//! in one place rather than written into each fetch. Each layer sees the
+//! request, and either answers it itself or hands it on with `next`.
+//!
+//! `api::Client` runs its own layers in this order, outermost first: any
+//! added with `with_middleware`, `Cached`, `Retry`, `RateLimit`, `Metrics`,
+//! the `Breaker`, the usage `Tracker`, `Log`, `Trace`, then `Authenticate`
+//! just before the request is sent.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::api::{ApiError, Error, PROVIDER};
+use crate::cache::Cache;
+use crate::circuit::Breaker;
+use crate::credentials::Auth;
+use crate::metrics::Metrics;
+use crate::progress;
+use crate::telemetry::{self, Kind};
+use crate::usage::Tracker;
+
+/// Rate-limited requests are retried this many times when the server asks
+/// for a short enough wait; longer waits are reported instead.
+const MAX_RETRIES: u32 = 2;
+const MAX_RETRY_WAIT_SECS: u64 = 10;
+
+/// A request to the provider: the endpoint path, its query and any headers
+/// beyond the client's own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub path: String,
+    pub params: Vec<(&'static str, String)>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn new(path: &str, params: Vec<(&'static str, String)>) -> Request {
+        Request {
+            path: path.to_string(),
+            params,
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// A success response, not yet decoded. Failures come back as `Error`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+    /// Seconds the response may be reused, from its cache headers.
+    pub max_age: Option<u64>,
+}
+
+impl Response {
+    /// A 200 with `body` and no cache headers, e.g. for canned answers.
+    pub fn ok(body: impl Into<String>) -> Response {
+        Response {
+            status: 200,
+            body: body.into(),
+            max_age: None,
+        }
+    }
+}
+
+pub trait Middleware {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error>;
+}
+
+/// The rest of the chain after a layer, ending with the network.
+pub struct Next<'a> {
+    layers: &'a [&'a dyn Middleware],
+    send: &'a dyn Fn(&Request) -> Result<Response, Error>,
+}
+
+impl<'a> Next<'a> {
+    pub fn new(
+        layers: &'a [&'a dyn Middleware],
+        send: &'a dyn Fn(&Request) -> Result<Response, Error>,
+    ) -> Next<'a> {
+        Next { layers, send }
+    }
+
+    /// Passes `request` to the next layer; may be called more than once,
+    /// as `Retry` does.
+    pub fn run(&self, request: &Request) -> Result<Response, Error> {
+        match self.layers.split_first() {
+            Some((layer, rest)) => layer.handle(request, &Next::new(rest, self.send)),
+            None => (self.send)(request),
+        }
+    }
+}
+
+/// Retries rate limited requests when the server asks for a short enough
+/// wait, or after 1, 2, 4... seconds when it does not say; longer waits
+/// are reported instead.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    pub max_retries: u32,
+    pub max_wait_secs: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry {
+            max_retries: MAX_RETRIES,
+            max_wait_secs: MAX_RETRY_WAIT_SECS,
+        }
+    }
+}
+
+impl Middleware for Retry {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        let mut attempt = 0;
+        loop {
+            match next.run(request) {
+                Err(Error::RateLimited { retry_after, .. })
+                    if attempt < self.max_retries
+                        && retry_after.unwrap_or_default() <= self.max_wait_secs =>
+                {
+                    let wait = retry_after.unwrap_or(1 << attempt);
+                    thread::sleep(Duration::from_secs(wait));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Answers from `cache` while its entry is fresh and keeps what comes back.
+/// A request with `Cache-Control: no-cache` skips the lookup but is still
+/// kept. When the provider is down, the last answer is used however old,
+/// with a warning. `metrics` counts the lookups.
+pub struct Cached<'a> {
+    pub cache: &'a Cache,
+    pub metrics: Option<&'a Metrics>,
+}
+
+impl Middleware for Cached<'_> {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        if !self.cache.keeps(request) {
+            return next.run(request);
+        }
+        let now = chrono::Utc::now().timestamp();
+        let refresh = request
+            .headers
+            .iter()
+            .any(|(name, value)| name.eq_ignore_ascii_case("cache-control") && value == "no-cache");
+        if !refresh {
+            let cached = self.cache.get(request, now);
+            if let Some(metrics) = self.metrics {
+                metrics.cache_lookup(cached.is_some());
+            }
+            if let Some(response) = cached {
+                return Ok(response);
+            }
+        }
+
+        match next.run(request) {
+            Ok(response) => {
+                if let Err(e) = self.cache.put(request, &response, now) {
+                    progress::suspend(|| eprintln!("Warning: could not cache response: {}", e));
+                }
+                Ok(response)
+            }
+            Err(e) if e.is_outage() => match self.cache.get_stale(request) {
+                Some((response, fetched_at)) => {
+                    progress::suspend(|| {
+                        eprintln!(
+                            "Warning: {} is unavailable; showing data from {} minutes ago",
+                            PROVIDER,
+                            (now - fetched_at) / 60
+                        )
+                    });
+                    Ok(response)
+                }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Holds requests to `per_minute` in any minute, so a busy `serve` stays
+/// under the provider's limit instead of getting the key blocked. One over
+/// it fails as rate limited, with the wait until there is room again, which
+/// `Retry` sits out when it is short enough.
+#[derive(Debug)]
+pub struct RateLimit {
+    per_minute: u32,
+    sent: RefCell<VecDeque<Instant>>,
+}
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+impl RateLimit {
+    pub fn new(per_minute: u32) -> RateLimit {
+        RateLimit {
+            per_minute: per_minute.max(1),
+            sent: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// Counts a request at `now` if there is room for it; otherwise how long
+    /// until there is.
+    pub fn admit(&self, now: Instant) -> Result<(), Duration> {
+        let mut sent = self.sent.borrow_mut();
+        while sent
+            .front()
+            .is_some_and(|at| now.saturating_duration_since(*at) >= MINUTE)
+        {
+            sent.pop_front();
+        }
+        if let Some(oldest) = sent
+            .front()
+            .filter(|_| sent.len() >= self.per_minute as usize)
+        {
+            return Err(MINUTE.saturating_sub(now.saturating_duration_since(*oldest)));
+        }
+        sent.push_back(now);
+        Ok(())
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        match self.admit(Instant::now()) {
+            Ok(()) => next.run(request),
+            Err(wait) => Err(Error::RateLimited {
+                // Rounded up, so the retry finds room.
+                retry_after: Some(wait.as_secs() + u64::from(wait.subsec_nanos() > 0)),
+                error: ApiError {
+                    cod: String::from("429"),
+                    message: format!(
+                        "held back to stay under {} requests a minute [config: usage.per_minute]",
+                        self.per_minute
+                    ),
+                },
+            }),
+        }
+    }
+}
+
+/// Times every attempt sent and counts failures by kind.
+impl Middleware for Metrics {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        let started = Instant::now();
+        let result = next.run(request);
+        if !matches!(result, Err(Error::CircuitOpen { .. })) {
+            self.observe_upstream(&request.path, started.elapsed());
+        }
+        if let Err(e) = &result {
+            self.upstream_error(e.kind());
+        }
+        result
+    }
+}
+
+/// Sends nothing while open; outages count towards opening it, rate
+/// limits do not.
+impl Middleware for Breaker {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        if let Err(wait) = self.check(Instant::now()) {
+            return Err(Error::CircuitOpen {
+                retry_in: wait.as_secs().max(1),
+            });
+        }
+
+        let result = next.run(request);
+        if !matches!(result, Err(Error::RateLimited { .. })) {
+            let outage = result.as_ref().is_err_and(Error::is_outage);
+            self.record(!outage, Instant::now());
+        }
+        result
+    }
+}
+
+/// Counts every request sent, for `usage` and the daily limit warning.
+/// Best effort; a failure to record never blocks the request.
+impl Middleware for Tracker {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        match self.record(chrono::Utc::now().date_naive(), crate::api::PROVIDER) {
+            Ok(Some(warning)) => progress::suspend(|| eprintln!("Warning: {}", warning)),
+            Ok(None) => {}
+            Err(e) => progress::suspend(|| eprintln!("Warning: could not record API usage: {}", e)),
+        }
+        next.run(request)
+    }
+}
+
+/// Prints each request and how it went to stderr, e.g.
+/// `GET /data/2.5/weather: 200 in 182 ms`. Credentials are added after
+/// this layer, so they never show.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Log;
+
+impl Middleware for Log {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        let started = Instant::now();
+        let result = next.run(request);
+        let outcome = match &result {
+            Ok(response) => response.status.to_string(),
+            Err(Error::Api { status, .. }) => status.to_string(),
+            Err(Error::RateLimited { .. }) => String::from("429"),
+            Err(e) => e.kind().to_string(),
+        };
+        progress::suspend(|| {
+            eprintln!(
+                "GET {}: {} in {} ms",
+                request.path,
+                outcome,
+                started.elapsed().as_millis()
+            )
+        });
+        result
+    }
+}
+
+/// A client span per request sent, for `serve` and `watch` tracing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Trace;
+
+impl Middleware for Trace {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        let mut span = telemetry::span_of_kind("fetch", Kind::Client);
+        span.attr("url.path", request.path.as_str());
+        let result = next.run(request);
+        match &result {
+            Ok(response) => span.attr("http.response.status_code", response.status),
+            Err(e) => {
+                if let Error::Api { status, .. } = e {
+                    span.attr("http.response.status_code", *status);
+                }
+                span.fail(e);
+            }
+        }
+        result
+    }
+}
+
+/// Adds the credentials `auth` calls for; `http` fetches OAuth tokens.
+pub struct Authenticate<'a> {
+    pub auth: &'a Auth,
+    pub http: &'a reqwest::blocking::Client,
+}
+
+impl Middleware for Authenticate<'_> {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        let credentials = self
+            .auth
+            .credentials(self.http, &request.path, &request.params)?;
+        let mut request = request.clone();
+        request.params.extend(credentials.query);
+        request.headers.extend(credentials.headers);
+        next.run(&request)
+    }
+}
+
+/// Answers requests for known paths with fixed bodies and passes the rest
+/// on, e.g. to work offline or in tests.
+#[derive(Debug, Clone, Default)]
+pub struct Canned {
+    responses: Vec<(String, String)>,
+}
+
+impl Canned {
+    pub fn new() -> Canned {
+        Canned::default()
+    }
+
+    /// Answers requests to `path` with `body`.
+    pub fn with(mut self, path: &str, body: impl Into<String>) -> Canned {
+        self.responses.push((path.to_string(), body.into()));
+        self
+    }
+}
+
+impl Middleware for Canned {
+    fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+        match self
+            .responses
+            .iter()
+            .find(|(path, _)| *path == request.path)
+        {
+            Some((_, body)) => Ok(Response::ok(body.clone())),
+            None => next.run(request),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Times of day for `--at`, such as "tomorrow 09:00" in the place's own
+//! time zone.
+
+use chrono::{DateTime, Days, FixedOffset, NaiveDate, NaiveTime, TimeZone, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum On {
+    Today,
+    Tomorrow,
+    Date(NaiveDate),
+    /// The next time the clock shows the time, today or tomorrow.
+    Next,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moment {
+    pub on: On,
+    pub time: NaiveTime,
+    /// Whether the time is in UTC rather than at the place.
+    pub utc: bool,
+}
+
+/// `[today|tomorrow|YYYY-MM-DD] HH:MM [local|utc]`, e.g. "tomorrow 09:00
+/// local" or "18:30".
+pub fn parse(input: &str) -> Result<Moment, String> {
+    let invalid = || {
+        format!(
+            "'{}' is not a time; use e.g. \"tomorrow 09:00\", \"2025-07-01 18:30\" or \"07:00 utc\"",
+            input
+        )
+    };
+    let mut words: Vec<String> = input.split_whitespace().map(str::to_lowercase).collect();
+
+    let utc = match words.last().map(String::as_str) {
+        Some("utc") => {
+            words.pop();
+            true
+        }
+        Some("local") => {
+            words.pop();
+            false
+        }
+        _ => false,
+    };
+    let (on, time) = match words.as_slice() {
+        [time] => (On::Next, time),
+        [day, time] => {
+            let on = match day.as_str() {
+                "today" => On::Today,
+                "tomorrow" => On::Tomorrow,
+                date => {
+                    On::Date(NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid())?)
+                }
+            };
+            (on, time)
+        }
+        _ => return Err(invalid()),
+    };
+    let time = NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| invalid())?;
+    Ok(Moment { on, time, utc })
+}
+
+impl Moment {
+    /// The instant meant, for a place at `offset` from UTC.
+    pub fn resolve(&self, now: DateTime<Utc>, offset: FixedOffset) -> DateTime<Utc> {
+        let zone = if self.utc {
+            FixedOffset::east_opt(0).expect("0 is a valid offset")
+        } else {
+            offset
+        };
+        let today = now.with_timezone(&zone).date_naive();
+        let tomorrow = today.checked_add_days(Days::new(1)).unwrap_or(today);
+        let at = |date: NaiveDate| {
+            zone.from_local_datetime(&date.and_time(self.time))
+                .single()
+                .map_or(now, |time| time.with_timezone(&Utc))
+        };
+
+        match self.on {
+            On::Today => at(today),
+            On::Tomorrow => at(tomorrow),
+            On::Date(date) => at(date),
+            On::Next if at(today) >= now => at(today),
+            On::Next => at(tomorrow),
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use chrono::NaiveDate;
+use notify_rust::Notification;
+use std::fmt;
+
+use crate::api::{Alert, ForecastResponse, WeatherResponse};
+use crate::climate::RAIN_DAY_MM;
+use crate::config::Thresholds;
+use crate::forecast;
+use crate::rule::{Field, Sample};
+
+/// A threshold crossed by the current conditions or the near forecast.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    Freezing(f64),
+    Windy(f64),
+    Rain {
+        at: String,
+        description: String,
+    },
+    Alert {
+        event: String,
+        sender: String,
+    },
+    /// One of the `[notify.rules]` holds.
+    Rule {
+        name: String,
+        rule: String,
+    },
+}
+
+/// How urgent a trigger is, for sinks that rank notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Severe,
+}
+
+/// Alert names with one of these in them are the most urgent, as with the
+/// red level of European warnings or "Severe Thunderstorm Warning".
+const SEVERE_WORDS: &[&str] = &["red", "extreme", "severe", "emergency"];
+/// And these the least, as with the yellow level or an "Advisory".
+const INFO_WORDS: &[&str] = &["yellow", "advisory", "statement", "minor"];
+
+/// OpenWeatherMap gives alerts no severity of their own, so it is read off
+/// the name; alerts that say neither way are warnings.
+pub fn alert_severity(event: &str) -> Severity {
+    let event = event.to_lowercase();
+    let has = |words: &[&str]| {
+        event
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| words.contains(&word))
+    };
+    if has(SEVERE_WORDS) {
+        Severity::Severe
+    } else if has(INFO_WORDS) {
+        Severity::Info
+    } else {
+        Severity::Warning
+    }
+}
+
+impl Trigger {
+    /// Identifies the condition independently of its current value, so watch
+    /// mode notifies once when a threshold is crossed rather than every cycle.
+    pub fn key(&self) -> String {
+        match self {
+            Trigger::Freezing(_) => String::from("freezing"),
+            Trigger::Windy(_) => String::from("windy"),
+            Trigger::Rain { .. } => String::from("rain"),
+            Trigger::Alert { event, .. } => format!("alert:{}", event),
+            Trigger::Rule { name, .. } => format!("rule:{}", name),
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Trigger::Freezing(_) | Trigger::Windy(_) => Severity::Warning,
+            Trigger::Rain { .. } | Trigger::Rule { .. } => Severity::Info,
+            Trigger::Alert { event, .. } => alert_severity(event),
+        }
+    }
+
+    /// A short title, as notifications show above the details.
+    pub fn summary(&self) -> &'static str {
+        match self {
+            Trigger::Freezing(_) => "Freezing temperature",
+            Trigger::Windy(_) => "Strong wind",
+            Trigger::Rain { .. } => "Rain expected",
+            Trigger::Alert { .. } => "Weather alert",
+            Trigger::Rule { .. } => "Weather rule",
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trigger::Freezing(temp) => write!(f, "temperature is {}°C", temp),
+            Trigger::Windy(speed) => write!(f, "wind speed is {} m/s", speed),
+            Trigger::Rain { at, description } => {
+                write!(f, "{} expected at {} UTC", description, at)
+            }
+            Trigger::Alert { event, sender } => write!(f, "{} ({})", event, sender),
+            Trigger::Rule { name, rule } => write!(f, "{}: {}", name, rule),
+        }
+    }
+}
+
+pub fn evaluate(
+    weather: &WeatherResponse,
+    forecast: Option<&ForecastResponse>,
+    alerts: &[Alert],
+    thresholds: &Thresholds,
+    now: i64,
+) -> Vec<Trigger> {
+    let mut triggers = Vec::new();
+
+    if freezing(weather.main.temp, thresholds) {
+        triggers.push(Trigger::Freezing(weather.main.temp));
+    }
+    if windy(weather.wind.speed, thresholds) {
+        triggers.push(Trigger::Windy(weather.wind.speed));
+    }
+
+    if let Some(rain) = forecast
+        .filter(|_| thresholds.rain_within_hours > 0)
+        .and_then(|f| forecast::first_rain(f, now, thresholds.rain_within_hours))
+    {
+        triggers.push(Trigger::Rain {
+            at: rain.entry.dt_txt.clone(),
+            description: rain.description,
+        });
+    }
+
+    for alert in alerts.iter().filter(|a| a.start <= now && now < a.end) {
+        triggers.push(Trigger::Alert {
+            event: alert.event.clone(),
+            sender: alert.sender_name.clone(),
+        });
+    }
+
+    // Checked when the config was loaded, so none fail here.
+    if let Ok(rules) = thresholds.rules() {
+        let mut timeline = vec![Sample::current(weather, now)];
+        if let Some(forecast) = forecast {
+            timeline.extend(
+                forecast
+                    .list
+                    .iter()
+                    .filter(|entry| entry.dt > now)
+                    .map(Sample::forecast),
+            );
+        }
+        for (name, expr) in rules {
+            if expr.holds(&timeline) {
+                triggers.push(Trigger::Rule {
+                    name: name.to_string(),
+                    rule: thresholds.rules[name].clone(),
+                });
+            }
+        }
+    }
+
+    triggers
+}
+
+fn freezing(temp: f64, thresholds: &Thresholds) -> bool {
+    temp < thresholds.min_temp
+}
+
+fn windy(speed: f64, thresholds: &Thresholds) -> bool {
+    speed > thresholds.max_wind
+}
+
+/// A past day as the rules see it, in °C, m/s and mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PastDay {
+    pub date: NaiveDate,
+    pub low: f64,
+    /// The strongest wind, when the history has it.
+    pub wind: Option<f64>,
+    pub rain: f64,
+}
+
+/// What a past day tells `[notify.rules]`.
+const DAY_FIELDS: [Field; 3] = [Field::Temp, Field::Wind, Field::Precipitation];
+
+impl PastDay {
+    /// The low for temp and the day's rain spread evenly over its hours.
+    fn sample(&self) -> Sample {
+        Sample {
+            at: self
+                .date
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or_default()
+                .and_utc()
+                .timestamp(),
+            temp: Some(self.low),
+            wind: self.wind,
+            precipitation: Some(self.rain / 24.0),
+            ..Sample::default()
+        }
+    }
+}
+
+/// How often one rule would have fired over the days replayed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fired {
+    /// As in `Trigger::key` for the fixed thresholds, or the name in
+    /// `[notify.rules]`.
+    pub rule: String,
+    /// The expression, for `[notify.rules]`.
+    pub expr: Option<String>,
+    pub days: Vec<NaiveDate>,
+    /// Days the history had enough to judge by; 0 for alerts, which it
+    /// does not keep.
+    pub judged: usize,
+    /// False for rules a day at a time cannot tell, such as ones on
+    /// humidity or with a `for` under a day.
+    pub replayable: bool,
+}
+
+/// Replays `days` through the rules `thresholds` turns on, each day
+/// standing in for every check made on it. Rain counts on days with at
+/// least `RAIN_DAY_MM`, since the history has totals rather than the
+/// forecast the live rule looks at. `[notify.rules]` see each day's low,
+/// strongest wind and mean rain per hour, with a `for` running over the
+/// days after.
+pub fn backtest(days: &[PastDay], thresholds: &Thresholds) -> Vec<Fired> {
+    let replay = |rule: &str, fires: &dyn Fn(usize) -> Option<bool>| {
+        let judged: Vec<(NaiveDate, bool)> = (0..days.len())
+            .filter_map(|i| fires(i).map(|fired| (days[i].date, fired)))
+            .collect();
+        Fired {
+            rule: rule.to_string(),
+            expr: None,
+            days: judged
+                .iter()
+                .filter(|(_, fired)| *fired)
+                .map(|(date, _)| *date)
+                .collect(),
+            judged: judged.len(),
+            replayable: true,
+        }
+    };
+
+    let mut fired = vec![
+        replay("freezing", &|i| Some(freezing(days[i].low, thresholds))),
+        replay("windy", &|i| {
+            days[i].wind.map(|wind| windy(wind, thresholds))
+        }),
+    ];
+    if thresholds.rain_within_hours > 0 {
+        fired.push(replay("rain", &|i| Some(days[i].rain >= RAIN_DAY_MM)));
+    }
+    if thresholds.alerts {
+        fired.push(replay("alert", &|_| None));
+    }
+
+    // Checked when the config was loaded, so none fail here.
+    let rules = thresholds.rules().unwrap_or_default();
+    let timeline: Vec<Sample> = days.iter().map(PastDay::sample).collect();
+    for (name, expr) in rules {
+        let replayable = expr.shortest_for().is_none_or(|secs| secs >= 86_400)
+            && Field::ALL
+                .into_iter()
+                .all(|field| DAY_FIELDS.contains(&field) || !expr.uses(field));
+        let fires = |i: usize| {
+            (replayable && (days[i].wind.is_some() || !expr.uses(Field::Wind)))
+                .then(|| expr.holds(&timeline[i..]))
+        };
+        fired.push(Fired {
+            expr: Some(thresholds.rules[name].clone()),
+            replayable,
+            ..replay(name, &fires)
+        });
+    }
+    fired
+}
+
+pub fn send(city: &str, trigger: &Trigger) -> Result<(), notify_rust::error::Error> {
+    Notification::new()
+        .appname("cli_weather")
+        .summary(&format!("{}: {}", city, trigger.summary()))
+        .body(&trigger.to_string())
+        .show()?;
+    Ok(())
+}
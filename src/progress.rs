@@ -0,0 +1,55 @@
+//! A spinner on stderr while requests are in flight, so a slow connection
+//! does not look like a hang.
+//!
+//! It is drawn only when stderr is a terminal, so piped and redirected
+//! output stays clean, and it is cleared when dropped, including when the
+//! request fails.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+const TICK: Duration = Duration::from_millis(100);
+
+/// The spinner on screen, so warnings can be printed around it.
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+pub struct Spinner(ProgressBar);
+
+impl Spinner {
+    pub fn start(message: String) -> Spinner {
+        let bar = ProgressBar::new_spinner().with_message(message);
+        if let Ok(style) = ProgressStyle::with_template("{spinner} {msg}…") {
+            bar.set_style(style);
+        }
+        bar.enable_steady_tick(TICK);
+        *ACTIVE.lock().unwrap_or_else(PoisonError::into_inner) = Some(bar.clone());
+        Spinner(bar)
+    }
+
+    pub fn set_message(&self, message: String) {
+        self.0.set_message(message);
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.0.finish_and_clear();
+        ACTIVE.lock().unwrap_or_else(PoisonError::into_inner).take();
+    }
+}
+
+/// Runs `print` with the spinner, if any, hidden, so its output is not
+/// drawn over.
+pub fn suspend<R>(print: impl FnOnce() -> R) -> R {
+    let active = ACTIVE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    match active {
+        Some(bar) => bar.suspend(print),
+        None => print(),
+    }
+}
@@ -0,0 +1,99 @@
+//! Line editing for the interactive prompts: arrow keys, history kept across
+//! runs and tab completion from it.
+
+use rustyline::{
+    completion::Completer, error::ReadlineError, history::DefaultHistory, Context, Editor, Helper,
+    Highlighter, Hinter, Validator,
+};
+use std::path::PathBuf;
+
+const DATA_DIR: &str = "cli_weather";
+/// Answers kept per prompt.
+const HISTORY_SIZE: usize = 200;
+
+/// Completes the whole line from earlier answers, most recent first.
+#[derive(Helper, Highlighter, Hinter, Validator, Default)]
+struct Earlier(Vec<String>);
+
+impl Completer for Earlier {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let typed = line[..pos].to_lowercase();
+        let mut matches: Vec<String> = Vec::new();
+        for answer in self.0.iter().rev() {
+            if answer.to_lowercase().starts_with(&typed) && !matches.contains(answer) {
+                matches.push(answer.clone());
+            }
+        }
+        Ok((0, matches))
+    }
+}
+
+pub struct Prompt {
+    editor: Editor<Earlier, DefaultHistory>,
+    history: Option<PathBuf>,
+}
+
+impl Prompt {
+    /// `history` names the history file, e.g. "cities" for
+    /// `cities_history.txt` in the data directory; None keeps none. A missing
+    /// or unreadable file starts empty.
+    pub fn new(history: Option<&str>) -> rustyline::Result<Prompt> {
+        let config = rustyline::Config::builder()
+            .max_history_size(HISTORY_SIZE)?
+            .history_ignore_dups(true)?
+            .auto_add_history(false)
+            .build();
+        let mut editor = Editor::with_config(config)?;
+        let history = history
+            .zip(dirs::data_dir())
+            .map(|(name, dir)| dir.join(DATA_DIR).join(format!("{}_history.txt", name)));
+        if let Some(path) = &history {
+            let _ = editor.load_history(path);
+        }
+        let earlier = editor.history().iter().cloned().collect();
+        editor.set_helper(Some(Earlier(earlier)));
+        Ok(Prompt { editor, history })
+    }
+
+    /// The trimmed line typed, or None on Ctrl-C or Ctrl-D.
+    pub fn read(&mut self, prompt: &str) -> Option<String> {
+        match self.editor.readline(prompt) {
+            Ok(line) => Some(line.trim().to_string()),
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => None,
+            Err(e) => {
+                eprintln!("Warning: could not read input: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Keeps `answer` for completion and the up arrow, now and in later runs.
+    pub fn remember(&mut self, answer: &str) {
+        if answer.is_empty() || !self.editor.add_history_entry(answer).unwrap_or(false) {
+            return;
+        }
+        if let Some(earlier) = self.editor.helper_mut() {
+            earlier.0.retain(|entry| entry != answer);
+            earlier.0.push(answer.to_string());
+        }
+        if let Some(path) = &self.history {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            if let Err(e) = self.editor.append_history(path) {
+                eprintln!(
+                    "Warning: could not save history to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+}
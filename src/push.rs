@@ -0,0 +1,196 @@
+//! Phone push through a Gotify server or ntfy, the usual self-hosted
+//! choices. Each notification's priority follows the trigger's severity.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::fmt;
+
+use crate::api;
+use crate::notify::{Severity, Trigger};
+
+/// Environment variable holding the Gotify application token; it takes
+/// precedence over `gotify.token` in the config file.
+pub const GOTIFY_TOKEN_VAR: &str = "GOTIFY_TOKEN";
+/// Environment variable holding an ntfy access token, for protected topics.
+pub const NTFY_TOKEN_VAR: &str = "NTFY_TOKEN";
+/// Used when `ntfy.server` is not set.
+pub const NTFY_SERVER: &str = "https://ntfy.sh";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    Gotify,
+    Ntfy,
+}
+
+impl Service {
+    pub fn name(self) -> &'static str {
+        match self {
+            Service::Gotify => "Gotify",
+            Service::Ntfy => "ntfy",
+        }
+    }
+
+    /// Gotify goes from 0 to 10, where clients make a sound from 4 up and
+    /// pop up from 8; ntfy from 1 to 5, 3 being its default.
+    pub fn priority(self, severity: Severity) -> u8 {
+        match (self, severity) {
+            (Service::Gotify, Severity::Info) => 3,
+            (Service::Gotify, Severity::Warning) => 5,
+            (Service::Gotify, Severity::Severe) => 8,
+            (Service::Ntfy, Severity::Info) => 3,
+            (Service::Ntfy, Severity::Warning) => 4,
+            (Service::Ntfy, Severity::Severe) => 5,
+        }
+    }
+}
+
+/// What either server sends with a failed request: Gotify's
+/// `{"error":"Unauthorized","errorDescription":"..."}` or ntfy's
+/// `{"http":401,"error":"unauthorized"}`.
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+    #[serde(rename = "errorDescription")]
+    description: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The configured server cannot be read as a URL.
+    Server(String),
+    Network(reqwest::Error),
+    Rejected {
+        status: u16,
+        message: String,
+    },
+}
+
+impl Error {
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::Server(_) => Some("give the server's base URL, e.g. https://ntfy.sh"),
+            Error::Network(_) => Some("check the server URL and your internet connection"),
+            Error::Rejected {
+                status: 401 | 403, ..
+            } => Some("check the token; Gotify needs an application token, not a client one"),
+            Error::Rejected { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Server(url) => write!(f, "'{}' is not a server URL", url),
+            Error::Network(e) => write!(f, "request failed: {}", e),
+            Error::Rejected { status, message } => write!(f, "rejected ({}): {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub struct Push {
+    http: reqwest::blocking::Client,
+    service: Service,
+    server: reqwest::Url,
+    /// The ntfy topic; Gotify picks the app from the token.
+    topic: Option<String>,
+    token: Option<String>,
+}
+
+impl Push {
+    /// Posts to the application `token` belongs to on the Gotify `server`.
+    pub fn gotify(server: &str, token: &str) -> Result<Push, Error> {
+        Push::new(Service::Gotify, server, None, Some(token.to_string()))
+    }
+
+    /// Publishes to `topic` on the ntfy `server`, with `token` for topics
+    /// that need one.
+    pub fn ntfy(server: &str, topic: &str, token: Option<&str>) -> Result<Push, Error> {
+        Push::new(
+            Service::Ntfy,
+            server,
+            Some(topic.to_string()),
+            token.map(String::from),
+        )
+    }
+
+    fn new(
+        service: Service,
+        server: &str,
+        topic: Option<String>,
+        token: Option<String>,
+    ) -> Result<Push, Error> {
+        let url = reqwest::Url::parse(server)
+            .ok()
+            .filter(|url| !url.cannot_be_a_base())
+            .ok_or_else(|| Error::Server(server.to_string()))?;
+        Ok(Push {
+            http: api::http_client(api::USER_AGENT, &Default::default()).unwrap_or_default(),
+            service,
+            server: url,
+            topic,
+            token,
+        })
+    }
+
+    pub fn with_http(self, http: reqwest::blocking::Client) -> Self {
+        Push { http, ..self }
+    }
+
+    pub fn service(&self) -> Service {
+        self.service
+    }
+
+    pub fn send(&self, city: &str, trigger: &Trigger) -> Result<(), Error> {
+        let title = format!("{}: {}", city, trigger.summary());
+        let priority = self.service.priority(trigger.severity());
+        let mut url = self.server.clone();
+        let path = match self.service {
+            Service::Gotify => "message",
+            // Publishing as JSON goes to the root, with the topic in the body.
+            Service::Ntfy => "",
+        };
+        url.path_segments_mut()
+            .expect("checked in new")
+            .pop_if_empty()
+            .push(path);
+
+        let mut body = json!({
+            "title": title,
+            "message": trigger.to_string(),
+            "priority": priority,
+        });
+        if let Some(topic) = &self.topic {
+            body["topic"] = json!(topic);
+        }
+        let mut request = self.http.post(url).json(&body);
+        if let Some(token) = &self.token {
+            request = match self.service {
+                Service::Gotify => request.header("X-Gotify-Key", token),
+                Service::Ntfy => request.bearer_auth(token),
+            };
+        }
+        let response = request
+            .send()
+            .map_err(|e| Error::Network(e.without_url()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = response.text().unwrap_or_default();
+        let message = match serde_json::from_str::<ErrorBody>(&body) {
+            Ok(body) => body.description.unwrap_or(body.error),
+            Err(_) => status
+                .canonical_reason()
+                .unwrap_or("unknown error")
+                .to_string(),
+        };
+        Err(Error::Rejected {
+            status: status.as_u16(),
+            message,
+        })
+    }
+}
@@ -0,0 +1,65 @@
+//! The past week at a place for `recent`: which days to ask for and what
+//! they add up to.
+
+use chrono::{Days, NaiveDate};
+
+use crate::climate::RAIN_DAY_MM;
+
+/// How far back `recent` looks.
+pub const DAYS: u32 = 7;
+
+/// One day's aggregates, in °C and mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayRecord {
+    pub date: NaiveDate,
+    pub low: f64,
+    pub high: f64,
+    pub rain: f64,
+}
+
+/// The `days` whole days before `today`, oldest first.
+pub fn past_days(today: NaiveDate, days: u32) -> Vec<NaiveDate> {
+    (1..=days)
+        .rev()
+        .filter_map(|back| today.checked_sub_days(Days::new(u64::from(back))))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Totals {
+    /// mm over all the days.
+    pub rain: f64,
+    /// Days with at least 1 mm, as in the climate normals.
+    pub rain_days: usize,
+    pub mean_high: f64,
+    pub lowest: f64,
+    pub highest: f64,
+    pub wettest: Option<DayRecord>,
+    pub hottest: Option<DayRecord>,
+}
+
+impl Totals {
+    /// None without any days.
+    pub fn of(days: &[DayRecord]) -> Option<Totals> {
+        if days.is_empty() {
+            return None;
+        }
+        let by = |key: fn(&DayRecord) -> f64| {
+            days.iter()
+                .copied()
+                .max_by(|a, b| key(a).total_cmp(&key(b)))
+        };
+        Some(Totals {
+            rain: days.iter().map(|day| day.rain).sum(),
+            rain_days: days.iter().filter(|day| day.rain >= RAIN_DAY_MM).count(),
+            mean_high: days.iter().map(|day| day.high).sum::<f64>() / days.len() as f64,
+            lowest: days.iter().map(|day| day.low).fold(f64::INFINITY, f64::min),
+            highest: days
+                .iter()
+                .map(|day| day.high)
+                .fold(f64::NEG_INFINITY, f64::max),
+            wettest: by(|day| day.rain).filter(|day| day.rain > 0.0),
+            hottest: by(|day| day.high),
+        })
+    }
+}
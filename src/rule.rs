@@ -0,0 +1,485 @@
+//! Conditions for `check` and `watch` beyond the fixed thresholds, written
+//! as expressions in the `[notify.rules]` section, e.g.
+//!
+//! ```toml
+//! [notify.rules]
+//! gale = "wind > 12 for 2h"
+//! black_ice = "temp < 2 && precipitation > 0"
+//! ```
+//!
+//! Comparisons join with `&&` or `and`, `||` or `or`, and parentheses;
+//! `&&` binds tighter. `for` asks for a condition to hold from now until
+//! the duration is up, going by the forecast. Values are in °C, m/s, hPa,
+//! mm per hour and percent, as elsewhere in the config. A `for` can run to
+//! at most 5 days, where the forecast ends.
+
+use std::fmt;
+
+use crate::api::{ForecastEntry, WeatherResponse};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Temp,
+    FeelsLike,
+    Humidity,
+    Pressure,
+    Wind,
+    Gust,
+    Precipitation,
+    RainChance,
+}
+
+impl Field {
+    pub const ALL: [Field; 8] = [
+        Field::Temp,
+        Field::FeelsLike,
+        Field::Humidity,
+        Field::Pressure,
+        Field::Wind,
+        Field::Gust,
+        Field::Precipitation,
+        Field::RainChance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Temp => "temp",
+            Field::FeelsLike => "feels_like",
+            Field::Humidity => "humidity",
+            Field::Pressure => "pressure",
+            Field::Wind => "wind",
+            Field::Gust => "gust",
+            Field::Precipitation => "precipitation",
+            Field::RainChance => "rain_chance",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Field> {
+        Field::ALL.into_iter().find(|field| field.name() == name)
+    }
+}
+
+/// The longest `for` there is forecast to tell, in seconds.
+pub const MAX_FOR_SECS: i64 = 5 * 86_400;
+
+/// What a rule can look at, for hints.
+pub const FIELDS_HINT: &str = "temp and feels_like (°C), humidity and rain_chance (%), \
+pressure (hPa), wind and gust (m/s) and precipitation (mm/h), e.g. \"wind > 12 for 2h\" \
+or \"temp < 2 && precipitation > 0\"";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn apply(self, left: f64, right: f64) -> bool {
+        match self {
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Compare {
+        field: Field,
+        op: Op,
+        value: f64,
+    },
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// Holds from now for this many seconds.
+    For(Box<Expr>, i64),
+}
+
+/// Where and why an expression could not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// 1-based, in characters.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Op(Op),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Op(_) => write!(f, "a comparison"),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+/// Tokens with the column each starts at.
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let column = i + 1;
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let (token, len) = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            '&' if next == Some('&') => (Token::And, 2),
+            '|' if next == Some('|') => (Token::Or, 2),
+            '<' if next == Some('=') => (Token::Op(Op::Le), 2),
+            '>' if next == Some('=') => (Token::Op(Op::Ge), 2),
+            '=' if next == Some('=') => (Token::Op(Op::Eq), 2),
+            '!' if next == Some('=') => (Token::Op(Op::Ne), 2),
+            '<' => (Token::Op(Op::Lt), 1),
+            '>' => (Token::Op(Op::Gt), 1),
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let len = 1 + chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit() || **c == '.')
+                    .count();
+                let text: String = chars[i..i + len].iter().collect();
+                let value = text.parse().map_err(|_| ParseError {
+                    column,
+                    message: format!("'{}' is not a number", text),
+                })?;
+                (Token::Number(value), len)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let len = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .count();
+                let word: String = chars[i..i + len].iter().collect();
+                let token = match word.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    _ => Token::Ident(word),
+                };
+                (token, len)
+            }
+            c => {
+                return Err(ParseError {
+                    column,
+                    message: format!("unexpected '{}'", c),
+                })
+            }
+        };
+        tokens.push((column, token));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    at: usize,
+    /// Where the input ends, for errors there.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(_, token)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.at)
+            .map_or(self.end, |(column, _)| *column)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).map(|(_, token)| token.clone());
+        self.at += 1;
+        token
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let found = match self.peek() {
+            Some(token) => format!("found {}", token),
+            None => String::from("the rule ends"),
+        };
+        ParseError {
+            column: self.column(),
+            message: format!("expected {}, but {}", expected, found),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.term()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.term()?));
+        }
+        Ok(expr)
+    }
+
+    /// A comparison or a group, maybe followed by `for` and a duration.
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let expr = match self.peek() {
+            Some(Token::Open) => {
+                let open = self.column();
+                self.next();
+                let inner = self.or()?;
+                if self.next() != Some(Token::Close) {
+                    return Err(ParseError {
+                        column: open,
+                        message: String::from("this '(' is never closed"),
+                    });
+                }
+                inner
+            }
+            _ => self.comparison()?,
+        };
+
+        match self.peek() {
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case("for") => {
+                self.next();
+                let secs = self.duration()?;
+                Ok(Expr::For(Box::new(expr), secs))
+            }
+            _ => Ok(expr),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let column = self.column();
+        let field = match self.peek() {
+            Some(Token::Ident(name)) => {
+                Field::from_name(&name.to_lowercase()).ok_or_else(|| ParseError {
+                    column,
+                    message: format!("unknown field '{}'", name),
+                })?
+            }
+            _ => return Err(self.error("a field such as temp or wind")),
+        };
+        self.next();
+
+        let Some(Token::Op(op)) = self.peek().cloned() else {
+            return Err(self.error(&format!("<, <=, >, >=, == or != after {}", field.name())));
+        };
+        self.next();
+
+        let Some(Token::Number(value)) = self.peek().cloned() else {
+            return Err(self.error("a number"));
+        };
+        self.next();
+        Ok(Expr::Compare { field, op, value })
+    }
+
+    /// A number and a unit, e.g. `2h`, `90 min` or `30m`, in seconds.
+    fn duration(&mut self) -> Result<i64, ParseError> {
+        let column = self.column();
+        let Some(Token::Number(amount)) = self.peek().cloned() else {
+            return Err(self.error("a duration such as 2h or 30m"));
+        };
+        self.next();
+        let unit_secs = match self.peek() {
+            Some(Token::Ident(unit)) => match unit.to_lowercase().as_str() {
+                "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+                "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+                "d" | "day" | "days" => 86_400.0,
+                _ => return Err(self.error("a unit of m, h or d")),
+            },
+            _ => return Err(self.error("a unit of m, h or d")),
+        };
+        self.next();
+        if amount <= 0.0 {
+            return Err(ParseError {
+                column,
+                message: String::from("a duration must be more than 0"),
+            });
+        }
+        let secs = (amount * unit_secs).round();
+        if secs > MAX_FOR_SECS as f64 {
+            return Err(ParseError {
+                column,
+                message: String::from("a duration can be at most 5 days"),
+            });
+        }
+        Ok(secs as i64)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        at: 0,
+        end: input.chars().count() + 1,
+    };
+    let expr = parser.or()?;
+    if parser.peek().is_some() {
+        return Err(parser.error("&&, || or the end of the rule"));
+    }
+    Ok(expr)
+}
+
+/// Conditions at one time, in the units rules use; None where unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sample {
+    pub at: i64,
+    pub temp: Option<f64>,
+    pub feels_like: Option<f64>,
+    pub humidity: Option<f64>,
+    pub pressure: Option<f64>,
+    pub wind: Option<f64>,
+    pub gust: Option<f64>,
+    /// mm per hour.
+    pub precipitation: Option<f64>,
+    /// Percent.
+    pub rain_chance: Option<f64>,
+}
+
+impl Sample {
+    pub fn current(weather: &WeatherResponse, at: i64) -> Sample {
+        Sample {
+            at,
+            temp: Some(weather.main.temp),
+            feels_like: Some(weather.main.feels_like),
+            humidity: Some(weather.main.humidity),
+            pressure: Some(weather.main.pressure),
+            wind: Some(weather.wind.speed),
+            gust: weather.wind.gust,
+            precipitation: Some(weather.rain.as_ref().map_or(0.0, |rain| rain.one_hour)),
+            rain_chance: None,
+        }
+    }
+
+    pub fn forecast(entry: &ForecastEntry) -> Sample {
+        Sample {
+            at: entry.dt,
+            temp: Some(entry.main.temp),
+            feels_like: entry.main.feels_like,
+            humidity: Some(entry.main.humidity),
+            pressure: entry.main.pressure,
+            wind: entry.wind.as_ref().map(|wind| wind.speed),
+            gust: entry.wind.as_ref().and_then(|wind| wind.gust),
+            precipitation: Some(
+                entry
+                    .rain
+                    .as_ref()
+                    .map_or(0.0, |rain| rain.three_hours / 3.0),
+            ),
+            rain_chance: Some(entry.pop * 100.0),
+        }
+    }
+
+    fn value(&self, field: Field) -> Option<f64> {
+        match field {
+            Field::Temp => self.temp,
+            Field::FeelsLike => self.feels_like,
+            Field::Humidity => self.humidity,
+            Field::Pressure => self.pressure,
+            Field::Wind => self.wind,
+            Field::Gust => self.gust,
+            Field::Precipitation => self.precipitation,
+            Field::RainChance => self.rain_chance,
+        }
+    }
+}
+
+impl Expr {
+    /// Whether this holds at the first sample of `timeline`, which runs
+    /// forward in time. A comparison on an unknown value does not hold,
+    /// and nor does a duration that runs past the timeline.
+    pub fn holds(&self, timeline: &[Sample]) -> bool {
+        let Some(now) = timeline.first() else {
+            return false;
+        };
+        match self {
+            Expr::Compare { field, op, value } => {
+                now.value(*field).is_some_and(|v| op.apply(v, *value))
+            }
+            Expr::And(left, right) => left.holds(timeline) && right.holds(timeline),
+            Expr::Or(left, right) => left.holds(timeline) || right.holds(timeline),
+            Expr::For(inner, secs) => {
+                let until = now.at.saturating_add(*secs);
+                timeline.last().is_some_and(|last| last.at >= until)
+                    && (0..timeline.len())
+                        .take_while(|&i| timeline[i].at < until)
+                        .all(|i| inner.holds(&timeline[i..]))
+            }
+        }
+    }
+
+    /// Whether telling needs the forecast as well as the current weather.
+    pub fn needs_forecast(&self) -> bool {
+        match self {
+            Expr::Compare { field, .. } => *field == Field::RainChance,
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.needs_forecast() || right.needs_forecast()
+            }
+            Expr::For(..) => true,
+        }
+    }
+
+    /// Whether any comparison in this looks at `field`.
+    pub fn uses(&self, field: Field) -> bool {
+        match self {
+            Expr::Compare {
+                field: compared, ..
+            } => *compared == field,
+            Expr::And(left, right) | Expr::Or(left, right) => left.uses(field) || right.uses(field),
+            Expr::For(inner, _) => inner.uses(field),
+        }
+    }
+
+    /// The shortest `for` in this, in seconds, if it has any.
+    pub fn shortest_for(&self) -> Option<i64> {
+        match self {
+            Expr::Compare { .. } => None,
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                match (left.shortest_for(), right.shortest_for()) {
+                    (Some(l), Some(r)) => Some(l.min(r)),
+                    (l, r) => l.or(r),
+                }
+            }
+            Expr::For(inner, secs) => Some(inner.shortest_for().map_or(*secs, |s| s.min(*secs))),
+        }
+    }
+}
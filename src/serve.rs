@@ -0,0 +1,529 @@
+//! `serve`: answers weather queries over local HTTP, so status bars and
+//! dashboards can share one API key and one cache. Each request can ask
+//! for its own language with `Accept-Language` and units with `?units=`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, io::Read, time::Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use cli_weather::access::{self, Denied};
+use cli_weather::api::{ForecastResponse, WeatherResponse};
+use cli_weather::config::Config;
+use cli_weather::error::{self, Context};
+use cli_weather::locale::Locale;
+use cli_weather::metrics::Quota;
+use cli_weather::speech::{self, Question};
+use cli_weather::telemetry::{self, Kind};
+use cli_weather::units::{self, Preferences};
+use cli_weather::{api, coords, countries};
+
+use crate::{current_weather, now, print_error, tidy};
+
+#[derive(Serialize)]
+pub(crate) struct ErrorBody {
+    error: String,
+    hints: Vec<String>,
+}
+
+impl ErrorBody {
+    pub(crate) fn of(e: &error::Error) -> ErrorBody {
+        ErrorBody {
+            error: e.to_string(),
+            hints: e.hints().to_vec(),
+        }
+    }
+}
+
+/// The units numbers are given in, when a client asked for its own.
+#[derive(Serialize)]
+pub(crate) struct Units {
+    temperature: &'static str,
+    wind: &'static str,
+}
+
+impl Units {
+    pub(crate) fn of(preferences: Preferences) -> Units {
+        Units {
+            temperature: preferences.temperature.symbol(),
+            wind: preferences.wind.symbol(),
+        }
+    }
+}
+
+/// A failed request: the status to answer with and the error to report.
+struct Failure(u16, error::Error);
+
+const JSON: &str = "application/json";
+const PROMETHEUS: &str = "text/plain; version=0.0.4";
+
+/// The largest `/intent` body read; a question and a place fit in far less.
+const MAX_INTENT_BYTES: u64 = 4096;
+
+/// Paths are labelled as themselves in metrics only when they are routes,
+/// so scanners cannot grow the label set.
+const ROUTES: [&str; 5] = ["/weather", "/forecast", "/intent", "/health", "/metrics"];
+
+fn bad_request(message: impl Into<String>) -> Failure {
+    Failure(400, error::Error::new(message.into()))
+}
+
+fn denied(denied: Denied) -> Failure {
+    Failure(denied.status(), denied.into())
+}
+
+pub fn serve(listen: &str, client: &api::Client, config: &Config) {
+    let server = match Server::http(listen) {
+        Ok(server) => server,
+        Err(e) => {
+            let e = error::Error::new(e)
+                .context(format!("listening on {}", listen))
+                .hint("pick another address with --listen");
+            print_error(&e);
+            std::process::exit(1);
+        }
+    };
+    let mut clients = match access::Clients::new(&config.serve.clients) {
+        Ok(clients) => clients,
+        Err(e) => {
+            print_error(&error::Error::new(e));
+            std::process::exit(1);
+        }
+    };
+    let default_lang = client.lang();
+    eprintln!("Listening on http://{}", listen);
+    if clients.required() {
+        eprintln!(
+            "Requests need a token; {} client(s) in [[serve.clients]]",
+            config.serve.clients.len()
+        );
+    }
+
+    let mut tidied = None;
+    tidy(&config.retention, &mut tidied);
+
+    for mut request in server.incoming_requests() {
+        tidy(&config.retention, &mut tidied);
+        let started = Instant::now();
+        let path = path(&request).to_string();
+        let route = ROUTES
+            .into_iter()
+            .find(|route| *route == path.as_str())
+            .unwrap_or("other");
+        let mut span =
+            telemetry::span_of_kind(&format!("{} {}", request.method(), route), Kind::Server);
+        span.attr("http.request.method", request.method().to_string());
+        span.attr("http.route", route);
+
+        // Health checks come from the machine itself, without a token.
+        let admitted = if clients.required() && path != "/health" {
+            clients.admit(bearer(&request), now()).map(Some)
+        } else {
+            Ok(None)
+        };
+        if let Ok(Some(admitted)) = &admitted {
+            span.attr("cli_weather.client", admitted.name.clone());
+        }
+        let asked = admitted
+            .map_err(denied)
+            .and_then(|who| Ok((who, Asked::of(&request)?)));
+        // Every request, so a refused one does not answer in the language
+        // the one before asked for.
+        client.set_lang(match &asked {
+            Ok((_, asked)) => asked.lang.clone().or_else(|| default_lang.clone()),
+            Err(_) => default_lang.clone(),
+        });
+
+        let (content_type, result) = match (asked, request.method(), path.as_str()) {
+            (Err(e), _, _) => (JSON, Err(e)),
+            (Ok((who, asked)), Method::Get, "/weather") => {
+                (JSON, weather(&request, who, &asked, client))
+            }
+            (Ok((who, asked)), Method::Get, "/forecast") => {
+                (JSON, forecast(&request, who, &asked, client))
+            }
+            (Ok((who, asked)), Method::Post, "/intent") => {
+                (JSON, intent(&mut request, who, &asked, client))
+            }
+            (_, Method::Get, "/health") => (JSON, Ok(String::from("{\"status\":\"ok\"}"))),
+            (_, Method::Get, "/metrics") => (PROMETHEUS, Ok(metrics(client))),
+            (_, Method::Get, _) => (
+                JSON,
+                Err(Failure(
+                    404,
+                    error::Error::new("no such endpoint")
+                        .hint("try /weather, /forecast, /health or /metrics, or POST to /intent"),
+                )),
+            ),
+            _ => (
+                JSON,
+                Err(Failure(
+                    405,
+                    error::Error::new("only GET is supported, and POST for /intent"),
+                )),
+            ),
+        };
+        if let Err(Failure(_, e)) = &result {
+            span.fail(e);
+        }
+        let status = respond(request, content_type, result, client.lang());
+        span.attr("http.response.status_code", status);
+
+        if let Some(metrics) = client.metrics() {
+            metrics.observe_served(route, status, started.elapsed());
+        }
+    }
+}
+
+fn path(request: &Request) -> &str {
+    request.url().split('?').next().unwrap_or_default()
+}
+
+/// The token in `Authorization: Bearer ...`, if sent.
+fn bearer(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Refuses places outside the scopes of the client asking.
+fn in_scope(who: Option<&access::Client>, location: &api::Location) -> Result<(), Failure> {
+    match who {
+        Some(who) if !who.allows(location) => Err(denied(Denied::OutOfScope {
+            client: who.name.clone(),
+            location: location.to_string(),
+        })),
+        _ => Ok(()),
+    }
+}
+
+/// How a client wants its answer: in the language its `Accept-Language`
+/// prefers, and in the units `?units=` gives, e.g. `?units=imperial` or
+/// `?units=fahrenheit,km/h`, or else those usual in its region.
+struct Asked {
+    lang: Option<String>,
+    /// None when the client said nothing about units, so the numbers stay
+    /// as the provider sends them.
+    preferences: Option<Preferences>,
+}
+
+impl Asked {
+    fn of(request: &Request) -> Result<Asked, Failure> {
+        let locale = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Accept-Language"))
+            .and_then(|header| Locale::from_accept_language(header.value.as_str()));
+        let by_region = locale
+            .as_ref()
+            .filter(|locale| locale.region.is_some())
+            .map(Locale::preferences);
+        let units = query(request)
+            .into_iter()
+            .find(|(key, _)| key == "units")
+            .map(|(_, units)| units);
+        let preferences = match units {
+            Some(units) => Some(
+                by_region
+                    .unwrap_or_else(units::preferences)
+                    .with_units(&units)
+                    .map_err(bad_request)?,
+            ),
+            None => by_region,
+        };
+        Ok(Asked {
+            lang: locale.and_then(|locale| locale.language),
+            preferences,
+        })
+    }
+}
+
+/// A report with its numbers in the units asked for, and which those are.
+#[derive(Serialize)]
+struct Converted<T> {
+    #[serde(flatten)]
+    report: T,
+    units: Units,
+}
+
+/// To two decimals, as the provider sends them.
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn convert_wind(wind: &mut api::Wind, preferences: Preferences) {
+    wind.speed = round(preferences.wind.from_mps(wind.speed));
+    wind.gust = wind.gust.map(|gust| round(preferences.wind.from_mps(gust)));
+}
+
+fn convert_weather(mut weather: WeatherResponse, preferences: Preferences) -> WeatherResponse {
+    let temperature = |celsius| round(preferences.temperature.from_celsius(celsius));
+    weather.main.temp = temperature(weather.main.temp);
+    weather.main.feels_like = temperature(weather.main.feels_like);
+    convert_wind(&mut weather.wind, preferences);
+    weather
+}
+
+fn convert_forecast(mut forecast: ForecastResponse, preferences: Preferences) -> ForecastResponse {
+    let temperature = |celsius| round(preferences.temperature.from_celsius(celsius));
+    for entry in &mut forecast.list {
+        entry.main.temp = temperature(entry.main.temp);
+        entry.main.temp_min = temperature(entry.main.temp_min);
+        entry.main.temp_max = temperature(entry.main.temp_max);
+        if let Some(wind) = &mut entry.wind {
+            convert_wind(wind, preferences);
+        }
+    }
+    forecast
+}
+
+fn query(request: &Request) -> Vec<(String, String)> {
+    reqwest::Url::parse(&format!("http://localhost{}", request.url()))
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default()
+}
+
+/// `?city=Paris&country=FR`, `?coords=48.85,2.35` or `?lat=48.85&lon=2.35`.
+fn location(request: &Request) -> Result<api::Location, Failure> {
+    let query = query(request);
+    parse_location(|name| {
+        query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    })
+    .map_err(bad_request)
+}
+
+/// A place from `city` and `country`, `coords`, or `lat` and `lon`, as
+/// `param` looks them up.
+pub(crate) fn parse_location<'a>(
+    param: impl Fn(&str) -> Option<&'a str>,
+) -> Result<api::Location, String> {
+    if let Some(text) = param("coords") {
+        let coord = coords::parse(text).map_err(|e| e.to_string())?;
+        return Ok(api::Location::Coordinates(coord));
+    }
+    if let (Some(lat), Some(lon)) = (param("lat"), param("lon")) {
+        let coord = coords::parse(&format!("{},{}", lat, lon)).map_err(|e| e.to_string())?;
+        return Ok(api::Location::Coordinates(coord));
+    }
+    match (param("city"), param("country")) {
+        (Some(city), Some(country)) => {
+            let country_code = countries::resolve(country).map_err(|e| e.to_string())?;
+            Ok(api::Location::Place {
+                city: city.to_string(),
+                country_code: country_code.to_string(),
+            })
+        }
+        _ => Err(String::from(
+            "give city and country, coords, or lat and lon",
+        )),
+    }
+}
+
+/// A place from a JSON object with the same fields, which may be strings
+/// or numbers, e.g. `{"lat": 48.85, "lon": 2.35}`.
+pub(crate) fn location_from_json(
+    fields: &BTreeMap<String, Value>,
+) -> Result<api::Location, String> {
+    let text: BTreeMap<&str, String> = fields
+        .iter()
+        .filter_map(|(name, value)| {
+            let text = match value {
+                Value::String(text) => text.clone(),
+                Value::Number(number) => number.to_string(),
+                _ => return None,
+            };
+            Some((name.as_str(), text))
+        })
+        .collect();
+    parse_location(|name| text.get(name).map(String::as_str))
+}
+
+/// Provider failures are the gateway's fault, except requests it rejected.
+fn upstream(e: error::Error) -> Failure {
+    let status = match e.source_as::<api::Error>() {
+        Some(api::Error::Api { status: 404, .. }) => 404,
+        Some(api::Error::RateLimited { .. }) => 429,
+        Some(api::Error::CircuitOpen { .. }) => 503,
+        Some(api::Error::Unsupported(_)) => 501,
+        _ => 502,
+    };
+    Failure(status, e)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, Failure> {
+    let _span = telemetry::span("render");
+    serde_json::to_string(value).map_err(|e| Failure(500, error::Error::new(e)))
+}
+
+fn weather(
+    request: &Request,
+    who: Option<&access::Client>,
+    asked: &Asked,
+    client: &api::Client,
+) -> Result<String, Failure> {
+    let location = location(request)?;
+    in_scope(who, &location)?;
+    let weather = current_weather(&location, client).map_err(upstream)?;
+    if let Some(metrics) = client.metrics() {
+        metrics.observe_weather(&location.to_string(), &weather);
+    }
+    match asked.preferences {
+        Some(preferences) => to_json(&Converted {
+            report: convert_weather(weather, preferences),
+            units: Units::of(preferences),
+        }),
+        None => to_json(&weather),
+    }
+}
+
+fn forecast(
+    request: &Request,
+    who: Option<&access::Client>,
+    asked: &Asked,
+    client: &api::Client,
+) -> Result<String, Failure> {
+    let location = location(request)?;
+    in_scope(who, &location)?;
+    let forecast = client
+        .forecast(&location)
+        .context(format!("fetching the forecast from {}", api::PROVIDER))
+        .with_context(|| format!("serving the forecast for {}", location))
+        .map_err(upstream)?;
+    match asked.preferences {
+        Some(preferences) => to_json(&Converted {
+            report: convert_forecast(forecast, preferences),
+            units: Units::of(preferences),
+        }),
+        None => to_json(&forecast),
+    }
+}
+
+/// What a voice assistant asks `/intent`, e.g.
+/// `{"question": "rain", "city": "Paris", "country": "FR"}`.
+#[derive(Deserialize)]
+struct Intent {
+    question: Question,
+    #[serde(flatten)]
+    location: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize)]
+struct Speech {
+    speech: String,
+}
+
+fn intent(
+    request: &mut Request,
+    who: Option<&access::Client>,
+    asked: &Asked,
+    client: &api::Client,
+) -> Result<String, Failure> {
+    let too_large = || {
+        Failure(
+            413,
+            error::Error::new(format!("the body is over {} bytes", MAX_INTENT_BYTES)),
+        )
+    };
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > MAX_INTENT_BYTES)
+    {
+        return Err(too_large());
+    }
+    let mut body = String::new();
+    Read::take(request.as_reader(), MAX_INTENT_BYTES + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| bad_request(e.to_string()))?;
+    if body.len() as u64 > MAX_INTENT_BYTES {
+        return Err(too_large());
+    }
+    let intent: Intent = serde_json::from_str(&body).map_err(|e| {
+        Failure(
+            400,
+            error::Error::new(e)
+                .hint(r#"send e.g. {"question": "rain", "city": "Paris", "country": "FR"}"#),
+        )
+    })?;
+    let location = location_from_json(&intent.location).map_err(bad_request)?;
+    in_scope(who, &location)?;
+    let preferences = asked.preferences.unwrap_or_else(units::preferences);
+
+    let speech = if intent.question.needs_forecast() {
+        let forecast = client
+            .forecast(&location)
+            .context(format!("fetching the forecast from {}", api::PROVIDER))
+            .with_context(|| format!("answering for {}", location))
+            .map_err(upstream)?;
+        speech::ahead(intent.question, &forecast, now(), preferences)
+    } else {
+        let weather = current_weather(&location, client).map_err(upstream)?;
+        speech::current(intent.question, &weather, preferences)
+    };
+    to_json(&Speech { speech })
+}
+
+fn metrics(client: &api::Client) -> String {
+    let quota = client.usage().and_then(|tracker| {
+        let usage = tracker.load().ok()?;
+        Some(Quota {
+            used: usage.count(chrono::Utc::now().date_naive(), api::PROVIDER),
+            limit: tracker.daily_limit(),
+        })
+    });
+    client
+        .metrics()
+        .map(|metrics| metrics.render(quota))
+        .unwrap_or_default()
+}
+
+/// Sends the response, saying which language descriptions are in when
+/// the provider was asked for one, and returns its status.
+fn respond(
+    request: Request,
+    content_type: &str,
+    result: Result<String, Failure>,
+    lang: Option<String>,
+) -> u16 {
+    let mut retry_after = None;
+    let (status, body) = match result {
+        Ok(body) => (200, body),
+        Err(Failure(status, e)) => {
+            if let Some(Denied::RateLimited {
+                retry_after: secs, ..
+            }) = e.source_as()
+            {
+                retry_after = Some(*secs);
+            }
+            (
+                status,
+                serde_json::to_string(&ErrorBody::of(&e)).unwrap_or_default(),
+            )
+        }
+    };
+
+    let content_type =
+        Header::from_bytes("Content-Type", content_type).expect("static header is valid");
+    let mut response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(content_type);
+    if let Some(lang) = lang {
+        if let Ok(header) = Header::from_bytes("Content-Language", lang) {
+            response.add_header(header);
+        }
+    }
+    if let Some(secs) = retry_after {
+        response.add_header(
+            Header::from_bytes("Retry-After", secs.to_string()).expect("digits are a valid header"),
+        );
+    }
+    if let Err(e) = request.respond(response) {
+        eprintln!("Warning: could not send response: {}", e);
+    }
+    status
+}
@@ -0,0 +1,48 @@
+//! Bundles for `snapshot`: the reports fetched for some places, saved to
+//! one file that someone else can view offline with the same renderers.
+
+use serde::{Deserialize, Serialize};
+use std::{io, path::Path};
+
+use crate::api::{Alert, ForecastResponse, WeatherResponse};
+use crate::storage::{self, Schema};
+
+pub const SCHEMA: Schema = Schema {
+    name: "snapshot",
+    migrations: &[],
+};
+
+/// Stored as JSON whatever the name, but this is the one to use.
+pub const EXTENSION: &str = "wsnap";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    /// Unix time the reports were fetched.
+    pub taken_at: i64,
+    pub places: Vec<Place>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Place {
+    pub weather: WeatherResponse,
+    /// None when it could not be fetched.
+    #[serde(default)]
+    pub forecast: Option<ForecastResponse>,
+    /// None when they could not be fetched, as without One Call; empty when
+    /// there were none.
+    #[serde(default)]
+    pub alerts: Option<Vec<Alert>>,
+}
+
+pub fn save(path: &Path, snapshot: &Snapshot) -> Result<(), storage::Error> {
+    storage::save(path, &SCHEMA, snapshot)
+}
+
+pub fn load(path: &Path) -> Result<Snapshot, storage::Error> {
+    storage::load(path, &SCHEMA)?.ok_or_else(|| {
+        storage::Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist", path.display()),
+        ))
+    })
+}
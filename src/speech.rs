@@ -0,0 +1,197 @@
+//! Answers to spoken weather questions as one or two plain sentences, for
+//! voice assistants to read out. Numbers are whole and units are spelled
+//! out, since "14.6°C" reads badly aloud.
+
+use chrono::{DateTime, Days};
+use serde::Deserialize;
+
+use crate::api::{ForecastResponse, WeatherResponse};
+use crate::display;
+use crate::forecast;
+use crate::units::{self, Preferences, SpeedUnit, COMPASS_POINTS};
+
+/// How far ahead `Rain` looks.
+pub const RAIN_HOURS: u32 = 12;
+/// Degrees between the temperature and what it feels like before the
+/// weather answer mentions both.
+const FEELS_APART: f64 = 2.0;
+/// Chance of rain from which the answer for tomorrow mentions it.
+const RAIN_WORTH_SAYING: f64 = 0.3;
+
+/// `COMPASS_POINTS` as they are said.
+const SPOKEN_POINTS: [&str; 16] = [
+    "north",
+    "north-northeast",
+    "northeast",
+    "east-northeast",
+    "east",
+    "east-southeast",
+    "southeast",
+    "south-southeast",
+    "south",
+    "south-southwest",
+    "southwest",
+    "west-southwest",
+    "west",
+    "west-northwest",
+    "northwest",
+    "north-northwest",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Question {
+    /// What it is like out now.
+    Weather,
+    Temperature,
+    Wind,
+    /// Whether it will rain in the next `RAIN_HOURS`.
+    Rain,
+    Tomorrow,
+}
+
+impl Question {
+    /// Whether the answer comes from the forecast rather than the current
+    /// weather.
+    pub fn needs_forecast(self) -> bool {
+        matches!(self, Question::Rain | Question::Tomorrow)
+    }
+}
+
+fn degrees(celsius: f64, preferences: Preferences) -> String {
+    format!("{:.0}", preferences.temperature.from_celsius(celsius))
+}
+
+fn speed(mps: f64, preferences: Preferences) -> String {
+    let unit = match preferences.wind {
+        SpeedUnit::MetersPerSecond => "meters per second",
+        SpeedUnit::KilometersPerHour => "kilometers per hour",
+        SpeedUnit::MilesPerHour => "miles per hour",
+        SpeedUnit::Knots => "knots",
+    };
+    format!("{:.0} {}", preferences.wind.from_mps(mps), unit)
+}
+
+fn direction(degrees: f64) -> &'static str {
+    let point = units::compass_point(degrees);
+    COMPASS_POINTS
+        .iter()
+        .position(|p| *p == point)
+        .map_or(point, |i| SPOKEN_POINTS[i])
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The answer to `Weather`, `Temperature` or `Wind`.
+pub fn current(question: Question, weather: &WeatherResponse, preferences: Preferences) -> String {
+    let city = &weather.name;
+    let temp = degrees(weather.main.temp, preferences);
+    let feels = degrees(weather.main.feels_like, preferences);
+    let wind = &weather.wind;
+    let breeze = units::beaufort_description(units::beaufort(wind.speed));
+    let from = wind
+        .deg
+        .map(|deg| format!(" from the {}", direction(deg)))
+        .unwrap_or_default();
+
+    match question {
+        Question::Temperature => format!(
+            "It's {} degrees in {} right now, feeling like {}.",
+            temp, city, feels
+        ),
+        Question::Wind => {
+            let gusts = wind
+                .gust
+                .map(|gust| format!(", with gusts up to {}", speed(gust, preferences)))
+                .unwrap_or_default();
+            format!(
+                "The wind in {} is {}{}, a {}{}.",
+                city,
+                speed(wind.speed, preferences),
+                from,
+                breeze,
+                gusts
+            )
+        }
+        _ => {
+            let sky = weather
+                .weather
+                .first()
+                .map(|w| format!(" with {}", w.description))
+                .unwrap_or_default();
+            let feels = if (weather.main.feels_like - weather.main.temp).abs() >= FEELS_APART {
+                format!(", though it feels like {}", feels)
+            } else {
+                String::new()
+            };
+            format!(
+                "In {} it's {} degrees{}{}, and a {}{}.",
+                city, temp, sky, feels, breeze, from
+            )
+        }
+    }
+}
+
+/// The answer to `Rain` or `Tomorrow` at `now`.
+pub fn ahead(
+    question: Question,
+    forecast: &ForecastResponse,
+    now: i64,
+    preferences: Preferences,
+) -> String {
+    let city = &forecast.city.name;
+    let offset = display::utc_offset(forecast.city.timezone);
+
+    if question == Question::Rain {
+        return match forecast::first_rain(forecast, now, RAIN_HOURS) {
+            Some(rain) => {
+                let at = DateTime::from_timestamp(rain.entry.dt, 0)
+                    .unwrap_or_default()
+                    .with_timezone(&offset)
+                    .format(preferences.clock.format());
+                format!(
+                    "{} is expected in {} around {}.",
+                    capitalized(&rain.description),
+                    city,
+                    at
+                )
+            }
+            None => format!(
+                "No rain is expected in {} in the next {} hours.",
+                city, RAIN_HOURS
+            ),
+        };
+    }
+
+    let tomorrow = DateTime::from_timestamp(now, 0)
+        .map(|time| time.with_timezone(&offset).date_naive())
+        .and_then(|today| today.checked_add_days(Days::new(1)));
+    let days = forecast::days(forecast, offset);
+    let Some(day) = days.iter().find(|day| Some(day.date) == tomorrow) else {
+        return format!("There's no forecast for tomorrow in {} yet.", city);
+    };
+    let sky = day
+        .condition
+        .map(|w| format!("{}, with ", w.description))
+        .unwrap_or_default();
+    let mut answer = format!(
+        "Tomorrow in {}: {}a low of {} and a high of {} degrees.",
+        city,
+        sky,
+        degrees(day.min, preferences),
+        degrees(day.max, preferences)
+    );
+    if day.pop >= RAIN_WORTH_SAYING {
+        answer.push_str(&format!(
+            " There's a {:.0} percent chance of rain.",
+            day.pop * 100.0
+        ));
+    }
+    answer
+}
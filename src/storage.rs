@@ -0,0 +1,278 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    fmt,
+    fs::{self, File},
+    io,
+    path::Path,
+    process,
+};
+
+const VERSION_KEY: &str = "version";
+
+/// Upgrades a document in place from one version to the next.
+pub type Migration = fn(&mut Map<String, Value>);
+
+/// A persisted file layout and the steps needed to bring old files up to date.
+///
+/// Files written before versioning existed have no `version` key and are
+/// treated as version 1. `migrations[i]` upgrades version `i + 1` to `i + 2`,
+/// so adding a layout change means appending a step, never editing one.
+pub struct Schema {
+    pub name: &'static str,
+    pub migrations: &'static [Migration],
+}
+
+impl Schema {
+    pub fn current(&self) -> u64 {
+        self.migrations.len() as u64 + 1
+    }
+
+    /// Applies any pending migrations. Returns whether the document changed.
+    pub fn upgrade(&self, doc: &mut Map<String, Value>) -> Result<bool, Error> {
+        let found = doc
+            .get(VERSION_KEY)
+            .and_then(Value::as_u64)
+            .unwrap_or(1)
+            .max(1);
+
+        if found > self.current() {
+            return Err(Error::Newer {
+                schema: self.name,
+                found,
+                supported: self.current(),
+            });
+        }
+
+        for migration in &self.migrations[(found - 1) as usize..] {
+            migration(doc);
+        }
+        doc.insert(VERSION_KEY.to_string(), Value::from(self.current()));
+
+        Ok(found < self.current())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    fn of(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json" | crate::snapshot::EXTENSION) => Format::Json,
+            _ => Format::Toml,
+        }
+    }
+
+    fn parse(self, contents: &str) -> Result<Value, String> {
+        match self {
+            Format::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            Format::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn render(self, value: &Value) -> Result<String, String> {
+        match self {
+            Format::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            Format::Json => serde_json::to_string(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse(String),
+    Newer {
+        schema: &'static str,
+        found: u64,
+        supported: u64,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "could not access file: {}", e),
+            Error::Parse(e) => write!(f, "invalid file: {}", e),
+            Error::Newer {
+                schema,
+                found,
+                supported,
+            } => write!(
+                f,
+                "{} file is version {} but this build only understands up to {}",
+                schema, found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Reads a versioned file, migrating it to the current layout first.
+///
+/// `.json` files are read as JSON and everything else as TOML. An upgraded
+/// file is written back so the migration runs only once, and the original is
+/// kept next to it with a `.bak` extension.
+pub fn load<T: DeserializeOwned>(path: &Path, schema: &Schema) -> Result<Option<T>, Error> {
+    let format = Format::of(path);
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Io(e)),
+    };
+
+    let mut doc = match format.parse(&contents).map_err(Error::Parse)? {
+        Value::Object(doc) => doc,
+        _ => {
+            return Err(Error::Parse(String::from(
+                "expected a table at the top level",
+            )))
+        }
+    };
+
+    if schema.upgrade(&mut doc)? {
+        let upgraded = Value::Object(doc.clone());
+        fs::copy(path, path.with_extension("bak"))?;
+        write(path, &format.render(&upgraded).map_err(Error::Parse)?)?;
+    }
+
+    serde_json::from_value(Value::Object(doc))
+        .map(Some)
+        .map_err(|e| Error::Parse(e.to_string()))
+}
+
+/// Writes a file in the current layout of `schema`, creating parent directories.
+pub fn save<T: Serialize>(path: &Path, schema: &Schema, value: &T) -> Result<(), Error> {
+    let mut doc = match serde_json::to_value(value) {
+        Ok(Value::Object(doc)) => doc,
+        Ok(_) => {
+            return Err(Error::Parse(String::from(
+                "expected a table at the top level",
+            )))
+        }
+        Err(e) => return Err(Error::Parse(e.to_string())),
+    };
+    doc.insert(VERSION_KEY.to_string(), Value::from(schema.current()));
+
+    let contents = Format::of(path)
+        .render(&Value::Object(doc))
+        .map_err(Error::Parse)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write(path, &contents)?;
+    Ok(())
+}
+
+/// Loads, changes and saves a file while holding a lock next to it, so that
+/// two processes counting into it at once do not lose each other's counts.
+/// A file that cannot be read is kept with a `.bak` extension and `change`
+/// starts over from the default, rather than every later call failing too.
+pub fn update<T, R>(
+    path: &Path,
+    schema: &Schema,
+    change: impl FnOnce(&mut T) -> R,
+) -> Result<R, Error>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock = File::create(path.with_extension("lock"))?;
+    lock.lock()?;
+
+    let mut value = match load(path, schema) {
+        Ok(value) => value.unwrap_or_default(),
+        Err(Error::Parse(_)) => {
+            fs::rename(path, path.with_extension("bak"))?;
+            T::default()
+        }
+        Err(e) => return Err(e),
+    };
+    let changed = change(&mut value);
+    save(path, schema, &value)?;
+    Ok(changed)
+}
+
+/// Writes a sibling file and renames it over `path`, so that readers see
+/// the old contents or the new and never half of them. The sibling is named
+/// for this process, for when two write the same file.
+fn write(path: &Path, contents: &str) -> io::Result<()> {
+    let partial = path.with_extension(format!("{}.tmp", process::id()));
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path).inspect_err(|_| {
+        fs::remove_file(&partial).ok();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const COUNTS: Schema = Schema {
+        name: "counts",
+        migrations: &[],
+    };
+
+    #[derive(Serialize, Deserialize, Default)]
+    struct Counts {
+        calls: u32,
+    }
+
+    fn dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cli_weather-storage-{}-{}", name, process::id()))
+    }
+
+    #[test]
+    fn update_counts_from_every_thread() {
+        let dir = dir("threads");
+        let path = dir.join("counts.json");
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        update(&path, &COUNTS, |counts: &mut Counts| counts.calls += 1).unwrap();
+                    }
+                });
+            }
+        });
+        let counts: Counts = load(&path, &COUNTS).unwrap().unwrap();
+        assert_eq!(counts.calls, 40);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn update_sets_a_broken_file_aside() {
+        let dir = dir("broken");
+        let path = dir.join("counts.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "{\"calls\": 7, \"ver").unwrap();
+
+        let calls = update(&path, &COUNTS, |counts: &mut Counts| {
+            counts.calls += 1;
+            counts.calls
+        })
+        .unwrap();
+        assert_eq!(calls, 1);
+        assert_eq!(
+            fs::read_to_string(path.with_extension("bak")).unwrap(),
+            "{\"calls\": 7, \"ver"
+        );
+        let counts: Counts = load(&path, &COUNTS).unwrap().unwrap();
+        assert_eq!(counts.calls, 1);
+        fs::remove_dir_all(dir).ok();
+    }
+}
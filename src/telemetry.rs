@@ -0,0 +1,338 @@
+//! Optional OpenTelemetry spans for `serve` and `watch`, sent to a
+//! collector with OTLP over HTTP as JSON.
+//!
+//! Nothing is recorded until `init` is called, so the one-shot commands pay
+//! only for an empty check per span. Spans are batched until the outermost
+//! one ends, such as one served request or one `watch` refresh, and then
+//! sent in one request.
+
+use serde_json::{json, Value as Json};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The collector's base URL; spans go to `/v1/traces` under it.
+pub const ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// The full URL for spans, when it is not the usual path.
+pub const TRACES_ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+pub const SERVICE_NAME_VAR: &str = "OTEL_SERVICE_NAME";
+
+const SERVICE_NAME: &str = "cli_weather";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where spans should be sent: the environment, then the configured base URL.
+pub fn traces_endpoint(configured: Option<&str>) -> Option<String> {
+    let var = |name| {
+        std::env::var(name)
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+    };
+    var(TRACES_ENDPOINT_VAR).or_else(|| {
+        var(ENDPOINT_VAR)
+            .or_else(|| configured.map(String::from))
+            .map(|base| format!("{}/v1/traces", base.trim_end_matches('/')))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Internal,
+    /// Handling a request made to us.
+    Server,
+    /// A request to another service.
+    Client,
+}
+
+impl Kind {
+    fn code(self) -> u8 {
+        match self {
+            Kind::Internal => 1,
+            Kind::Server => 2,
+            Kind::Client => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<u16> for Value {
+    fn from(value: u16) -> Self {
+        Value::Int(value.into())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl Value {
+    fn to_json(&self) -> Json {
+        match self {
+            Value::Str(s) => json!({ "stringValue": s }),
+            // 64-bit integers are strings in OTLP's JSON encoding.
+            Value::Int(i) => json!({ "intValue": i.to_string() }),
+            Value::Bool(b) => json!({ "boolValue": b }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Finished {
+    trace_id: u128,
+    span_id: u64,
+    parent: Option<u64>,
+    name: String,
+    kind: Kind,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+    error: Option<String>,
+}
+
+impl Finished {
+    fn to_json(&self) -> Json {
+        let attributes: Vec<Json> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value.to_json() }))
+            .collect();
+        let status = match &self.error {
+            Some(message) => json!({ "code": 2, "message": message }),
+            None => json!({ "code": 0 }),
+        };
+        json!({
+            "traceId": format!("{:032x}", self.trace_id),
+            "spanId": format!("{:016x}", self.span_id),
+            "parentSpanId": self.parent.map(|id| format!("{:016x}", id)).unwrap_or_default(),
+            "name": self.name,
+            "kind": self.kind.code(),
+            "startTimeUnixNano": unix_nanos(self.start).to_string(),
+            "endTimeUnixNano": unix_nanos(self.end).to_string(),
+            "attributes": attributes,
+            "status": status,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Spans still open, innermost last.
+    open: Vec<(u128, u64)>,
+    finished: Vec<Finished>,
+    warned: bool,
+}
+
+#[derive(Debug)]
+pub struct Tracer {
+    http: reqwest::blocking::Client,
+    endpoint: String,
+    service_name: String,
+    state: Mutex<State>,
+}
+
+impl Tracer {
+    /// `OTEL_SERVICE_NAME` names the service, or else "cli_weather".
+    pub fn new(endpoint: &str) -> Tracer {
+        let service_name = std::env::var(SERVICE_NAME_VAR)
+            .ok()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| SERVICE_NAME.to_string());
+        Tracer {
+            http: reqwest::blocking::Client::builder()
+                .timeout(EXPORT_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            endpoint: endpoint.to_string(),
+            service_name,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sends the finished spans. A collector that cannot be reached is
+    /// reported once and never stops the program.
+    fn export(&self, spans: Vec<Finished>) {
+        let spans: Vec<Json> = spans.iter().map(Finished::to_json).collect();
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [{
+                        "key": "service.name",
+                        "value": { "stringValue": self.service_name },
+                    }],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": SERVICE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }],
+            }],
+        });
+
+        let result = self
+            .http
+            .post(&self.endpoint)
+            .json(&body)
+            .send()
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            let mut state = self.state();
+            if !state.warned {
+                state.warned = true;
+                eprintln!(
+                    "Warning: could not send traces to {}: {}",
+                    self.endpoint,
+                    e.without_url()
+                );
+            }
+        }
+    }
+}
+
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+/// Starts recording spans for the rest of the run. Only the first call has
+/// an effect.
+pub fn init(tracer: Tracer) {
+    let _ = TRACER.set(tracer);
+}
+
+#[derive(Debug)]
+struct Open {
+    trace_id: u128,
+    span_id: u64,
+    parent: Option<u64>,
+    name: String,
+    kind: Kind,
+    start: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+    error: Option<String>,
+}
+
+/// Ends, and is recorded, when dropped. Spans started while it is open
+/// become its children.
+#[derive(Debug)]
+pub struct Span(Option<Open>);
+
+pub fn span(name: &str) -> Span {
+    span_of_kind(name, Kind::Internal)
+}
+
+pub fn span_of_kind(name: &str, kind: Kind) -> Span {
+    let Some(tracer) = TRACER.get() else {
+        return Span(None);
+    };
+
+    let mut state = tracer.state();
+    let parent = state.open.last().copied();
+    let trace_id = parent.map_or_else(
+        || u128::from(random_id()) << 64 | u128::from(random_id()),
+        |(trace_id, _)| trace_id,
+    );
+    let span_id = random_id();
+    state.open.push((trace_id, span_id));
+
+    Span(Some(Open {
+        trace_id,
+        span_id,
+        parent: parent.map(|(_, id)| id),
+        name: name.to_string(),
+        kind,
+        start: SystemTime::now(),
+        attributes: Vec::new(),
+        error: None,
+    }))
+}
+
+impl Span {
+    /// Attribute names follow OpenTelemetry's conventions where one fits,
+    /// e.g. `http.response.status_code`.
+    pub fn attr(&mut self, key: &'static str, value: impl Into<Value>) {
+        if let Some(open) = &mut self.0 {
+            open.attributes.push((key, value.into()));
+        }
+    }
+
+    /// Marks the span as failed.
+    pub fn fail(&mut self, error: impl fmt::Display) {
+        if let Some(open) = &mut self.0 {
+            open.error = Some(error.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let (Some(open), Some(tracer)) = (self.0.take(), TRACER.get()) else {
+            return;
+        };
+
+        let batch = {
+            let mut state = tracer.state();
+            state.open.retain(|&(_, id)| id != open.span_id);
+            state.finished.push(Finished {
+                trace_id: open.trace_id,
+                span_id: open.span_id,
+                parent: open.parent,
+                name: open.name,
+                kind: open.kind,
+                start: open.start,
+                end: SystemTime::now(),
+                attributes: open.attributes,
+                error: open.error,
+            });
+            if state.open.is_empty() {
+                std::mem::take(&mut state.finished)
+            } else {
+                Vec::new()
+            }
+        };
+        if !batch.is_empty() {
+            tracer.export(batch);
+        }
+    }
+}
+
+/// Each `RandomState` is seeded differently, which is random enough for ids.
+fn random_id() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(unix_nanos(SystemTime::now()));
+    hasher.finish().max(1)
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+/// A `--format` string such as `{temp}{icon} {city}`, parsed once up front so
+/// typos are reported before any request is made. `{{` and `}}` produce
+/// literal braces.
+#[derive(Debug, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateError {
+    Unclosed {
+        offset: usize,
+    },
+    UnmatchedClose {
+        offset: usize,
+    },
+    Unknown {
+        name: String,
+        known: &'static [&'static str],
+    },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed { offset } => {
+                write!(f, "unclosed '{{' at position {}", offset)
+            }
+            TemplateError::UnmatchedClose { offset } => {
+                write!(
+                    f,
+                    "unmatched '}}' at position {} (write '}}}}' for a literal brace)",
+                    offset
+                )
+            }
+            TemplateError::Unknown { name, known } => {
+                write!(
+                    f,
+                    "unknown placeholder {{{}}}; expected one of: {}",
+                    name,
+                    known.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Escapes `text` so a template renders it verbatim.
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+impl Template {
+    pub fn parse(source: &str, known: &'static [&'static str]) -> Result<Template, TemplateError> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => literal.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => literal.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '}')) => break,
+                            Some((_, c)) => name.push(c),
+                            None => return Err(TemplateError::Unclosed { offset }),
+                        }
+                    }
+
+                    if !known.contains(&name.as_str()) {
+                        return Err(TemplateError::Unknown { name, known });
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(name));
+                }
+                '}' => return Err(TemplateError::UnmatchedClose { offset }),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Template { segments })
+    }
+
+    /// Fills every placeholder with the value `lookup` returns for its name.
+    pub fn render<F>(&self, lookup: F) -> String
+    where
+        F: Fn(&str) -> String,
+    {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Placeholder(name) => lookup(name),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,439 @@
+//! Colours for everything printed, by what the text means rather than how
+//! it looks, so a theme can restyle the whole program.
+//!
+//! Escape codes are left out entirely when `NO_COLOR` is set or stdout is
+//! not a terminal (unless `CLICOLOR_FORCE` is set); `colored` checks this.
+
+use colored::*;
+use std::{collections::BTreeMap, fmt, sync::OnceLock};
+
+/// What a piece of text is, for picking its style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Place names above a report.
+    Heading,
+    /// Numbers in a report.
+    Value,
+    /// Secondary details such as dates and coordinates.
+    Muted,
+    Link,
+    Prompt,
+    Error,
+    Warning,
+    /// Temperature bands: below 0, 10, 20 and 30 °C, and above.
+    Freezing,
+    Cold,
+    Mild,
+    Warm,
+    Hot,
+    /// Condition descriptions.
+    Clear,
+    Cloudy,
+    Wet,
+    /// Sunrise and sunset, and the sun in `--art`.
+    Sun,
+    Night,
+    /// The moon and stars in `--art`.
+    Moon,
+    Cloud,
+    Rain,
+    Snow,
+    /// UV index risk levels, low to extreme.
+    UvLow,
+    UvModerate,
+    UvHigh,
+    UvVeryHigh,
+    UvExtreme,
+}
+
+impl Role {
+    pub const ALL: [Role; 26] = [
+        Role::Heading,
+        Role::Value,
+        Role::Muted,
+        Role::Link,
+        Role::Prompt,
+        Role::Error,
+        Role::Warning,
+        Role::Freezing,
+        Role::Cold,
+        Role::Mild,
+        Role::Warm,
+        Role::Hot,
+        Role::Clear,
+        Role::Cloudy,
+        Role::Wet,
+        Role::Sun,
+        Role::Night,
+        Role::Moon,
+        Role::Cloud,
+        Role::Rain,
+        Role::Snow,
+        Role::UvLow,
+        Role::UvModerate,
+        Role::UvHigh,
+        Role::UvVeryHigh,
+        Role::UvExtreme,
+    ];
+
+    /// The key used under `[theme.colors]` in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Heading => "heading",
+            Role::Value => "value",
+            Role::Muted => "muted",
+            Role::Link => "link",
+            Role::Prompt => "prompt",
+            Role::Error => "error",
+            Role::Warning => "warning",
+            Role::Freezing => "freezing",
+            Role::Cold => "cold",
+            Role::Mild => "mild",
+            Role::Warm => "warm",
+            Role::Hot => "hot",
+            Role::Clear => "clear",
+            Role::Cloudy => "cloudy",
+            Role::Wet => "wet",
+            Role::Sun => "sun",
+            Role::Night => "night",
+            Role::Moon => "moon",
+            Role::Cloud => "cloud",
+            Role::Rain => "rain",
+            Role::Snow => "snow",
+            Role::UvLow => "uv_low",
+            Role::UvModerate => "uv_moderate",
+            Role::UvHigh => "uv_high",
+            Role::UvVeryHigh => "uv_very_high",
+            Role::UvExtreme => "uv_extreme",
+        }
+    }
+
+    /// The band `temp` in °C falls into.
+    pub fn for_temperature(temp: f64) -> Role {
+        if temp < 0.0 {
+            Role::Freezing
+        } else if temp < 10.0 {
+            Role::Cold
+        } else if temp < 20.0 {
+            Role::Mild
+        } else if temp < 30.0 {
+            Role::Warm
+        } else {
+            Role::Hot
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub dimmed: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    const fn color(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            bold: false,
+            dimmed: false,
+            underline: false,
+        }
+    }
+
+    const fn bold(mut self) -> Style {
+        self.bold = true;
+        self
+    }
+
+    const PLAIN: Style = Style {
+        color: None,
+        bold: false,
+        dimmed: false,
+        underline: false,
+    };
+
+    /// Parses words such as `"bold bright green"`, `"dimmed"` or
+    /// `"underline #268bd2"`.
+    pub fn parse(spec: &str) -> Result<Style, String> {
+        let mut style = Style::PLAIN;
+        let mut color = Vec::new();
+
+        for word in spec.split_whitespace() {
+            match word.to_lowercase().as_str() {
+                "bold" => style.bold = true,
+                "dimmed" | "dim" => style.dimmed = true,
+                "underline" => style.underline = true,
+                "plain" | "none" => {}
+                hex if hex.starts_with('#') => style.color = Some(parse_hex(hex)?),
+                name => color.push(name.to_string()),
+            }
+        }
+        if !color.is_empty() {
+            let name = color.join(" ");
+            style.color = Some(
+                name.parse()
+                    .map_err(|_| format!("unknown color '{}'", name))?,
+            );
+        }
+        Ok(style)
+    }
+
+    pub fn apply(&self, text: &str) -> ColoredString {
+        let mut painted = match self.color {
+            Some(color) => text.color(color),
+            None => text.normal(),
+        };
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        painted
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Color, String> {
+    parse_rgb(hex).map(|(r, g, b)| Color::TrueColor { r, g, b })
+}
+
+/// The channels of a `#rrggbb` color.
+pub(crate) fn parse_rgb(hex: &str) -> Result<(u8, u8, u8), String> {
+    let digits = hex.trim_start_matches('#');
+    let channel = |i: usize| {
+        digits
+            .get(i..i + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+    };
+    match (digits.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok((r, g, b)),
+        _ => Err(format!("'{}' is not a #rrggbb color", hex)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    Default,
+    Solarized,
+    Monochrome,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 3] = [Builtin::Default, Builtin::Solarized, Builtin::Monochrome];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Default => "default",
+            Builtin::Solarized => "solarized",
+            Builtin::Monochrome => "monochrome",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Builtin, String> {
+        Builtin::ALL
+            .into_iter()
+            .find(|builtin| builtin.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Builtin::ALL.iter().map(|b| b.name()).collect();
+                format!("unknown theme '{}'; choose {}", name, names.join(", "))
+            })
+    }
+
+    fn style(self, role: Role) -> Style {
+        match self {
+            Builtin::Default => default_style(role),
+            Builtin::Solarized => solarized_style(role),
+            Builtin::Monochrome => monochrome_style(role),
+        }
+    }
+}
+
+fn default_style(role: Role) -> Style {
+    match role {
+        Role::Heading => Style::color(Color::BrightWhite).bold(),
+        Role::Value => Style::color(Color::Green).bold(),
+        Role::Muted => Style {
+            dimmed: true,
+            ..Style::PLAIN
+        },
+        Role::Link => Style {
+            underline: true,
+            ..Style::PLAIN
+        },
+        Role::Prompt => Style::color(Color::White),
+        Role::Error => Style::color(Color::Red),
+        Role::Warning => Style::color(Color::Yellow).bold(),
+        Role::Freezing => Style::color(Color::Cyan),
+        Role::Cold => Style::color(Color::Blue),
+        Role::Mild => Style::color(Color::BrightGreen),
+        Role::Warm => Style::color(Color::Yellow),
+        Role::Hot => Style::color(Color::Red),
+        Role::Clear => Style::color(Color::BrightYellow),
+        Role::Cloudy => Style::color(Color::BrightBlue),
+        Role::Wet => Style::color(Color::BrightCyan),
+        Role::Sun => Style::color(Color::Yellow).bold(),
+        Role::Night => Style::color(Color::Blue),
+        Role::Moon => Style::color(Color::BrightWhite),
+        Role::Cloud => Style::color(Color::White),
+        Role::Rain => Style::color(Color::BrightBlue),
+        Role::Snow => Style::color(Color::BrightWhite).bold(),
+        Role::UvLow => Style::color(Color::Green),
+        Role::UvModerate => Style::color(Color::Yellow),
+        Role::UvHigh => Style::color(Color::BrightRed),
+        Role::UvVeryHigh => Style::color(Color::Red).bold(),
+        Role::UvExtreme => Style::color(Color::Magenta).bold(),
+    }
+}
+
+/// Ethan Schoonover's palette, in true colour.
+fn solarized_style(role: Role) -> Style {
+    let rgb = |r, g, b| Style::color(Color::TrueColor { r, g, b });
+    let base01 = rgb(0x58, 0x6e, 0x75);
+    let base0 = rgb(0x83, 0x94, 0x96);
+    let base1 = rgb(0x93, 0xa1, 0xa1);
+    let yellow = rgb(0xb5, 0x89, 0x00);
+    let orange = rgb(0xcb, 0x4b, 0x16);
+    let red = rgb(0xdc, 0x32, 0x2f);
+    let violet = rgb(0x6c, 0x71, 0xc4);
+    let blue = rgb(0x26, 0x8b, 0xd2);
+    let cyan = rgb(0x2a, 0xa1, 0x98);
+    let green = rgb(0x85, 0x99, 0x00);
+    let magenta = rgb(0xd3, 0x36, 0x82);
+
+    match role {
+        Role::Heading => base1.bold(),
+        Role::Value => green.bold(),
+        Role::Muted => base01,
+        Role::Link => Style {
+            underline: true,
+            ..blue
+        },
+        Role::Prompt => base0,
+        Role::Error => red,
+        Role::Warning => orange.bold(),
+        Role::Freezing => cyan,
+        Role::Cold => blue,
+        Role::Mild => green,
+        Role::Warm => yellow,
+        Role::Hot => red,
+        Role::Clear => yellow,
+        Role::Cloudy => base0,
+        Role::Wet => blue,
+        Role::Sun => yellow.bold(),
+        Role::Night => violet,
+        Role::Moon => base1,
+        Role::Cloud => base0,
+        Role::Rain => blue,
+        Role::Snow => base1.bold(),
+        Role::UvLow => green,
+        Role::UvModerate => yellow,
+        Role::UvHigh => orange,
+        Role::UvVeryHigh => red.bold(),
+        Role::UvExtreme => magenta.bold(),
+    }
+}
+
+/// No colours, only weight, for light terminals and screenshots.
+fn monochrome_style(role: Role) -> Style {
+    match role {
+        Role::Heading
+        | Role::Value
+        | Role::Error
+        | Role::Warning
+        | Role::Sun
+        | Role::Snow
+        | Role::UvHigh
+        | Role::UvVeryHigh
+        | Role::UvExtreme => Style::PLAIN.bold(),
+        Role::Muted | Role::Night => Style {
+            dimmed: true,
+            ..Style::PLAIN
+        },
+        Role::Link => Style {
+            underline: true,
+            ..Style::PLAIN
+        },
+        _ => Style::PLAIN,
+    }
+}
+
+#[derive(Debug)]
+pub struct ThemeError(String);
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// A built-in theme with per-role overrides from the config file.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    base: Builtin,
+    overrides: BTreeMap<Role, Style>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            base: Builtin::Default,
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// `colors` maps role names to styles, e.g. `hot = "bold #ff5f00"`.
+    pub fn new(name: &str, colors: &BTreeMap<String, String>) -> Result<Theme, ThemeError> {
+        let base = Builtin::from_name(name).map_err(ThemeError)?;
+        let mut overrides = BTreeMap::new();
+
+        for (key, spec) in colors {
+            let role = Role::ALL
+                .into_iter()
+                .find(|role| role.name() == key)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = Role::ALL.iter().map(|r| r.name()).collect();
+                    ThemeError(format!(
+                        "unknown color '{}'; known ones are {}",
+                        key,
+                        names.join(", ")
+                    ))
+                })?;
+            let style = Style::parse(spec).map_err(|e| ThemeError(format!("{}: {}", key, e)))?;
+            overrides.insert(role, style);
+        }
+
+        Ok(Theme { base, overrides })
+    }
+
+    pub fn style(&self, role: Role) -> Style {
+        self.overrides
+            .get(&role)
+            .copied()
+            .unwrap_or_else(|| self.base.style(role))
+    }
+}
+
+static CURRENT: OnceLock<Theme> = OnceLock::new();
+
+/// Sets the theme for the rest of the run. Only the first call has an effect.
+pub fn init(theme: Theme) {
+    let _ = CURRENT.set(theme);
+}
+
+pub fn current() -> &'static Theme {
+    CURRENT.get_or_init(Theme::default)
+}
+
+/// Styles `text` for `role` with the current theme.
+pub fn paint<S: AsRef<str>>(role: Role, text: S) -> ColoredString {
+    current().style(role).apply(text.as_ref())
+}
@@ -0,0 +1,64 @@
+//! Tints the terminal background after a report, blue when it is cold and
+//! amber when it is hot, with OSC 11 or a command of your own. Off unless
+//! `tint.enabled` is set.
+
+use crate::config::TintSettings;
+use crate::theme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tint {
+    Cold,
+    Hot,
+    /// Neither; the terminal's own background is put back.
+    Neutral,
+}
+
+impl Tint {
+    pub fn of(temp: f64, settings: &TintSettings) -> Tint {
+        if temp < settings.cold_below {
+            Tint::Cold
+        } else if temp > settings.hot_above {
+            Tint::Hot
+        } else {
+            Tint::Neutral
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tint::Cold => "cold",
+            Tint::Hot => "hot",
+            Tint::Neutral => "neutral",
+        }
+    }
+
+    /// The configured `#rrggbb` background; None for `Neutral`.
+    pub fn color(self, settings: &TintSettings) -> Option<&str> {
+        match self {
+            Tint::Cold => Some(&settings.cold_color),
+            Tint::Hot => Some(&settings.hot_color),
+            Tint::Neutral => None,
+        }
+    }
+}
+
+/// OSC 11 setting the background to `color`, or OSC 111 resetting it.
+/// Terminals that do not know them ignore them.
+pub fn osc(color: Option<&str>) -> Result<String, String> {
+    let Some(color) = color else {
+        return Ok(String::from("\x1b]111\x07"));
+    };
+    let (r, g, b) = theme::parse_rgb(color)?;
+    Ok(format!("\x1b]11;rgb:{:02x}/{:02x}/{:02x}\x07", r, g, b))
+}
+
+/// Checks both colors, naming the one that is wrong.
+pub fn validate(settings: &TintSettings) -> Result<(), String> {
+    for (name, color) in [
+        ("cold_color", &settings.cold_color),
+        ("hot_color", &settings.hot_color),
+    ] {
+        theme::parse_rgb(color).map_err(|e| format!("tint.{}: {}", name, e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! The short tour offered after `login`: a guided run through a first
+//! report, the forecast and the units, ending with a config written for
+//! them. Each step either asks a question or does something, and what
+//! comes next depends on the answer or on whether it worked.
+
+use crate::units::{Clock, Preferences, SpeedUnit, TempUnit};
+
+pub const METRIC: Preferences = Preferences {
+    temperature: TempUnit::Celsius,
+    wind: SpeedUnit::KilometersPerHour,
+    clock: Clock::TwentyFourHour,
+};
+
+pub const IMPERIAL: Preferences = Preferences {
+    temperature: TempUnit::Fahrenheit,
+    wind: SpeedUnit::MilesPerHour,
+    clock: Clock::TwelveHour,
+};
+
+/// How many hours ahead the forecast step looks for rain.
+pub const RAIN_HOURS: u32 = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Asks whether to take the tour at all.
+    Offer,
+    /// Asks for a place to try, as a city and country or coordinates.
+    Place,
+    /// Shows the current weather at the place typed.
+    Weather(String),
+    /// Asks whether to look at the forecast there.
+    Forecast(String),
+    /// Shows whether it will rain there soon.
+    Rain(String),
+    /// Asks for metric or imperial units.
+    Units,
+    /// Asks whether to write the config with these units.
+    Config(Preferences),
+    /// Writes the config.
+    Write(Preferences),
+    Done,
+}
+
+/// What happened at a step: the answer to its question, or whether what
+/// it did worked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input<'a> {
+    Answer(&'a str),
+    Ran(bool),
+}
+
+/// Yes or no, with Enter taking `default`; None for anything else.
+pub fn yes(answer: &str, default: bool) -> Option<bool> {
+    match answer.trim().to_lowercase().as_str() {
+        "" => Some(default),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+pub struct Tour {
+    pub step: Step,
+    /// The units in use, kept when the units question is skipped.
+    units: Preferences,
+}
+
+impl Tour {
+    pub fn new(units: Preferences) -> Tour {
+        Tour {
+            step: Step::Offer,
+            units,
+        }
+    }
+
+    /// What to ask at this step; None when it does something instead.
+    pub fn question(&self) -> Option<String> {
+        let question = match &self.step {
+            Step::Offer => String::from("Take a short tour of cli_weather? [Y/n] "),
+            Step::Place => {
+                String::from("Try a place, e.g. \"Paris, FR\" or \"48.85,2.35\" (Enter skips): ")
+            }
+            Step::Forecast(place) => format!(
+                "Check whether it will rain in {} in the next {} hours? [Y/n] ",
+                place, RAIN_HOURS
+            ),
+            Step::Units => format!(
+                "Metric (°C, km/h) or imperial (°F, mph) units? [m/i, Enter keeps {}, {}] ",
+                self.units.temperature.symbol(),
+                self.units.wind.symbol()
+            ),
+            Step::Config(_) => String::from("Save these settings to the config file? [Y/n] "),
+            Step::Weather(_) | Step::Rain(_) | Step::Write(_) | Step::Done => return None,
+        };
+        Some(question)
+    }
+
+    /// Moves on from the current step. Answers that do not fit the
+    /// question stay on it, so it is asked again.
+    pub fn advance(&mut self, input: Input) {
+        let step = std::mem::replace(&mut self.step, Step::Done);
+        self.step = match (step, input) {
+            (Step::Offer, Input::Answer(answer)) => match yes(answer, true) {
+                Some(true) => Step::Place,
+                Some(false) => Step::Done,
+                None => Step::Offer,
+            },
+            (Step::Place, Input::Answer(answer)) if answer.trim().is_empty() => Step::Units,
+            (Step::Place, Input::Answer(answer)) => Step::Weather(answer.trim().to_string()),
+            // A place that cannot be found is asked for again.
+            (Step::Weather(place), Input::Ran(true)) => Step::Forecast(place),
+            (Step::Weather(_), Input::Ran(false)) => Step::Place,
+            (Step::Forecast(place), Input::Answer(answer)) => match yes(answer, true) {
+                Some(true) => Step::Rain(place),
+                Some(false) => Step::Units,
+                None => Step::Forecast(place),
+            },
+            (Step::Rain(_), Input::Ran(_)) => Step::Units,
+            (Step::Units, Input::Answer(answer)) => match answer.trim().to_lowercase().as_str() {
+                "" => Step::Config(self.units),
+                "m" | "metric" => Step::Config(METRIC),
+                "i" | "imperial" => Step::Config(IMPERIAL),
+                _ => Step::Units,
+            },
+            (Step::Config(units), Input::Answer(answer)) => match yes(answer, true) {
+                Some(true) => Step::Write(units),
+                Some(false) => Step::Done,
+                None => Step::Config(units),
+            },
+            (Step::Write(_), Input::Ran(_)) => Step::Done,
+            // An input the step does not take leaves it where it is.
+            (step, _) => step,
+        };
+    }
+}
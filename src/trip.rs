@@ -0,0 +1,83 @@
+//! Planning for `trip`: stops along a route, each on a date, matched to the
+//! days of the forecast there.
+
+use chrono::{Days, NaiveDate};
+use std::fmt;
+
+use crate::forecast::Day;
+
+/// °C; a day this hot or hotter is flagged.
+pub const HEAT_TEMP: f64 = 32.0;
+/// Probability of precipitation from which a day counts as rainy.
+const RAIN_POP: f64 = 0.5;
+/// mm of rain over the day that count as rainy whatever the probability.
+const RAIN_MM: f64 = 1.0;
+
+/// A stop as typed, e.g. "Lyon, FR@2025-07-01".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    /// A city with an optional country code or name, or coordinates.
+    pub place: String,
+    pub date: Option<NaiveDate>,
+}
+
+/// A place with an optional `@YYYY-MM-DD` date.
+pub fn parse_waypoint(input: &str) -> Result<Waypoint, String> {
+    let (place, date) = match input.rsplit_once('@') {
+        Some((place, date)) => {
+            let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| format!("'{}' is not a date; use YYYY-MM-DD", date))?;
+            (place, Some(date))
+        }
+        None => (input, None),
+    };
+    let place = place.trim();
+    if place.is_empty() {
+        return Err(format!("'{}' names no place", input));
+    }
+    Ok(Waypoint {
+        place: place.to_string(),
+        date,
+    })
+}
+
+/// The date of each stop: its own, or else the day after the stop before,
+/// with the first stop today.
+pub fn schedule(waypoints: &[Waypoint], today: NaiveDate) -> Vec<NaiveDate> {
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    for waypoint in waypoints {
+        let date = waypoint.date.unwrap_or_else(|| match dates.last() {
+            Some(previous) => previous.checked_add_days(Days::new(1)).unwrap_or(*previous),
+            None => today,
+        });
+        dates.push(date);
+    }
+    dates
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Rain,
+    Heat,
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Flag::Rain => write!(f, "rain likely"),
+            Flag::Heat => write!(f, "extreme heat"),
+        }
+    }
+}
+
+/// What to plan around on `day`.
+pub fn flags(day: &Day) -> Vec<Flag> {
+    let mut flags = Vec::new();
+    if day.pop >= RAIN_POP || day.rain_mm >= RAIN_MM {
+        flags.push(Flag::Rain);
+    }
+    if day.max >= HEAT_TEMP {
+        flags.push(Flag::Heat);
+    }
+    flags
+}
@@ -0,0 +1,275 @@
+//! Conversions between the units the API reports (metric) and display units.
+
+use std::sync::OnceLock;
+
+pub fn celsius_to_fahrenheit(celsius: f64) -> f64 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+pub fn fahrenheit_to_celsius(fahrenheit: f64) -> f64 {
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SpeedUnit {
+    #[default]
+    MetersPerSecond,
+    KilometersPerHour,
+    MilesPerHour,
+    Knots,
+}
+
+impl SpeedUnit {
+    pub const ALL: [SpeedUnit; 4] = [
+        SpeedUnit::MetersPerSecond,
+        SpeedUnit::KilometersPerHour,
+        SpeedUnit::MilesPerHour,
+        SpeedUnit::Knots,
+    ];
+
+    /// How many of this unit make one metre per second.
+    fn per_mps(self) -> f64 {
+        match self {
+            SpeedUnit::MetersPerSecond => 1.0,
+            SpeedUnit::KilometersPerHour => 3.6,
+            SpeedUnit::MilesPerHour => 3600.0 / 1609.344,
+            SpeedUnit::Knots => 3600.0 / 1852.0,
+        }
+    }
+
+    pub fn from_mps(self, mps: f64) -> f64 {
+        mps * self.per_mps()
+    }
+
+    pub fn to_mps(self, value: f64) -> f64 {
+        value / self.per_mps()
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            SpeedUnit::MetersPerSecond => "m/s",
+            SpeedUnit::KilometersPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+            SpeedUnit::Knots => "kn",
+        }
+    }
+}
+
+impl std::str::FromStr for SpeedUnit {
+    type Err = String;
+
+    /// Accepts the symbols and a few common spellings: "ms", "kmh", "kt".
+    fn from_str(s: &str) -> Result<SpeedUnit, String> {
+        match s.trim().to_lowercase().as_str() {
+            "m/s" | "ms" | "mps" => Ok(SpeedUnit::MetersPerSecond),
+            "km/h" | "kmh" | "kph" => Ok(SpeedUnit::KilometersPerHour),
+            "mph" => Ok(SpeedUnit::MilesPerHour),
+            "kn" | "kt" | "kts" | "knots" => Ok(SpeedUnit::Knots),
+            _ => {
+                let symbols: Vec<&str> = SpeedUnit::ALL.iter().map(|u| u.symbol()).collect();
+                Err(format!(
+                    "unknown speed unit '{}'; choose {}",
+                    s,
+                    symbols.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+/// Upper bounds in m/s of Beaufort forces 0 to 11; anything faster is 12.
+const BEAUFORT_LIMITS: [f64; 12] = [
+    0.5, 1.5, 3.3, 5.5, 7.9, 10.7, 13.8, 17.1, 20.7, 24.4, 28.4, 32.6,
+];
+
+const BEAUFORT_DESCRIPTIONS: [&str; 13] = [
+    "calm",
+    "light air",
+    "light breeze",
+    "gentle breeze",
+    "moderate breeze",
+    "fresh breeze",
+    "strong breeze",
+    "near gale",
+    "gale",
+    "strong gale",
+    "storm",
+    "violent storm",
+    "hurricane force",
+];
+
+pub fn beaufort(mps: f64) -> u8 {
+    BEAUFORT_LIMITS
+        .iter()
+        .position(|limit| mps < *limit)
+        .unwrap_or(BEAUFORT_LIMITS.len()) as u8
+}
+
+pub fn beaufort_description(force: u8) -> &'static str {
+    BEAUFORT_DESCRIPTIONS[usize::from(force).min(BEAUFORT_DESCRIPTIONS.len() - 1)]
+}
+
+pub const COMPASS_POINTS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+const ARROWS: [char; 8] = ['↑', '↗', '→', '↘', '↓', '↙', '←', '↖'];
+
+/// Index of the sector of `sectors` equal slices that `degrees` falls in,
+/// with sector 0 centred on north.
+fn sector(degrees: f64, sectors: usize) -> usize {
+    let width = 360.0 / sectors as f64;
+    let normalized = (degrees + width / 2.0).rem_euclid(360.0);
+    (normalized / width) as usize % sectors
+}
+
+/// The 16-point compass name for a bearing, e.g. 22.5° is "NNE".
+pub fn compass_point(degrees: f64) -> &'static str {
+    COMPASS_POINTS[sector(degrees, COMPASS_POINTS.len())]
+}
+
+/// An arrow pointing along the bearing, to the nearest 45°.
+pub fn compass_arrow(degrees: f64) -> char {
+    ARROWS[sector(degrees, ARROWS.len())]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TempUnit {
+    pub fn from_celsius(self, celsius: f64) -> f64 {
+        match self {
+            TempUnit::Celsius => celsius,
+            TempUnit::Fahrenheit => celsius_to_fahrenheit(celsius),
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "°C",
+            TempUnit::Fahrenheit => "°F",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "celsius",
+            TempUnit::Fahrenheit => "fahrenheit",
+        }
+    }
+}
+
+impl std::str::FromStr for TempUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TempUnit, String> {
+        match s.trim().to_lowercase().as_str() {
+            "c" | "°c" | "celsius" => Ok(TempUnit::Celsius),
+            "f" | "°f" | "fahrenheit" => Ok(TempUnit::Fahrenheit),
+            _ => Err(format!(
+                "unknown temperature unit '{}'; choose celsius or fahrenheit",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Clock {
+    #[default]
+    TwentyFourHour,
+    TwelveHour,
+}
+
+impl Clock {
+    /// The `chrono` format for hours and minutes.
+    pub fn format(self) -> &'static str {
+        match self {
+            Clock::TwentyFourHour => "%H:%M",
+            Clock::TwelveHour => "%-I:%M %p",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Clock::TwentyFourHour => "24h",
+            Clock::TwelveHour => "12h",
+        }
+    }
+}
+
+impl std::str::FromStr for Clock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Clock, String> {
+        match s.trim().to_lowercase().as_str() {
+            "24h" | "24" => Ok(Clock::TwentyFourHour),
+            "12h" | "12" => Ok(Clock::TwelveHour),
+            _ => Err(format!("unknown clock '{}'; choose 24h or 12h", s)),
+        }
+    }
+}
+
+/// How numbers and times are shown, for the rest of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Preferences {
+    pub temperature: TempUnit,
+    pub wind: SpeedUnit,
+    pub clock: Clock,
+}
+
+impl Preferences {
+    /// These with some units changed, from a comma-separated list such as
+    /// "fahrenheit,km/h,12h", or "metric" or "imperial" for both
+    /// temperature and wind.
+    pub fn with_units(self, list: &str) -> Result<Preferences, String> {
+        let mut preferences = self;
+        for unit in list
+            .split(',')
+            .map(str::trim)
+            .filter(|unit| !unit.is_empty())
+        {
+            match unit.to_lowercase().as_str() {
+                "metric" => {
+                    preferences.temperature = TempUnit::Celsius;
+                    preferences.wind = SpeedUnit::MetersPerSecond;
+                }
+                "imperial" => {
+                    preferences.temperature = TempUnit::Fahrenheit;
+                    preferences.wind = SpeedUnit::MilesPerHour;
+                }
+                _ => {
+                    if let Ok(temperature) = unit.parse() {
+                        preferences.temperature = temperature;
+                    } else if let Ok(wind) = unit.parse() {
+                        preferences.wind = wind;
+                    } else if let Ok(clock) = unit.parse() {
+                        preferences.clock = clock;
+                    } else {
+                        return Err(format!(
+                            "unknown unit '{}'; give e.g. metric, imperial, fahrenheit, km/h or 12h",
+                            unit
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(preferences)
+    }
+}
+
+static PREFERENCES: OnceLock<Preferences> = OnceLock::new();
+
+/// Only the first call has an effect.
+pub fn init(preferences: Preferences) {
+    let _ = PREFERENCES.set(preferences);
+}
+
+pub fn preferences() -> Preferences {
+    PREFERENCES.get().copied().unwrap_or_default()
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, collections::BTreeMap, path::PathBuf};
+
+use crate::storage::{self, Schema};
+
+const DATA_DIR: &str = "cli_weather";
+const USAGE_FILE: &str = "usage.json";
+/// Days counted one by one for `usage` unless `retention.usage_days` says
+/// otherwise; older days are folded into their month on the next call.
+pub const KEEP_DAYS: u32 = 31;
+/// Share of the daily limit after which each run warns once.
+pub const WARN_RATIO: f64 = 0.9;
+
+pub const SCHEMA: Schema = Schema {
+    name: "usage",
+    migrations: &[],
+};
+
+/// API calls made from this machine, per UTC day and provider, and per
+/// month for days too old to keep apart.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Usage {
+    #[serde(default)]
+    pub days: BTreeMap<String, BTreeMap<String, u32>>,
+    /// By "YYYY-MM".
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub months: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+fn day_key(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+impl Usage {
+    /// Calls per provider on `day`.
+    pub fn day(&self, day: NaiveDate) -> Option<&BTreeMap<String, u32>> {
+        self.days.get(&day_key(day))
+    }
+
+    pub fn count(&self, day: NaiveDate, provider: &str) -> u32 {
+        self.day(day)
+            .and_then(|providers| providers.get(provider))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Counts one call and returns the day's total for `provider`.
+    pub fn record(&mut self, day: NaiveDate, provider: &str) -> u32 {
+        let count = self
+            .days
+            .entry(day_key(day))
+            .or_default()
+            .entry(provider.to_string())
+            .or_default();
+        *count += 1;
+        *count
+    }
+
+    /// Folds all but the last `keep_days` days into monthly totals; 0 keeps
+    /// every day.
+    pub fn downsample(&mut self, keep_days: u32) {
+        if keep_days == 0 {
+            return;
+        }
+        while self.days.len() > keep_days as usize {
+            let Some((day, providers)) = self.days.pop_first() else {
+                break;
+            };
+            let month = day.get(..7).unwrap_or(&day).to_string();
+            let month = self.months.entry(month).or_default();
+            for (provider, count) in providers {
+                *month.entry(provider).or_default() += count;
+            }
+        }
+    }
+}
+
+/// Persists the call counter and warns when a day's calls near the limit.
+pub struct Tracker {
+    path: PathBuf,
+    daily_limit: u32,
+    keep_days: u32,
+    warned: Cell<bool>,
+}
+
+impl Tracker {
+    pub fn new(path: PathBuf, daily_limit: u32) -> Tracker {
+        Tracker {
+            path,
+            daily_limit,
+            keep_days: KEEP_DAYS,
+            warned: Cell::new(false),
+        }
+    }
+
+    /// Days counted one by one before they are folded into their month.
+    pub fn with_keep_days(self, keep_days: u32) -> Tracker {
+        Tracker { keep_days, ..self }
+    }
+
+    pub fn open(daily_limit: u32) -> Option<Tracker> {
+        dirs::data_dir().map(|dir| Tracker::new(dir.join(DATA_DIR).join(USAGE_FILE), daily_limit))
+    }
+
+    pub fn daily_limit(&self) -> u32 {
+        self.daily_limit
+    }
+
+    pub fn load(&self) -> Result<Usage, storage::Error> {
+        Ok(storage::load(&self.path, &SCHEMA)?.unwrap_or_default())
+    }
+
+    /// Counts one call. Returns a warning the first time in this run that
+    /// the day's total reaches 90% of the limit.
+    pub fn record(&self, day: NaiveDate, provider: &str) -> Result<Option<String>, storage::Error> {
+        let count = storage::update(&self.path, &SCHEMA, |usage: &mut Usage| {
+            let count = usage.record(day, provider);
+            usage.downsample(self.keep_days);
+            count
+        })?;
+
+        if self.daily_limit == 0 || self.warned.get() {
+            return Ok(None);
+        }
+        if f64::from(count) < f64::from(self.daily_limit) * WARN_RATIO {
+            return Ok(None);
+        }
+        self.warned.set(true);
+
+        Ok(Some(format!(
+            "{} of {} daily {} calls used today (UTC)",
+            count, self.daily_limit, provider
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWM: &str = "OpenWeatherMap";
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn four_days() -> Usage {
+        let mut usage = Usage::default();
+        for date in ["2024-03-30", "2024-03-31", "2024-04-01", "2024-04-02"] {
+            usage.record(day(date), OWM);
+        }
+        usage.record(day("2024-03-31"), "Met.no");
+        usage
+    }
+
+    #[test]
+    fn downsample_keeps_the_latest_days() {
+        let mut usage = four_days();
+        usage.downsample(2);
+        assert_eq!(
+            usage.days.keys().collect::<Vec<_>>(),
+            ["2024-04-01", "2024-04-02"]
+        );
+        assert_eq!(usage.count(day("2024-04-01"), OWM), 1);
+    }
+
+    #[test]
+    fn downsample_folds_older_days_into_their_month() {
+        let mut usage = four_days();
+        usage.downsample(2);
+        assert_eq!(usage.months["2024-03"][OWM], 2);
+        assert_eq!(usage.months["2024-03"]["Met.no"], 1);
+        assert!(!usage.months.contains_key("2024-04"));
+    }
+
+    #[test]
+    fn downsample_to_zero_keeps_every_day() {
+        let mut usage = four_days();
+        usage.downsample(0);
+        assert_eq!(usage.days.len(), 4);
+        assert!(usage.months.is_empty());
+    }
+}
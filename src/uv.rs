@@ -0,0 +1,75 @@
+//! UV index risk levels and sun protection advice, on the WHO's scale.
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+use crate::api::{UvForecast, UvReading};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Risk {
+    Low,
+    Moderate,
+    High,
+    VeryHigh,
+    Extreme,
+}
+
+impl Risk {
+    /// Bands are 0–2, 3–5, 6–7, 8–10 and 11+, after rounding.
+    pub fn from_index(uvi: f64) -> Risk {
+        match uvi.round() {
+            i if i <= 2.0 => Risk::Low,
+            i if i <= 5.0 => Risk::Moderate,
+            i if i <= 7.0 => Risk::High,
+            i if i <= 10.0 => Risk::VeryHigh,
+            _ => Risk::Extreme,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Risk::Low => "low",
+            Risk::Moderate => "moderate",
+            Risk::High => "high",
+            Risk::VeryHigh => "very high",
+            Risk::Extreme => "extreme",
+        }
+    }
+
+    pub fn advice(self) -> &'static str {
+        match self {
+            Risk::Low => "no protection needed",
+            Risk::Moderate => "wear sunscreen and a hat around midday",
+            Risk::High => "SPF 30+ sunscreen, a hat and shade around midday",
+            Risk::VeryHigh => "SPF 50+ sunscreen; stay in the shade around midday",
+            Risk::Extreme => "avoid the sun around midday; SPF 50+, hat and sunglasses",
+        }
+    }
+}
+
+/// The UV index now and the day's maximum, for the weather report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub now: f64,
+    pub today_max: Option<f64>,
+}
+
+impl Summary {
+    pub fn new(forecast: &UvForecast) -> Summary {
+        Summary {
+            now: forecast.current.uvi,
+            today_max: forecast.daily.first().map(|day| day.uvi),
+        }
+    }
+}
+
+/// The hourly readings on `date` at the location.
+pub fn hours_on(forecast: &UvForecast, date: NaiveDate, offset: FixedOffset) -> Vec<&UvReading> {
+    forecast
+        .hourly
+        .iter()
+        .filter(|hour| {
+            DateTime::from_timestamp(hour.dt, 0)
+                .is_some_and(|time| time.with_timezone(&offset).date_naive() == date)
+        })
+        .collect()
+}
@@ -0,0 +1,119 @@
+//! Resolves what3words addresses (`///filled.count.soap`) to coordinates.
+//! Needs a separate what3words API key.
+
+use serde::Deserialize;
+
+use crate::api::{self, ApiError, Coord};
+use crate::credentials::Auth;
+
+const BASE_URL: &str = "https://api.what3words.com";
+
+pub const PROVIDER: &str = "what3words";
+
+/// Three words joined by dots, with or without the leading `///`.
+pub fn parse_address(input: &str) -> Result<String, String> {
+    let words = input.trim().trim_start_matches("///");
+    let parts: Vec<&str> = words.split('.').collect();
+
+    if parts.len() == 3
+        && parts
+            .iter()
+            .all(|word| !word.is_empty() && word.chars().all(char::is_alphabetic))
+    {
+        Ok(words.to_lowercase())
+    } else {
+        Err(format!(
+            "'{}' is not a what3words address; expected three words like ///filled.count.soap",
+            input
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct Conversion {
+    coordinates: Coordinates,
+}
+
+#[derive(Deserialize)]
+struct Coordinates {
+    lat: f64,
+    lng: f64,
+}
+
+/// What the API sends with a failed request, e.g.
+/// `{"error":{"code":"BadWords","message":"..."}}`.
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    code: String,
+    message: String,
+}
+
+pub struct Client {
+    http: reqwest::blocking::Client,
+    auth: Auth,
+}
+
+impl Client {
+    pub fn new(api_key: &str) -> Client {
+        Client {
+            http: api::http_client(api::USER_AGENT, &Default::default()).unwrap_or_default(),
+            auth: Auth::QueryKey {
+                param: "key",
+                key: api_key.to_string(),
+            },
+        }
+    }
+
+    pub fn with_http(self, http: reqwest::blocking::Client) -> Self {
+        Client { http, ..self }
+    }
+
+    /// The centre of the 3m square `words` names.
+    pub fn convert(&self, words: &str) -> Result<Coord, api::Error> {
+        let network = |e: reqwest::Error| api::Error::Network(e.without_url());
+        let path = "/v3/convert-to-coordinates";
+        let params = [("words", words.to_string())];
+        let request = self
+            .http
+            .get(format!("{}{}", BASE_URL, path))
+            .query(&params);
+        let response = self
+            .auth
+            .apply(&self.http, request, path, &params)?
+            .send()
+            .map_err(network)?;
+        let status = response.status();
+        let body = response.text().map_err(network)?;
+
+        if !status.is_success() {
+            let error = match serde_json::from_str::<ErrorBody>(&body) {
+                Ok(body) => ApiError {
+                    cod: body.error.code,
+                    message: body.error.message,
+                },
+                Err(_) => ApiError {
+                    cod: status.as_str().to_string(),
+                    message: status
+                        .canonical_reason()
+                        .unwrap_or("unknown error")
+                        .to_string(),
+                },
+            };
+            return Err(api::Error::Api {
+                status: status.as_u16(),
+                error,
+            });
+        }
+
+        let conversion: Conversion = serde_json::from_str(&body).map_err(api::Error::Decode)?;
+        Ok(Coord {
+            lat: conversion.coordinates.lat,
+            lon: conversion.coordinates.lng,
+        })
+    }
+}
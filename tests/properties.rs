@@ -0,0 +1,1255 @@
+use proptest::prelude::*;
+
+use chrono::{FixedOffset, NaiveDate, TimeZone, Utc};
+
+use cli_weather::advice::{self, Conditions};
+use cli_weather::api::{ForecastResponse, WeatherResponse};
+use cli_weather::astro;
+use cli_weather::chart;
+use cli_weather::climate::{self, Daily, Normals};
+use cli_weather::comfort::{Miss, Score};
+use cli_weather::config::{self, Comfort, Config, KeyError, Thresholds};
+use cli_weather::coords;
+use cli_weather::credentials;
+use cli_weather::locale::Locale;
+use cli_weather::metrics::{Metrics, Quota};
+use cli_weather::middleware::{Canned, Middleware, Next, RateLimit, Request, Response, Retry};
+use cli_weather::moment;
+use cli_weather::recent::{self, DayRecord, Totals};
+use cli_weather::template::{self, Template};
+use cli_weather::trip::{self, Flag, Waypoint};
+use cli_weather::units::{self, Clock, Preferences, SpeedUnit, TempUnit, COMPASS_POINTS};
+use cli_weather::uv::Risk;
+use cli_weather::{display, forecast, ical};
+
+const KNOWN: &[&str] = &["temp", "city"];
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+fn speed_unit() -> impl Strategy<Value = SpeedUnit> {
+    prop::sample::select(SpeedUnit::ALL.to_vec())
+}
+
+proptest! {
+    #[test]
+    fn temperature_round_trips(celsius in -100.0..100.0f64) {
+        let back = units::fahrenheit_to_celsius(units::celsius_to_fahrenheit(celsius));
+        prop_assert!(close(back, celsius), "{} became {}", celsius, back);
+    }
+
+    #[test]
+    fn speed_round_trips(mps in 0.0..200.0f64, unit in speed_unit()) {
+        let back = unit.to_mps(unit.from_mps(mps));
+        prop_assert!(close(back, mps), "{} m/s became {} via {:?}", mps, back, unit);
+    }
+
+    #[test]
+    fn speed_conversion_keeps_order(a in 0.0..200.0f64, b in 0.0..200.0f64, unit in speed_unit()) {
+        let (slow, fast) = if a <= b { (a, b) } else { (b, a) };
+        prop_assert!(unit.from_mps(slow) <= unit.from_mps(fast));
+    }
+
+    #[test]
+    fn beaufort_is_monotonic_and_bounded(a in 0.0..100.0f64, b in 0.0..100.0f64) {
+        let (slow, fast) = if a <= b { (a, b) } else { (b, a) };
+        prop_assert!(units::beaufort(slow) <= units::beaufort(fast));
+        prop_assert!(units::beaufort(fast) <= 12);
+        prop_assert!(!units::beaufort_description(units::beaufort(fast)).is_empty());
+    }
+
+    #[test]
+    fn compass_ignores_full_turns(degrees in -720.0..720.0f64, turns in -3i32..3) {
+        let shifted = degrees + f64::from(turns) * 360.0;
+        prop_assert_eq!(units::compass_point(degrees), units::compass_point(shifted));
+        prop_assert_eq!(units::compass_arrow(degrees), units::compass_arrow(shifted));
+    }
+
+    #[test]
+    fn compass_picks_nearest_point(index in 0usize..16, offset in -11.0..11.0f64) {
+        let degrees = index as f64 * 22.5 + offset;
+        prop_assert_eq!(units::compass_point(degrees), COMPASS_POINTS[index]);
+    }
+
+    #[test]
+    fn geo_uri_round_trips(lat in -90.0..=90.0f64, lon in -180.0..=180.0f64) {
+        let coord = coords::parse(&coords::geo_uri(&cli_weather::api::Coord { lat, lon })).unwrap();
+        prop_assert!((coord.lat - lat).abs() < 1e-5 && (coord.lon - lon).abs() < 1e-5);
+    }
+
+    #[test]
+    fn coordinate_parse_never_panics(input in any::<String>()) {
+        let _ = coords::parse(&input);
+    }
+
+    #[test]
+    fn escaped_text_renders_verbatim(text in any::<String>()) {
+        let parsed = Template::parse(&template::escape(&text), KNOWN).unwrap();
+        prop_assert_eq!(parsed.render(|_| String::from("x")), text);
+    }
+
+    #[test]
+    fn parse_never_panics(source in any::<String>()) {
+        let _ = Template::parse(&source, KNOWN);
+    }
+
+    #[test]
+    fn placeholders_are_substituted(prefix in "[^{}]*", suffix in "[^{}]*", value in any::<String>()) {
+        let parsed = Template::parse(&format!("{}{{temp}}{}", prefix, suffix), KNOWN).unwrap();
+        prop_assert_eq!(parsed.render(|_| value.clone()), format!("{}{}{}", prefix, value, suffix));
+    }
+
+    #[test]
+    fn calendar_lines_fit(summary in any::<String>(), description in any::<String>()) {
+        let event = ical::Event {
+            uid: String::from("20240410-paris@cli_weather"),
+            date: NaiveDate::from_ymd_opt(2024, 4, 10).unwrap(),
+            summary,
+            description,
+        };
+        let calendar = ical::calendar("Weather", &[event], Utc::now());
+        for line in calendar.split_terminator("\r\n") {
+            prop_assert!(line.len() <= 75, "{:?} is too long", line);
+            prop_assert!(!line.contains('\n') && !line.contains('\r'));
+        }
+    }
+
+    #[test]
+    fn calendar_reads_back(summary in any::<String>()) {
+        let date = NaiveDate::from_ymd_opt(2024, 4, 10).unwrap();
+        let event = ical::Event {
+            uid: String::from("20240410-paris@cli_weather"),
+            date,
+            summary: summary.clone(),
+            description: String::new(),
+        };
+        let read = ical::read(&ical::calendar("Weather", &[event], Utc::now())).unwrap();
+        prop_assert_eq!(read.len(), 1);
+        prop_assert_eq!(&read[0].summary, &summary);
+        prop_assert_eq!(read[0].start, ical::Start::AllDay(date));
+    }
+}
+
+#[test]
+fn compass_examples() {
+    assert_eq!(units::compass_point(0.0), "N");
+    assert_eq!(units::compass_point(22.5), "NNE");
+    assert_eq!(units::compass_point(359.0), "N");
+    assert_eq!(units::compass_arrow(22.5), '↗');
+    assert_eq!(units::compass_arrow(180.0), '↓');
+}
+
+#[test]
+fn coordinate_examples() {
+    let paris = |input: &str| {
+        let coord = coords::parse(input).unwrap();
+        (
+            (coord.lat * 100.0).round() / 100.0,
+            (coord.lon * 100.0).round() / 100.0,
+        )
+    };
+    assert_eq!(paris("48.8566, 2.3522"), (48.86, 2.35));
+    assert_eq!(paris("48.8566 2.3522"), (48.86, 2.35));
+    assert_eq!(paris("48°51'24\"N 2°21'08\"E"), (48.86, 2.35));
+    assert_eq!(paris("48 51 24 N, 2 21 8 E"), (48.86, 2.35));
+    assert_eq!(paris("geo:48.8566,2.3522,35;u=10"), (48.86, 2.35));
+    assert_eq!(paris("33°52′S 151°12′E"), (-33.87, 151.2));
+    assert!(coords::parse("91, 0").is_err());
+    assert!(coords::parse("48°61'N 2°E").is_err());
+    assert!(coords::parse("geo:48.85").is_err());
+}
+
+#[test]
+fn plus_code_examples() {
+    // Cell centres of boxes from the Open Location Code test data.
+    let decodes_to = |code: &str, lat: f64, lon: f64| {
+        let coord = coords::parse(code).unwrap();
+        assert!(
+            close(coord.lat, lat) && close(coord.lon, lon),
+            "{} gave {:?}",
+            code,
+            coord
+        );
+    };
+    decodes_to("7FG49Q00+", 20.375, 2.775);
+    decodes_to("7FG49QCJ+2V", 20.3700625, 2.7821875);
+    decodes_to("7fg49qcj+2v", 20.3700625, 2.7821875);
+    decodes_to("7FG49QCJ+2VX", 20.3701125, 2.782234375);
+    decodes_to("8FW4V75V+8Q", 48.8583125, 2.2944375);
+    assert!(coords::parse("V75V+8Q").is_err());
+    assert!(coords::parse("8FW4V75V+8").is_err());
+    assert!(coords::parse("8FW4V700+8Q").is_err());
+    assert!(coords::parse("XFW4V75V+8Q").is_err());
+}
+
+#[test]
+fn metrics_examples() {
+    let metrics = Metrics::default();
+    metrics.observe_upstream("/data/2.5/weather", std::time::Duration::from_millis(30));
+    metrics.observe_upstream("/data/2.5/weather", std::time::Duration::from_secs(20));
+    metrics.upstream_error("network");
+    metrics.cache_lookup(true);
+    metrics.cache_lookup(false);
+    metrics.observe_served("/weather", 200, std::time::Duration::from_millis(40));
+
+    let text = metrics.render(Some(Quota {
+        used: 12,
+        limit: 1000,
+    }));
+    let has = |line: &str| text.lines().any(|l| l == line);
+    let upstream = "cli_weather_upstream_request_duration_seconds";
+    assert!(has(&format!(
+        "{}_bucket{{endpoint=\"/data/2.5/weather\",le=\"0.025\"}} 0",
+        upstream
+    )));
+    assert!(has(&format!(
+        "{}_bucket{{endpoint=\"/data/2.5/weather\",le=\"0.05\"}} 1",
+        upstream
+    )));
+    assert!(has(&format!(
+        "{}_bucket{{endpoint=\"/data/2.5/weather\",le=\"+Inf\"}} 2",
+        upstream
+    )));
+    assert!(has(&format!(
+        "{}_count{{endpoint=\"/data/2.5/weather\"}} 2",
+        upstream
+    )));
+    assert!(has("cli_weather_upstream_errors_total{kind=\"network\"} 1"));
+    assert!(has("cli_weather_cache_hit_ratio 0.5"));
+    assert!(has(
+        "cli_weather_http_responses_total{route=\"/weather\",status=\"200\"} 1"
+    ));
+    assert!(has("cli_weather_api_calls_today 12"));
+}
+
+#[test]
+fn uv_risk_examples() {
+    assert_eq!(Risk::from_index(0.0), Risk::Low);
+    assert_eq!(Risk::from_index(2.4), Risk::Low);
+    assert_eq!(Risk::from_index(2.5), Risk::Moderate);
+    assert_eq!(Risk::from_index(7.0), Risk::High);
+    assert_eq!(Risk::from_index(10.4), Risk::VeryHigh);
+    assert_eq!(Risk::from_index(11.0), Risk::Extreme);
+}
+
+#[test]
+fn signature_example() {
+    // Pairs are sorted before signing, whatever order they come in.
+    let params = [
+        ("timestamp", String::from("1700000000")),
+        ("lat", String::from("1")),
+        ("appid", String::from("k")),
+    ];
+    assert_eq!(
+        credentials::signature("secret", "/data/2.5/weather", &params),
+        "64d10ea3d25124b29a79bd1a99cce83debd15892f4152f4aebe367235cbe5498"
+    );
+}
+
+#[test]
+fn speed_unit_examples() {
+    assert_eq!("km/h".parse(), Ok(SpeedUnit::KilometersPerHour));
+    assert_eq!("KT".parse(), Ok(SpeedUnit::Knots));
+    assert!("furlongs".parse::<SpeedUnit>().is_err());
+    for unit in SpeedUnit::ALL {
+        assert_eq!(unit.symbol().parse(), Ok(unit));
+    }
+}
+
+#[test]
+fn forecast_days_example() {
+    let forecast: ForecastResponse =
+        serde_json::from_str(include_str!("fixtures/forecast.json")).unwrap();
+    let days = forecast::days(&forecast, display::utc_offset(forecast.city.timezone));
+
+    assert_eq!(days.len(), 6);
+    assert_eq!(days[0].date, NaiveDate::from_ymd_opt(2024, 4, 10).unwrap());
+    assert!(days.windows(2).all(|pair| pair[0].date < pair[1].date));
+    assert_eq!((days[1].min, days[1].max), (10.0, 15.0));
+}
+
+#[test]
+fn locale_examples() {
+    let us = Locale::parse("en_US.UTF-8");
+    assert_eq!(us.language.as_deref(), Some("en"));
+    assert_eq!(us.region.as_deref(), Some("US"));
+    assert_eq!(
+        us.preferences(),
+        Preferences {
+            temperature: TempUnit::Fahrenheit,
+            wind: SpeedUnit::MilesPerHour,
+            clock: Clock::TwelveHour,
+        }
+    );
+
+    let de = Locale::parse("de_DE@euro");
+    assert_eq!(de.language.as_deref(), Some("de"));
+    assert_eq!(de.preferences(), Preferences::default());
+
+    assert_eq!(Locale::parse("C.UTF-8"), Locale::default());
+    assert_eq!(Locale::parse("POSIX"), Locale::default());
+
+    let asked = Locale::from_accept_language("fr;q=0.5, de-CH, en;q=0.9").unwrap();
+    assert_eq!(asked.language.as_deref(), Some("de"));
+    assert_eq!(asked.region.as_deref(), Some("CH"));
+    let asked = Locale::from_accept_language("*, es;q=0.2, it;q=0").unwrap();
+    assert_eq!(asked.language.as_deref(), Some("es"));
+    assert_eq!(Locale::from_accept_language("*"), None);
+    assert_eq!(Locale::from_accept_language(""), None);
+}
+
+#[test]
+fn with_units_examples() {
+    let base = Preferences::default();
+    assert_eq!(
+        base.with_units("imperial, 12h"),
+        Ok(Preferences {
+            temperature: TempUnit::Fahrenheit,
+            wind: SpeedUnit::MilesPerHour,
+            clock: Clock::TwelveHour,
+        })
+    );
+    assert_eq!(
+        base.with_units("fahrenheit,km/h"),
+        Ok(Preferences {
+            temperature: TempUnit::Fahrenheit,
+            wind: SpeedUnit::KilometersPerHour,
+            ..base
+        })
+    );
+    assert_eq!(base.with_units(""), Ok(base));
+    assert!(base.with_units("metric,furlongs").is_err());
+}
+
+#[test]
+fn config_set_examples() {
+    let config = Config::default();
+
+    let changed = config.set("cache.ttl_secs", "60").unwrap();
+    assert_eq!(changed.cache.ttl_secs, 60);
+    let changed = config.set("http.headers.X-Gateway", "on").unwrap();
+    assert_eq!(changed.http.headers["X-Gateway"], "on");
+    assert_eq!(
+        changed
+            .get("http.headers.X-Gateway")
+            .map(|v| config::display_value(&v)),
+        Ok(String::from("on"))
+    );
+
+    assert_eq!(
+        config.set("nope", "1").err(),
+        Some(KeyError::Unknown(String::from("nope")))
+    );
+    assert_eq!(
+        config.set("units", "1").err(),
+        Some(KeyError::Table(String::from("units")))
+    );
+    assert!(matches!(
+        config.set("uv.in_report", "maybe"),
+        Err(KeyError::Invalid { .. })
+    ));
+
+    let oauth = config.set("auth.scheme", "oauth").unwrap();
+    let rendered = oauth.validate().unwrap_err().render();
+    assert!(
+        rendered.contains("auth.token_url is not set"),
+        "{}",
+        rendered
+    );
+    let oauth = oauth
+        .set("auth.token_url", "https://id.example.com/token")
+        .and_then(|config| config.set("auth.client_id", "weather-app"))
+        .unwrap();
+    assert!(oauth.validate().is_ok());
+    assert!(config
+        .set("auth.scheme", "header")
+        .and_then(|config| config.set("auth.header", "X Key"))
+        .unwrap()
+        .validate()
+        .is_err());
+    assert!(config
+        .set("auth.scheme", "kerberos")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn monitor_places_examples() {
+    let config: Config = toml::from_str(
+        r#"
+        [[monitor.places]]
+        place = "Lyon, FR"
+        name = "Grandma"
+        ntfy_topic = "grandma"
+
+        [[monitor.places]]
+        place = "48.85,2.35"
+        "#,
+    )
+    .unwrap();
+    let places = &config.monitor.places;
+    assert_eq!(places.len(), 2);
+    assert_eq!(places[0].name.as_deref(), Some("Grandma"));
+    assert!(places[0].has_channels());
+    assert!(!places[1].has_channels());
+    assert!(config.validate().is_ok());
+
+    let config: Config = toml::from_str("[[monitor.places]]\nname = \"Nowhere\"").unwrap();
+    let rendered = config.validate().unwrap_err().render();
+    assert!(rendered.contains("entry 1 has no place"), "{}", rendered);
+}
+
+#[test]
+fn comfort_examples() {
+    let profile = Comfort::default();
+    let ideal = Score::new(&profile, 21.0, 2.0, 50.0);
+    assert_eq!(ideal.value, 100);
+    assert!(ideal.misses.is_empty());
+
+    let raw = Score::new(&profile, 14.0, 7.0, 80.0);
+    assert_eq!(
+        raw.misses,
+        [
+            Miss::TooCold(4.0),
+            Miss::TooWindy(2.0),
+            Miss::TooHumid(10.0)
+        ]
+    );
+    assert_eq!(raw.value, 100 - 20 - 8 - 10);
+
+    assert_eq!(Score::new(&profile, -20.0, 30.0, 100.0).value, 0);
+}
+
+proptest! {
+    #[test]
+    fn comfort_never_rises_when_colder(temp in -30.0f64..18.0, drop in 0.0f64..10.0) {
+        let profile = Comfort::default();
+        let warmer = Score::new(&profile, temp, 1.0, 40.0).value;
+        let colder = Score::new(&profile, temp - drop, 1.0, 40.0).value;
+        prop_assert!(colder <= warmer);
+    }
+}
+
+#[test]
+fn climate_normals_example() {
+    let daily = Daily {
+        time: ["2019-07-01", "2019-07-02", "2019-08-01", "2020-07-01"]
+            .map(String::from)
+            .to_vec(),
+        temperature_2m_max: vec![Some(24.0), Some(28.0), Some(40.0), None],
+        precipitation_sum: vec![Some(0.2), Some(5.0), Some(9.0), Some(1.0)],
+        relative_humidity_2m_mean: vec![Some(60.0), Some(70.0), Some(10.0), Some(80.0)],
+    };
+    assert_eq!(
+        Normals::for_month(&daily, 7),
+        Some(Normals {
+            high: 26.0,
+            rain_days: 1.0,
+            humidity: 70.0,
+        })
+    );
+    assert_eq!(Normals::for_month(&daily, 1), None);
+
+    assert_eq!(climate::parse_month("July"), Ok(7));
+    assert_eq!(climate::parse_month("sep"), Ok(9));
+    assert_eq!(climate::parse_month("12"), Ok(12));
+    assert!(climate::parse_month("ju").is_err());
+    assert!(climate::parse_month("13").is_err());
+}
+
+#[test]
+fn trip_examples() {
+    let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let waypoints: Vec<Waypoint> = ["Lyon@2025-07-01", "Nice, FR", "43.7,7.27@2025-07-05"]
+        .iter()
+        .map(|input| trip::parse_waypoint(input).unwrap())
+        .collect();
+    assert_eq!(waypoints[0].place, "Lyon");
+    assert_eq!(waypoints[1].date, None);
+    assert_eq!(
+        trip::schedule(&waypoints, date("2025-06-01")),
+        [date("2025-07-01"), date("2025-07-02"), date("2025-07-05")]
+    );
+    assert!(trip::parse_waypoint("@2025-07-01").is_err());
+    assert!(trip::parse_waypoint("Lyon@tomorrow").is_err());
+
+    let forecast: ForecastResponse =
+        serde_json::from_str(include_str!("fixtures/forecast.json")).unwrap();
+    let days = forecast::days(&forecast, FixedOffset::east_opt(0).unwrap());
+    assert!(days
+        .iter()
+        .all(|day| !trip::flags(day).contains(&Flag::Heat)));
+}
+
+#[test]
+fn advice_examples() {
+    let names = |conditions: &Conditions| -> Vec<&str> {
+        advice::advise(conditions)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    };
+
+    let wet_and_windy = Conditions {
+        feels_like: 8.0,
+        wind: 12.0,
+        condition: Some(501),
+        rain_chance: Some(0.8),
+        ..Conditions::default()
+    };
+    assert_eq!(names(&wet_and_windy), ["umbrella", "layers", "wind"]);
+
+    let beach_day = Conditions {
+        feels_like: 31.0,
+        wind: 2.0,
+        condition: Some(800),
+        rain_chance: Some(0.1),
+        uv: Some(9.0),
+        ..Conditions::default()
+    };
+    assert_eq!(names(&beach_day), ["layers", "heat", "sunscreen"]);
+}
+
+#[test]
+fn moment_examples() {
+    let paris = FixedOffset::east_opt(2 * 3600).unwrap();
+    let now = Utc.with_ymd_and_hms(2024, 4, 10, 20, 0, 0).unwrap();
+    let at = |input| moment::parse(input).unwrap().resolve(now, paris);
+
+    assert_eq!(
+        at("tomorrow 09:00 local"),
+        Utc.with_ymd_and_hms(2024, 4, 11, 7, 0, 0).unwrap()
+    );
+    assert_eq!(
+        at("07:00 UTC"),
+        Utc.with_ymd_and_hms(2024, 4, 11, 7, 0, 0).unwrap()
+    );
+    assert_eq!(
+        at("23:30"),
+        Utc.with_ymd_and_hms(2024, 4, 10, 21, 30, 0).unwrap()
+    );
+    assert!(moment::parse("soon").is_err());
+    assert!(moment::parse("tomorrow 25:00").is_err());
+
+    let forecast: ForecastResponse =
+        serde_json::from_str(include_str!("fixtures/forecast.json")).unwrap();
+    let dt = |time: chrono::DateTime<Utc>| {
+        forecast::entry_at(&forecast, time.timestamp()).map(|entry| entry.dt)
+    };
+    assert_eq!(
+        dt(Utc.with_ymd_and_hms(2024, 4, 10, 16, 0, 0).unwrap()),
+        Some(1712761200)
+    );
+    assert_eq!(dt(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap()), None);
+}
+
+#[test]
+fn sun_position_examples() {
+    // Solar noon in Paris at the June solstice: 90 - 48.85 + 23.44 degrees up.
+    let noon = Utc.with_ymd_and_hms(2024, 6, 20, 11, 52, 0).unwrap();
+    let sun = astro::sun_position(48.85, 2.35, noon);
+    assert!((sun.elevation - 64.6).abs() < 1.0, "{:?}", sun);
+    assert!((sun.azimuth - 180.0).abs() < 3.0, "{:?}", sun);
+    assert!(close(sun.shadow_bearing(), (sun.azimuth + 180.0) % 360.0));
+    assert!(sun.shadow_ratio().unwrap() < 0.5);
+
+    let midnight = Utc.with_ymd_and_hms(2024, 6, 20, 23, 52, 0).unwrap();
+    let sun = astro::sun_position(48.85, 2.35, midnight);
+    assert!(sun.elevation < 0.0);
+    assert_eq!(sun.shadow_ratio(), None);
+}
+
+#[test]
+fn recent_examples() {
+    let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let days = recent::past_days(date("2024-03-02"), 3);
+    assert_eq!(
+        days,
+        [date("2024-02-28"), date("2024-02-29"), date("2024-03-01")]
+    );
+
+    let records: Vec<DayRecord> = days
+        .iter()
+        .zip([(4.0, 9.0, 0.0), (6.0, 14.0, 5.5), (2.0, 11.0, 0.6)])
+        .map(|(&date, (low, high, rain))| DayRecord {
+            date,
+            low,
+            high,
+            rain,
+        })
+        .collect();
+    let totals = Totals::of(&records).unwrap();
+    assert!(close(totals.rain, 6.1));
+    assert_eq!(totals.rain_days, 1);
+    assert_eq!(totals.wettest.unwrap().date, date("2024-02-29"));
+    assert_eq!(totals.hottest.unwrap().date, date("2024-02-29"));
+    assert_eq!((totals.lowest, totals.highest), (2.0, 14.0));
+    assert_eq!(Totals::of(&[]), None);
+
+    assert_eq!(chart::bar(2.5, 2.0, 40), "█████");
+    assert_eq!(chart::bar(0.375, 1.0, 40), "▍");
+    assert_eq!(chart::bar(100.0, 1.0, 4), "████");
+    assert_eq!(chart::bar(-1.0, 1.0, 4), "");
+    assert_eq!(chart::span(4.0, 9.0, 2.0, 14.0, 12), "  █████     ");
+    assert_eq!(chart::span(5.0, 5.0, 5.0, 5.0, 4), "█   ");
+}
+
+#[test]
+fn twilight_examples() {
+    use cli_weather::astro::Crossing;
+
+    let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let winter = astro::sun_times(48.85, 2.35, date("2024-12-21"));
+    let starts = [
+        winter.sunrise,
+        winter.civil_twilight,
+        winter.nautical_twilight,
+        winter.astronomical_twilight,
+    ]
+    .map(|crossing| match crossing {
+        Crossing::At { rising, .. } => rising,
+        other => panic!("{:?}", other),
+    });
+    // Each deeper twilight starts earlier, about 35 to 40 minutes apart.
+    for pair in starts.windows(2) {
+        let gap = (pair[0] - pair[1]).num_minutes();
+        assert!((30..50).contains(&gap), "{}", gap);
+    }
+
+    let summer = astro::sun_times(48.85, 2.35, date("2024-06-21"));
+    assert!(matches!(summer.nautical_twilight, Crossing::At { .. }));
+    assert_eq!(summer.astronomical_twilight, Crossing::AlwaysAbove);
+}
+
+#[test]
+fn middleware_examples() {
+    use cli_weather::api::{ApiError, Error};
+    use std::cell::Cell;
+
+    /// Records the order layers run in.
+    struct Mark<'a>(&'static str, &'a Cell<Vec<&'static str>>);
+    impl Middleware for Mark<'_> {
+        fn handle(&self, request: &Request, next: &Next) -> Result<Response, Error> {
+            let mut seen = self.1.take();
+            seen.push(self.0);
+            self.1.set(seen);
+            next.run(request)
+        }
+    }
+
+    let seen = Cell::new(Vec::new());
+    let sent = Cell::new(0);
+    let send = |request: &Request| {
+        sent.set(sent.get() + 1);
+        if sent.get() < 3 {
+            Err(Error::RateLimited {
+                retry_after: Some(0),
+                error: ApiError {
+                    cod: String::from("429"),
+                    message: String::from("slow down"),
+                },
+            })
+        } else {
+            Ok(Response::ok(request.path.clone()))
+        }
+    };
+
+    let canned = Canned::new().with("/canned", "{}");
+    let retry = Retry::default();
+    let (outer, inner) = (Mark("outer", &seen), Mark("inner", &seen));
+    let layers: [&dyn Middleware; 4] = [&outer, &canned, &retry, &inner];
+    let chain = Next::new(&layers, &send);
+
+    assert_eq!(
+        chain
+            .run(&Request::new("/canned", Vec::new()))
+            .unwrap()
+            .body,
+        "{}"
+    );
+    assert_eq!(sent.get(), 0);
+    assert_eq!(seen.take(), ["outer"]);
+
+    let response = chain.run(&Request::new("/weather", Vec::new())).unwrap();
+    assert_eq!(response.body, "/weather");
+    assert_eq!(sent.get(), 3);
+    assert_eq!(seen.take(), ["outer", "inner", "inner", "inner"]);
+
+    let limit = RateLimit::new(2);
+    let start = std::time::Instant::now();
+    let at = |secs| start + std::time::Duration::from_secs(secs);
+    assert_eq!(limit.admit(at(0)), Ok(()));
+    assert_eq!(limit.admit(at(10)), Ok(()));
+    assert_eq!(limit.admit(at(15)), Err(std::time::Duration::from_secs(45)));
+    assert_eq!(limit.admit(at(60)), Ok(()));
+    assert!(limit.admit(at(65)).is_err());
+    assert_eq!(limit.admit(at(70)), Ok(()));
+}
+
+#[test]
+fn snapshot_round_trips() {
+    use cli_weather::snapshot::{self, Place, Snapshot};
+
+    let place = |alerts| Place {
+        weather: serde_json::from_str(include_str!("fixtures/weather.json")).unwrap(),
+        forecast: serde_json::from_str(include_str!("fixtures/forecast.json")).ok(),
+        alerts,
+    };
+    let saved = Snapshot {
+        taken_at: 1_712_650_000,
+        places: vec![place(Some(Vec::new())), place(None)],
+    };
+    let dir = std::env::temp_dir().join(format!("cli_weather-snapshot-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("trip.{}", snapshot::EXTENSION));
+    snapshot::save(&path, &saved).unwrap();
+
+    let loaded = snapshot::load(&path).unwrap();
+    assert_eq!(loaded.taken_at, saved.taken_at);
+    assert_eq!(loaded.places.len(), 2);
+    assert_eq!(loaded.places[0].weather.name, "Paris");
+    assert!(loaded.places[0].forecast.is_some());
+    assert_eq!(loaded.places[0].alerts.as_deref().map(<[_]>::len), Some(0));
+    assert!(loaded.places[1].alerts.is_none());
+
+    assert!(snapshot::load(&dir.join("missing.wsnap")).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn events_examples() {
+    use cli_weather::api::Alert;
+    use cli_weather::events::{self, Kind, Log};
+
+    let weather_at = |temp: f64| -> WeatherResponse {
+        let mut weather: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/weather.json")).unwrap();
+        weather["main"]["temp"] = temp.into();
+        serde_json::from_value(weather).unwrap()
+    };
+    let alert = |event: &str| Alert {
+        sender_name: String::from("Météo-France"),
+        event: event.to_string(),
+        start: 1_760_000_000,
+        end: 1_760_050_000,
+        description: String::new(),
+    };
+    let storm = || alert("Orange thunderstorm warning");
+    let now = 1_760_010_000;
+
+    let found = events::detect(
+        &weather_at(-1.5),
+        &[storm(), alert("Yellow fog warning")],
+        &[],
+        now,
+    );
+    let kinds: Vec<Kind> = found.iter().map(|e| e.kind).collect();
+    assert_eq!(kinds, [Kind::FirstFrost, Kind::Storm]);
+    assert_eq!(found[0].date, "2025-10-09");
+    assert_eq!(
+        found[1].alert.as_deref(),
+        Some("Orange thunderstorm warning")
+    );
+
+    // Once a year each, and each storm once.
+    assert!(events::detect(&weather_at(-3.0), &[storm()], &found, now + 3600).is_empty());
+    let next_year = now + 365 * 86_400;
+    assert_eq!(
+        events::detect(&weather_at(-3.0), &[], &found, next_year)[0].kind,
+        Kind::FirstFrost
+    );
+    assert_eq!(
+        events::detect(&weather_at(31.0), &[], &found, now)[0].kind,
+        Kind::FirstHot
+    );
+    assert!(events::detect(&weather_at(30.0), &[], &[], now).is_empty());
+
+    let dir = std::env::temp_dir().join(format!("cli_weather-events-{}", std::process::id()));
+    let log = Log::new(dir.join("events.jsonl"));
+    assert!(log.read().unwrap().is_empty());
+    assert_eq!(
+        log.record(&weather_at(-1.5), &[storm()], now)
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(log
+        .record(&weather_at(-1.5), &[storm()], now)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        log.record(&weather_at(-2.0), &[], next_year).unwrap().len(),
+        1
+    );
+
+    let logged = log.read().unwrap();
+    assert_eq!(logged.len(), 3);
+    assert_eq!(
+        events::matching(&logged, Some("paris"), Some(2025)).len(),
+        2
+    );
+    assert!(events::matching(&logged, Some("Oslo"), None).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn backtest_examples() {
+    use cli_weather::notify::{self, PastDay};
+
+    let day = |d: u32, low: f64, wind: Option<f64>, rain: f64| PastDay {
+        date: NaiveDate::from_ymd_opt(2025, 1, d).unwrap(),
+        low,
+        wind,
+        rain,
+    };
+    let days = [
+        day(1, -3.0, Some(4.0), 0.0),
+        day(2, 1.0, Some(14.0), 6.2),
+        day(3, -0.5, None, 0.4),
+        day(4, 2.0, Some(9.0), 1.0),
+    ];
+    let thresholds = Thresholds {
+        min_temp: 0.0,
+        max_wind: 10.0,
+        rain_within_hours: 6,
+        alerts: true,
+        ..Thresholds::default()
+    };
+
+    let fired = notify::backtest(&days, &thresholds);
+    let summary: Vec<(&str, usize, usize)> = fired
+        .iter()
+        .map(|rule| (rule.rule.as_str(), rule.days.len(), rule.judged))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("freezing", 2, 4),
+            ("windy", 1, 3),
+            ("rain", 2, 4),
+            ("alert", 0, 0)
+        ]
+    );
+    assert_eq!(fired[0].days.last(), Some(&days[2].date));
+
+    let quiet = Thresholds {
+        rain_within_hours: 0,
+        alerts: false,
+        ..thresholds
+    };
+    let rules: Vec<String> = notify::backtest(&days, &quiet)
+        .into_iter()
+        .map(|rule| rule.rule)
+        .collect();
+    assert_eq!(rules, ["freezing", "windy"]);
+
+    let mut custom = quiet.clone();
+    for (name, source) in [
+        ("wet_frost", "temp < 0 && precipitation > 0"),
+        ("muggy", "humidity > 90"),
+        ("squall", "wind > 12 for 2h"),
+        ("cold_spell", "temp < 2 for 2d"),
+    ] {
+        custom.rules.insert(name.to_string(), source.to_string());
+    }
+    let fired = notify::backtest(&days, &custom);
+    let rule = |name: &str| fired.iter().find(|rule| rule.rule == name).unwrap();
+    assert_eq!(rule("wet_frost").days, [days[2].date]);
+    assert_eq!(
+        rule("wet_frost").expr.as_deref(),
+        Some("temp < 0 && precipitation > 0")
+    );
+    assert_eq!(rule("cold_spell").days, [days[0].date, days[1].date]);
+    assert_eq!(rule("cold_spell").judged, 4);
+    for name in ["muggy", "squall"] {
+        assert!(!rule(name).replayable, "{}", name);
+        assert_eq!(rule(name).judged, 0);
+    }
+}
+
+#[test]
+fn rule_examples() {
+    use cli_weather::rule::{self, Sample};
+
+    let at = |hours: i64, wind: f64, temp: f64, rain: f64| Sample {
+        at: hours * 3600,
+        wind: Some(wind),
+        temp: Some(temp),
+        precipitation: Some(rain),
+        ..Sample::default()
+    };
+    let timeline = [
+        at(0, 13.0, 1.5, 0.2),
+        at(1, 14.0, 1.0, 0.0),
+        at(4, 9.0, 3.0, 0.0),
+    ];
+    let holds = |source: &str| rule::parse(source).unwrap().holds(&timeline);
+
+    assert!(holds("temp < 2 && precipitation > 0"));
+    assert!(holds("wind > 12 for 2h"));
+    assert!(holds("wind>12 FOR 90 min"));
+    assert!(!holds("wind > 12 for 5h"), "the wind drops after 4 hours");
+    assert!(!holds("wind > 12 for 1d"), "the timeline ends first");
+    assert!(holds("temp > 30 or wind > 12 and temp < 2"));
+    assert!(!holds("(temp > 30 or wind > 12) and temp > 2"));
+    assert!(holds("(temp < 2 && wind > 12) for 1h"));
+    assert!(!holds("gust > 0"), "unknown values never hold");
+    assert!(holds("temp <= -0.5 || humidity != -1 || temp >= 1.5"));
+    let late = [Sample {
+        at: i64::MAX - 60,
+        ..timeline[0]
+    }];
+    assert!(!rule::parse("wind > 12 for 1h").unwrap().holds(&late));
+
+    let forecast: ForecastResponse =
+        serde_json::from_str(include_str!("fixtures/forecast.json")).unwrap();
+    let step = Sample::forecast(&forecast.list[0]);
+    assert_eq!((step.feels_like, step.pressure), (Some(9.0), Some(1015.0)));
+    assert!(rule::parse("wind > 12 for 2h").unwrap().needs_forecast());
+    assert!(!rule::parse("temp < 2 && precipitation > 0")
+        .unwrap()
+        .needs_forecast());
+
+    let error = |source: &str| rule::parse(source).unwrap_err().to_string();
+    assert_eq!(
+        error("wind > 12 fro 2h"),
+        "expected &&, || or the end of the rule, but found 'fro' at column 11"
+    );
+    assert_eq!(error("win > 12"), "unknown field 'win' at column 1");
+    assert_eq!(
+        error("temp < "),
+        "expected a number, but the rule ends at column 8"
+    );
+    assert_eq!(
+        error("wind > 12 for 2 weeks"),
+        "expected a unit of m, h or d, but found 'weeks' at column 17"
+    );
+    assert_eq!(
+        error("(temp < 2 && wind > 3"),
+        "this '(' is never closed at column 1"
+    );
+    assert_eq!(error("temp = 2"), "unexpected '=' at column 6");
+    assert_eq!(
+        error("wind > 12 for 100000000000000000000000000000000h"),
+        "a duration can be at most 5 days at column 15"
+    );
+    assert_eq!(
+        error("temp 2"),
+        "expected <, <=, >, >=, == or != after temp, but found '2' at column 6"
+    );
+
+    let mut config = Config::default();
+    config
+        .notify
+        .rules
+        .insert(String::from("gale"), String::from("wind >> 12"));
+    let rendered = config.validate().unwrap_err().render();
+    assert!(rendered.contains("notify.rules.gale"), "{}", rendered);
+    assert!(rendered.contains("column 7"), "{}", rendered);
+}
+
+#[test]
+fn tour_examples() {
+    use cli_weather::tour::{self, Input, Step, Tour};
+
+    let units = Preferences::default();
+    let mut tour = Tour::new(units);
+    let answer = |tour: &mut Tour, input: Input| {
+        tour.advance(input);
+        tour.step.clone()
+    };
+
+    assert_eq!(answer(&mut tour, Input::Answer("maybe")), Step::Offer);
+    assert_eq!(answer(&mut tour, Input::Answer("")), Step::Place);
+    assert!(tour.question().is_some());
+    assert_eq!(
+        answer(&mut tour, Input::Answer(" Atlantis ")),
+        Step::Weather(String::from("Atlantis"))
+    );
+    assert_eq!(tour.question(), None);
+    // Not found, so asked again.
+    assert_eq!(answer(&mut tour, Input::Ran(false)), Step::Place);
+    answer(&mut tour, Input::Answer("Oslo, NO"));
+    // An answer while it is fetching changes nothing.
+    assert_eq!(
+        answer(&mut tour, Input::Answer("y")),
+        Step::Weather(String::from("Oslo, NO"))
+    );
+    assert_eq!(
+        answer(&mut tour, Input::Ran(true)),
+        Step::Forecast(String::from("Oslo, NO"))
+    );
+    assert_eq!(
+        answer(&mut tour, Input::Answer("yes")),
+        Step::Rain(String::from("Oslo, NO"))
+    );
+    assert_eq!(answer(&mut tour, Input::Ran(false)), Step::Units);
+    assert_eq!(answer(&mut tour, Input::Answer("kelvin")), Step::Units);
+    assert_eq!(
+        answer(&mut tour, Input::Answer("I")),
+        Step::Config(tour::IMPERIAL)
+    );
+    assert_eq!(
+        answer(&mut tour, Input::Answer("")),
+        Step::Write(tour::IMPERIAL)
+    );
+    assert_eq!(answer(&mut tour, Input::Ran(true)), Step::Done);
+
+    // Skipping the place and keeping the units.
+    let mut tour = Tour::new(units);
+    answer(&mut tour, Input::Answer("y"));
+    assert_eq!(answer(&mut tour, Input::Answer("")), Step::Units);
+    assert_eq!(answer(&mut tour, Input::Answer("")), Step::Config(units));
+    assert_eq!(answer(&mut tour, Input::Answer("n")), Step::Done);
+
+    let mut tour = Tour::new(units);
+    assert_eq!(answer(&mut tour, Input::Answer("No")), Step::Done);
+    assert_eq!(tour::yes("Y", false), Some(true));
+    assert_eq!(tour::yes("", false), Some(false));
+}
+
+#[test]
+fn severity_examples() {
+    use cli_weather::notify::{alert_severity, Severity, Trigger};
+    use cli_weather::push::Service;
+
+    assert_eq!(alert_severity("Red wind warning"), Severity::Severe);
+    assert_eq!(
+        alert_severity("Severe Thunderstorm Warning"),
+        Severity::Severe
+    );
+    assert_eq!(alert_severity("Yellow fog warning"), Severity::Info);
+    assert_eq!(alert_severity("Wind Advisory"), Severity::Info);
+    assert_eq!(
+        alert_severity("Orange thunderstorm warning"),
+        Severity::Warning
+    );
+    // Whole words only: "Redding" is not red.
+    assert_eq!(alert_severity("Redding flood watch"), Severity::Warning);
+
+    assert_eq!(Trigger::Windy(18.0).severity(), Severity::Warning);
+    let rule = Trigger::Rule {
+        name: String::from("gale"),
+        rule: String::from("wind > 12"),
+    };
+    assert_eq!(rule.severity(), Severity::Info);
+
+    for service in [Service::Gotify, Service::Ntfy] {
+        let priorities = [Severity::Info, Severity::Warning, Severity::Severe]
+            .map(|severity| service.priority(severity));
+        assert!(priorities.is_sorted() && priorities[0] < priorities[2]);
+    }
+    assert_eq!(Service::Ntfy.priority(Severity::Severe), 5);
+}
+
+#[test]
+fn tint_examples() {
+    use cli_weather::config::TintSettings;
+    use cli_weather::tint::{self, Tint};
+
+    let settings = TintSettings::default();
+    assert!(!settings.enabled);
+    assert_eq!(Tint::of(-2.0, &settings), Tint::Cold);
+    assert_eq!(Tint::of(5.0, &settings), Tint::Neutral);
+    assert_eq!(Tint::of(31.0, &settings), Tint::Hot);
+
+    assert_eq!(
+        tint::osc(Some("#141C2B")).unwrap(),
+        "\x1b]11;rgb:14/1c/2b\x07"
+    );
+    assert_eq!(
+        tint::osc(Tint::Neutral.color(&settings)).unwrap(),
+        "\x1b]111\x07"
+    );
+
+    let bad = TintSettings {
+        hot_color: String::from("amber"),
+        ..TintSettings::default()
+    };
+    assert_eq!(
+        tint::validate(&bad).unwrap_err(),
+        "tint.hot_color: 'amber' is not a #rrggbb color"
+    );
+}
+
+#[test]
+fn speech_examples() {
+    use cli_weather::api::WeatherResponse;
+    use cli_weather::speech::{self, Question};
+
+    let metric = Preferences {
+        temperature: TempUnit::Celsius,
+        wind: SpeedUnit::KilometersPerHour,
+        clock: Clock::TwentyFourHour,
+    };
+    let weather: WeatherResponse =
+        serde_json::from_str(include_str!("fixtures/weather.json")).unwrap();
+    let forecast: ForecastResponse =
+        serde_json::from_str(include_str!("fixtures/forecast.json")).unwrap();
+    let now = 1_712_761_200;
+
+    assert_eq!(
+        speech::current(Question::Weather, &weather, metric),
+        "In Paris it's 15 degrees with broken clouds, and a gentle breeze from the west-southwest."
+    );
+    assert_eq!(
+        speech::current(Question::Temperature, &weather, metric),
+        "It's 15 degrees in Paris right now, feeling like 14."
+    );
+    assert_eq!(speech::current(Question::Wind, &weather, metric), "The wind in Paris is 15 kilometers per hour from the west-southwest, a gentle breeze, with gusts up to 26 kilometers per hour.");
+    assert_eq!(
+        speech::ahead(Question::Rain, &forecast, now, metric),
+        "Light rain is expected in Paris around 23:00."
+    );
+    assert_eq!(speech::ahead(Question::Tomorrow, &forecast, now, metric), "Tomorrow in Paris: moderate rain, with a low of 10 and a high of 15 degrees. There's a 60 percent chance of rain.");
+    assert!(Question::Tomorrow.needs_forecast() && !Question::Wind.needs_forecast());
+}
+
+#[test]
+fn hooks_examples() {
+    use cli_weather::api::WeatherResponse;
+    use cli_weather::hooks::{self, Category};
+
+    let weather_with = |id: u32| -> WeatherResponse {
+        let mut weather: serde_json::Value =
+            serde_json::from_str(include_str!("fixtures/weather.json")).unwrap();
+        weather["weather"][0]["id"] = id.into();
+        serde_json::from_value(weather).unwrap()
+    };
+    assert_eq!(Category::of(&weather_with(211)), Some(Category::Storm));
+    assert_eq!(Category::of(&weather_with(311)), Some(Category::Rain));
+    assert_eq!(Category::of(&weather_with(800)), Some(Category::Clear));
+    assert_eq!(Category::of(&weather_with(803)), Some(Category::Cloudy));
+    assert_eq!(Category::of(&weather_with(42)), None);
+
+    if cfg!(unix) {
+        let check = r#"test "$CLI_WEATHER_PREVIOUS/$CLI_WEATHER_CONDITION" = clear/rain && grep -q '"name":"Paris"'"#;
+        let status = hooks::run(
+            check,
+            &weather_with(500),
+            Some(Category::Clear),
+            Category::Rain,
+        )
+        .unwrap()
+        .wait()
+        .unwrap();
+        assert!(status.success());
+    }
+}
+
+#[test]
+fn ical_import_examples() {
+    use ical::Start;
+
+    let calendar = "BEGIN:VCALENDAR\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:Lunch\\, then a walk\r\n\
+                    DTSTART;TZID=\"Europe/Paris\":20240410T120000\r\n\
+                    LOCATION:Café de Flore\\, 172 Bd Saint-Germain\\, 75006 \r\n \
+                    Paris\\, France\r\n\
+                    BEGIN:VALARM\r\n\
+                    SUMMARY:Reminder\r\n\
+                    END:VALARM\r\n\
+                    END:VEVENT\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:Flight\r\n\
+                    DTSTART:20240411T063000Z\r\n\
+                    END:VEVENT\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:No start\r\n\
+                    END:VEVENT\r\n\
+                    END:VCALENDAR\r\n";
+    let read = ical::read(calendar).unwrap();
+    assert_eq!(read.len(), 2);
+    assert_eq!(read[0].summary, "Lunch, then a walk");
+    assert_eq!(
+        read[0].location.as_deref(),
+        Some("Café de Flore, 172 Bd Saint-Germain, 75006 Paris, France")
+    );
+    let noon = NaiveDate::from_ymd_opt(2024, 4, 10)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap();
+    assert_eq!(read[0].start, Start::Local(noon));
+    // Local times are at the place's offset.
+    let paris = FixedOffset::east_opt(7200).unwrap();
+    assert_eq!(
+        read[0].start.at(paris),
+        Utc.with_ymd_and_hms(2024, 4, 10, 10, 0, 0).unwrap()
+    );
+    assert_eq!(read[1].location, None);
+    assert_eq!(
+        read[1].start,
+        Start::At(Utc.with_ymd_and_hms(2024, 4, 11, 6, 30, 0).unwrap())
+    );
+
+    assert_eq!(
+        ical::read("BEGIN:VEVENT\nDTSTART:tomorrow\nEND:VEVENT\n")
+            .unwrap_err()
+            .to_string(),
+        "line 2: 'tomorrow' is not a date or time"
+    );
+
+    assert_eq!(
+        ical::place_queries("Café de Flore, 172 Bd Saint-Germain, 75006 Paris, France"),
+        [
+            "Café de Flore, 172 Bd Saint-Germain, 75006 Paris, France",
+            "Paris, France",
+            "Bd Saint-Germain, France",
+            "Café de Flore, France",
+        ]
+    );
+    assert_eq!(ical::place_queries("Lyon"), ["Lyon"]);
+}
+
+#[test]
+fn access_examples() {
+    use cli_weather::access::{Clients, Denied, Scope};
+    use cli_weather::api::{Coord, Location};
+    use cli_weather::config::ClientSettings;
+
+    let paris = Location::Place {
+        city: String::from("paris"),
+        country_code: String::from("FR"),
+    };
+    let near = |lat, lon| Location::Coordinates(Coord { lat, lon });
+    assert!(Scope::parse("Paris, France").unwrap().allows(&paris));
+    assert!(!Scope::parse("Paris, US").unwrap().allows(&paris));
+    let home = Scope::parse("48.85,2.35").unwrap();
+    assert!(home.allows(&near(48.87, 2.33)));
+    assert!(!home.allows(&near(48.95, 2.35)));
+    assert!(!home.allows(&paris));
+    assert!(Scope::parse("Paris").is_err());
+
+    let client = |name: &str, token: &str| ClientSettings {
+        name: name.to_string(),
+        token: token.to_string(),
+        per_minute: 2,
+        places: vec![String::from("Paris, FR")],
+    };
+    assert!(Clients::new(&[client("a", "")]).is_err());
+    assert!(Clients::new(&[client("a", "x"), client("a", "y")]).is_err());
+    assert!(Clients::new(&[client("a", "x"), client("b", "x")]).is_err());
+    assert!(!Clients::new(&[]).unwrap().required());
+
+    let mut clients = Clients::new(&[client("kitchen", "k1"), client("phone", "p1")]).unwrap();
+    assert_eq!(clients.admit(None, 0).unwrap_err(), Denied::Missing);
+    assert_eq!(clients.admit(Some("k2"), 0).unwrap_err(), Denied::Unknown);
+    assert!(clients.admit(Some("k1"), 0).unwrap().allows(&paris));
+    assert!(!clients
+        .admit(Some("k1"), 10)
+        .unwrap()
+        .allows(&near(0.0, 0.0)));
+    assert_eq!(
+        clients.admit(Some("k1"), 45).unwrap_err(),
+        Denied::RateLimited {
+            client: String::from("kitchen"),
+            retry_after: 15
+        }
+    );
+    // Each client has its own limit, and a new minute starts afresh.
+    assert_eq!(clients.admit(Some("p1"), 50).unwrap().name, "phone");
+    assert_eq!(clients.admit(Some("k1"), 60).unwrap().name, "kitchen");
+}